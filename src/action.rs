@@ -14,10 +14,10 @@ use strum::Display;
 
 use crate::{
     mode::Mode as AppMode,
-    models::{RadioStation, SearchParam},
+    models::{DirectoryCheck, RadioStation, SearchParam, SongEntry, StreamCheck},
 };
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Display, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Display, Deserialize)]
 pub enum Action {
     /// Represents a periodic tick event, typically used for UI updates.
     Tick,
@@ -35,10 +35,24 @@ pub enum Action {
     Refresh,
     /// Represents an error event with an associated message.
     Error(String),
+    /// Shows a short informational message in the footer.
+    Notify(String),
     /// Represents a request to show help information.
     Help,
     /// Toggles the visibility of the help UI.
     ToggleShowHelp,
+    /// Toggles the details pane of the selected station.
+    ToggleStationDetails,
+    /// Sorts the stations by the next column.
+    CycleSortColumn,
+    /// Flips the direction of the current sort.
+    ReverseSort,
+    /// Opens the prompt to filter the stations list.
+    EnterFilter,
+    /// Closes the filter prompt, keeping the filter.
+    ApplyFilter,
+    /// Removes the filter and shows all stations again.
+    ClearFilter,
     /// Moves to the next item in a list or menu.
     NextItem,
     /// Moves to the previous item in a list or menu.
@@ -55,18 +69,114 @@ pub enum Action {
     Update,
     /// Initiates a search with the given search parameters.
     Search(Vec<SearchParam>),
-    /// Indicates that a list of radio stations has been found.
-    StationsFound(Vec<RadioStation>),
+    /// Cancels the in-flight station search, if any.
+    CancelSearch,
+    /// Indicates that the search with the given id found a list of radio stations.
+    StationsFound(u64, Vec<RadioStation>),
+    /// Indicates that the search with the given id failed or timed out.
+    SearchFailed(u64, String),
     /// Requests playback of the currently selected radio station.
     PlaySelectedStation,
     /// Requests stopping playback of the current radio station.
     StopPlayingStation,
+    /// Upvotes the selected station in the directory.
+    VoteSelectedStation,
+    /// Indicates that the directory accepted a vote for the station with the given uuid.
+    VoteRegistered(String),
+    /// Indicates that the vote for the station with the given uuid failed, with the reason.
+    VoteFailed(String, String),
+    /// Adds the selected station to the favorites, or removes it.
+    ToggleFavorite,
+    /// Moves the selected item one place up in a manually ordered list.
+    MoveItemUp,
+    /// Moves the selected item one place down in a manually ordered list.
+    MoveItemDown,
+    /// Switches Home to its next view.
+    NextView,
+    /// Shows the search results in Home.
+    ShowResults,
+    /// Shows the favorites in Home.
+    ShowFavorites,
+    /// Shows the listening history in Home.
+    ShowHistory,
+    /// Forgets all stations played.
+    ClearHistory,
+    /// Indicates that the station with the given uuid could not be played, with the reason.
+    PlaybackFailed(String, String),
+    /// Indicates that a station announced a new track.
+    TrackChanged(SongEntry),
+    /// Shows or hides the song log.
+    ToggleSongLog,
+    /// Exports the songs shown in the song log as CSV.
+    ExportSongLogCsv,
+    /// Exports the songs shown in the song log as JSON.
+    ExportSongLogJson,
+    /// Switches to the collections.
+    ShowCollections,
+    /// Asks for the name of a new collection.
+    NewCollection,
+    /// Asks for a new name for the selected collection.
+    RenameCollection,
+    /// Deletes the selected collection, or removes the selected station from its collection.
+    DeleteSelected,
+    /// Asks which collection to add the selected station to.
+    AddToCollection,
+    /// Asks for a stream url to add to the selected collection.
+    AddUrlToCollection,
+    /// Asks for a file to export the current list, or the selected collection, to.
+    ExportList,
+    /// Asks for a playlist file to import into a collection.
+    ImportPlaylist,
+    /// Indicates that the stations of a playlist were read, with the collection they go to and
+    /// how many were found in the directory.
+    PlaylistImported(String, Vec<RadioStation>, usize),
+    /// Asks for a stream or playlist url to play.
+    OpenUrl,
+    /// Indicates that an url was resolved to a station that can be played.
+    UrlOpened(Box<RadioStation>),
+    /// Indicates that the stations of the listing at the url were fetched.
+    ListingFetched(String, Vec<RadioStation>),
+    /// Indicates that the listing at the url could not be fetched, with the reason.
+    ListingFailed(String, String),
+    /// Downloads the directory, or the changes since the last sync, for searching offline.
+    SyncDirectory,
+    /// Indicates that the local copy of the directory was synced, with what changed.
+    DirectorySynced(String),
+    /// Indicates that syncing the directory failed, with the reason.
+    SyncFailed(String),
+    /// Looks up the saved stations in the directory to bring them up to date.
+    RefreshSaved,
+    /// Indicates that the saved stations were looked up, with the `stationuuid`s asked for and
+    /// the stations the directory still lists.
+    SavedStationsFetched(Vec<String>, Vec<RadioStation>),
+    /// Indicates that the directory's checks of the station with the `stationuuid` were fetched.
+    StationChecksFetched(String, Vec<DirectoryCheck>),
+    /// Indicates that the checks of the station could not be fetched, with the reason.
+    StationChecksFailed(String, String),
+    /// Shows every search result, or hides the duplicate and failing ones again.
+    ToggleAllResults,
+    /// Checks that the streams of the stations in the list play.
+    CheckStreams,
+    /// Indicates that the streams were checked.
+    StreamsChecked(Vec<StreamCheck>),
+    /// Removes the stations marked broken by a check from the favorites or the collection.
+    RemoveBroken,
+    /// Accepts the answer to the open prompt.
+    ConfirmPrompt,
+    /// Closes the open prompt without acting on it.
+    CancelPrompt,
     /// Updates the application's mode.
     Mode(AppMode),
     /// Switches the application to search mode.
     SearchMode,
     /// Switches the application to home mode.
     HomeMode,
+    /// Toggles searching as you type in the search form.
+    ToggleLiveSearch,
+    /// Asks for a name to save the search form under.
+    SaveSearch,
+    /// Shows or hides the saved searches in the search form.
+    ToggleSavedSearches,
     /// Increases the audio volume.
     IncreaseVolume,
     /// Decreases the audio volume.
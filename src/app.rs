@@ -6,7 +6,7 @@ use tokio::sync::mpsc;
 
 use crate::{
     action::Action,
-    components::{fps::FpsCounter, home::Home, search::Search, Component},
+    components::{fps::FpsCounter, home::Home, search::Search, song_log::SongLogPanel, Component},
     config::Config,
     mode::Mode,
     tui,
@@ -47,15 +47,21 @@ impl App {
     ///
     /// Returns a [`Result`] containing the initialized [`App`] instance on success.
     pub async fn new(tick_rate: f64, frame_rate: f64) -> Result<Self> {
-        let home = Home::new().await?;
+        let config = Config::new()?;
+        let home = Home::new(&config).await?;
         let fps = FpsCounter::default();
         let search = Search::default();
-        let config = Config::new()?;
+        let song_log = SongLogPanel::new()?;
         let mode = Mode::Home;
         Ok(Self {
             tick_rate,
             frame_rate,
-            components: vec![Box::new(home), Box::new(search), Box::new(fps)],
+            components: vec![
+                Box::new(home),
+                Box::new(search),
+                Box::new(song_log),
+                Box::new(fps),
+            ],
             should_quit: false,
             should_suspend: false,
             config,
@@ -1,8 +1,8 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
-use crate::utils::version;
+use crate::{models::PlaylistFormat, utils::version};
 
 /// Command-line interface options for the application.
 ///
@@ -29,4 +29,65 @@ pub struct Cli {
         default_value_t = 4.0
     )]
     pub frame_rate: f64,
+
+    /// Runs a command instead of the TUI.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Commands that run without starting the TUI.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Imports the stations of a playlist into a collection or the favorites.
+    Import {
+        /// The M3U, PLS, XSPF or JSON playlist to read.
+        path: PathBuf,
+        /// The playlist format, guessed from the contents if not given.
+        #[arg(short, long, value_name = "FORMAT")]
+        format: Option<PlaylistFormat>,
+        /// The collection to add the stations to, named after the file if not given.
+        #[arg(short, long, value_name = "NAME", conflicts_with = "favorites")]
+        collection: Option<String>,
+        /// Adds the stations to the favorites instead of a collection.
+        #[arg(long)]
+        favorites: bool,
+        /// Keeps the stations as read instead of looking up their urls in the directory.
+        #[arg(long)]
+        offline: bool,
+    },
+    /// Exports the favorites or a collection as a playlist.
+    Export {
+        /// The file to write.
+        path: PathBuf,
+        /// The playlist format, taken from the file extension if not given.
+        #[arg(short, long, value_name = "FORMAT")]
+        format: Option<PlaylistFormat>,
+        /// The collection to export instead of the favorites.
+        #[arg(short, long, value_name = "NAME")]
+        collection: Option<String>,
+    },
+    /// Checks that the streams of the favorites, a collection or a playlist play.
+    Check {
+        /// The M3U, PLS, XSPF or JSON playlist to check instead of the favorites.
+        #[arg(conflicts_with_all = ["collection", "mark", "remove"])]
+        path: Option<PathBuf>,
+        /// The collection to check instead of the favorites.
+        #[arg(short, long, value_name = "NAME")]
+        collection: Option<String>,
+        /// How many streams are checked at the same time, from the config if not given.
+        #[arg(short = 'j', long, value_name = "N")]
+        concurrency: Option<usize>,
+        /// Marks the broken stations, and clears the mark of the ones that play again.
+        #[arg(long)]
+        mark: bool,
+        /// Removes the broken stations.
+        #[arg(long, conflicts_with = "mark")]
+        remove: bool,
+    },
+    /// Downloads the directory, or the changes since the last sync, to search it offline.
+    Sync {
+        /// Downloads every station again, which also drops the ones deleted from the directory.
+        #[arg(long)]
+        full: bool,
+    },
 }
@@ -0,0 +1,285 @@
+//! The commands of [`crate::cli::Command`], which print their result and exit.
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use color_eyre::eyre::{eyre, Result};
+
+use crate::{
+    cli::Command,
+    config::Config,
+    errors::Error,
+    models::{
+        check_streams, report_table, BrokenMarks, Collections, Favorites, Mirror, PlaylistFormat,
+        RadioApi, RadioStation, ResponseCache,
+    },
+    utils::{get_data_dir, load_or_set_aside, write_atomic},
+};
+
+pub async fn run(command: Command) -> Result<()> {
+    let config = Config::new()?;
+    match command {
+        Command::Import {
+            path,
+            format,
+            collection,
+            favorites,
+            offline,
+        } => {
+            let target = match (favorites, collection) {
+                (true, _) => None,
+                (false, Some(name)) => Some(name),
+                (false, None) => Some(playlist_name(&path)),
+            };
+            import(&config, &path, format, target, offline).await
+        }
+        Command::Export {
+            path,
+            format,
+            collection,
+        } => export(&path, format, collection.as_deref()),
+        Command::Check {
+            path,
+            collection,
+            concurrency,
+            mark,
+            remove,
+        } => {
+            let list = match (path, collection) {
+                (Some(path), _) => CheckedList::Playlist(path),
+                (None, Some(name)) => CheckedList::Collection(name),
+                (None, None) => CheckedList::Favorites,
+            };
+            let fix = match (mark, remove) {
+                (true, _) => Some(BrokenFix::Mark),
+                (_, true) => Some(BrokenFix::Remove),
+                _ => None,
+            };
+            check(&config, list, concurrency, fix).await
+        }
+        Command::Sync { full } => sync(&config, full).await,
+    }
+}
+
+/// Connects to the configured directory, answering from the response cache if it is enabled.
+///
+/// A cache file that cannot be parsed is set aside and the cache starts empty. One that cannot
+/// be used otherwise, e.g. because a newer version wrote it, is left alone and the directory is
+/// asked without a cache.
+pub fn radio_api(config: &Config) -> Result<Arc<RadioApi>, Error> {
+    let mut api = RadioApi::new(&config.directory.api_url)?;
+    if config.cache.enabled {
+        let path = get_data_dir().join(ResponseCache::FILE_NAME);
+        let limits = config.cache.limits();
+        match load_or_set_aside(&path, |path| ResponseCache::load(path, limits)) {
+            Ok((cache, error)) => {
+                if let Some(error) = error {
+                    tracing::warn!(%error, "failed to parse the response cache, starting empty");
+                }
+                api = api.with_cache(cache);
+            }
+            Err(e) => {
+                tracing::warn!(error = ?e, "failed to load the response cache, not caching");
+            }
+        }
+    }
+    Ok(Arc::new(api))
+}
+
+/// The collection name for a playlist imported without one: its file name without extension.
+pub fn playlist_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Imported".to_string())
+}
+
+/// Reads a playlist, matching its stations with the directory if an `api` is given.
+///
+/// Returns the stations and how many of them were found in the directory.
+pub async fn read_playlist(
+    api: Option<&Arc<RadioApi>>,
+    path: &Path,
+    format: Option<PlaylistFormat>,
+) -> Result<(Vec<RadioStation>, usize)> {
+    let contents = tokio::fs::read_to_string(path).await?;
+    let mut stations = PlaylistFormat::import(&contents, format)?;
+    let matched = match api {
+        Some(api) => api.match_stations(&mut stations).await,
+        None => 0,
+    };
+    Ok((stations, matched))
+}
+
+/// Adds the stations of the playlist to the collection, or to the favorites if `collection` is
+/// `None`.
+async fn import(
+    config: &Config,
+    path: &Path,
+    format: Option<PlaylistFormat>,
+    collection: Option<String>,
+    offline: bool,
+) -> Result<()> {
+    let api = match offline {
+        true => None,
+        false => Some(radio_api(config)?),
+    };
+    let (stations, matched) = read_playlist(api.as_ref(), path, format).await?;
+    let count = stations.len();
+    let target = match collection {
+        Some(name) => {
+            let mut collections = Collections::load(&get_data_dir().join(Collections::FILE_NAME))?;
+            collections.add_stations(&name, stations)?;
+            name
+        }
+        None => {
+            let mut favorites = Favorites::load(&get_data_dir().join(Favorites::FILE_NAME))?;
+            let added = favorites.extend(stations)?;
+            println!("{} stations were favorites already", count - added);
+            "favorites".to_string()
+        }
+    };
+    println!("Imported {count} stations into {target}, {matched} found in the directory");
+    Ok(())
+}
+
+/// Writes the favorites, or the collection, to `path`.
+fn export(path: &Path, format: Option<PlaylistFormat>, collection: Option<&str>) -> Result<()> {
+    let format = format
+        .or_else(|| PlaylistFormat::from_path(path))
+        .unwrap_or(PlaylistFormat::ExtM3u);
+    let stations = match collection {
+        Some(name) => {
+            let collections = Collections::load(&get_data_dir().join(Collections::FILE_NAME))?;
+            collections
+                .get(name)
+                .ok_or_else(|| eyre!("there is no collection named {name}"))?
+                .stations
+                .clone()
+        }
+        None => Favorites::load(&get_data_dir().join(Favorites::FILE_NAME))?
+            .stations()
+            .to_vec(),
+    };
+    write_atomic(path, format.export(&stations)?.as_bytes())?;
+    println!(
+        "Exported {} stations to {} as {format}",
+        stations.len(),
+        path.display()
+    );
+    match PlaylistFormat::headers_left_out(&stations) {
+        0 => (),
+        n => println!("Left out the request headers of {n} stations, they may hold credentials"),
+    }
+    Ok(())
+}
+
+/// The stations checked by [`check`].
+enum CheckedList {
+    Favorites,
+    Collection(String),
+    Playlist(PathBuf),
+}
+
+/// What [`check`] does with the broken stations.
+enum BrokenFix {
+    Mark,
+    Remove,
+}
+
+/// Checks that the streams of the list play and prints a report, marking or removing the broken
+/// stations of the favorites or the collection if asked to.
+async fn check(
+    config: &Config,
+    list: CheckedList,
+    concurrency: Option<usize>,
+    fix: Option<BrokenFix>,
+) -> Result<()> {
+    let favorites_path = get_data_dir().join(Favorites::FILE_NAME);
+    let collections_path = get_data_dir().join(Collections::FILE_NAME);
+    let stations = match &list {
+        CheckedList::Favorites => Favorites::load(&favorites_path)?.stations().to_vec(),
+        CheckedList::Collection(name) => Collections::load(&collections_path)?
+            .get(name)
+            .ok_or_else(|| eyre!("there is no collection named {name}"))?
+            .stations
+            .clone(),
+        CheckedList::Playlist(path) => read_playlist(None, path, None).await?.0,
+    };
+    let concurrency = concurrency.unwrap_or(config.check.concurrency);
+    println!(
+        "Checking {} streams, {concurrency} at a time...",
+        stations.len()
+    );
+    let checks = check_streams(
+        &reqwest::Client::new(),
+        stations,
+        concurrency,
+        config.check.timeout(),
+    )
+    .await;
+    for line in report_table(&checks) {
+        println!("{line}");
+    }
+    let broken = checks.iter().filter(|check| !check.is_ok()).count();
+    println!("{} play, {broken} broken", checks.len() - broken);
+
+    let Some(fix) = fix else {
+        return Ok(());
+    };
+    let marks = BrokenMarks::new(&checks);
+    let is_broken = |station: &RadioStation| {
+        checks
+            .iter()
+            .any(|check| !check.is_ok() && check.station.stationuuid == station.stationuuid)
+    };
+    match (fix, &list) {
+        (BrokenFix::Mark, CheckedList::Favorites) => {
+            let mut changed = 0;
+            Favorites::load(&favorites_path)?.update_stations(|s| {
+                let marked = marks.apply(s);
+                changed += usize::from(marked);
+                marked
+            })?;
+            println!("Updated the broken mark of {changed} favorites");
+        }
+        (BrokenFix::Mark, CheckedList::Collection(name)) => {
+            let changed = Collections::load(&collections_path)?
+                .update_collection(name, |s| marks.apply(s))?;
+            println!("Updated the broken mark of {changed} stations of {name}");
+        }
+        (BrokenFix::Remove, CheckedList::Favorites) => {
+            let removed = Favorites::load(&favorites_path)?.retain(|s| !is_broken(s))?;
+            println!("Removed {removed} favorites");
+        }
+        (BrokenFix::Remove, CheckedList::Collection(name)) => {
+            let removed =
+                Collections::load(&collections_path)?.retain_stations(name, |s| !is_broken(s))?;
+            println!("Removed {removed} stations from {name}");
+        }
+        (_, CheckedList::Playlist(_)) => {
+            return Err(eyre!(
+                "the stations of a playlist cannot be marked or removed"
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Brings the local copy of the directory up to date.
+async fn sync(config: &Config, full: bool) -> Result<()> {
+    let api = radio_api(config)?;
+    let mut mirror = Mirror::load(&get_data_dir().join(Mirror::FILE_NAME))?;
+    println!(
+        "Downloading {} from {}...",
+        match full || mirror.is_empty() {
+            true => "the directory",
+            false => "the changes since the last sync",
+        },
+        config.directory.api_url
+    );
+    let report = api.sync_mirror(&mut mirror, full).await?;
+    mirror.save()?;
+    println!("Synced {report}");
+    Ok(())
+}
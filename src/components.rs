@@ -9,9 +9,13 @@ use crate::{
     tui::{Event, Frame},
 };
 
+pub mod collection_tree;
 pub mod fps;
+pub mod fuzzy;
 pub mod home;
 pub mod search;
+pub mod song_log;
+pub mod station_table;
 
 /// `Component` is a trait that represents a visual and interactive element of the user interface.
 /// Implementors of this trait can be registered with the main application loop and will be able to receive events,
@@ -82,6 +86,7 @@ pub trait Component {
         let r = match event {
             Some(Event::Key(key_event)) => self.handle_key_events(key_event)?,
             Some(Event::Mouse(mouse_event)) => self.handle_mouse_events(mouse_event)?,
+            Some(Event::Paste(text)) => self.handle_paste_events(text)?,
             _ => None,
         };
         Ok(r)
@@ -126,6 +131,26 @@ pub trait Component {
     fn handle_mouse_events(&mut self, mouse: MouseEvent) -> Result<Option<Action>> {
         Ok(None)
     }
+    /// Handles pasted text and produces actions if necessary.
+    ///
+    /// This method processes text pasted into the terminal and may return an [`Action`] to be handled by the application.
+    /// Override this method to accept pasted text, e.g. in an input field.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The pasted text.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if paste handling fails.
+    ///
+    /// # Returns
+    ///
+    /// Returns an [`Option<Action>`] to be processed, or `None` if no action is produced.
+    #[allow(unused_variables)]
+    fn handle_paste_events(&mut self, text: String) -> Result<Option<Action>> {
+        Ok(None)
+    }
     /// Updates the state of the component based on a received action. (REQUIRED)
     ///
     /// This method processes the provided [`Action`] and may update the component's state or produce a new action.
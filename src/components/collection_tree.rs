@@ -0,0 +1,215 @@
+//! The collections of [`super::home::Home`] as a tree of collections and their stations.
+use std::collections::HashSet;
+
+use ratatui::{
+    style::{palette::tailwind, Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Cell, Row, TableState},
+};
+
+use super::station_table::FAVORITE_MARKER;
+use crate::models::{Collections, RadioStation};
+
+const NORMAL_ROW_COLOR: Color = tailwind::SLATE.c950;
+const ALT_ROW_COLOR: Color = tailwind::SLATE.c900;
+const TEXT_COLOR: Color = tailwind::SLATE.c200;
+const COLLECTION_COLOR: Color = tailwind::BLUE.c300;
+const FAVORITE_COLOR: Color = tailwind::YELLOW.c400;
+
+/// A row of the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeNode {
+    /// The collection at this index.
+    Collection(usize),
+    /// The station at the second index of the collection at the first index.
+    Station(usize, usize),
+}
+
+#[derive(Debug, Default)]
+pub struct CollectionTree {
+    pub state: TableState,
+    /// The names of the collections whose stations are shown.
+    expanded: HashSet<String>,
+}
+
+impl CollectionTree {
+    /// The rows of the tree, collections followed by their stations if expanded.
+    pub fn nodes(&self, collections: &Collections) -> Vec<TreeNode> {
+        let mut nodes = Vec::new();
+        for (i, collection) in collections.collections().iter().enumerate() {
+            nodes.push(TreeNode::Collection(i));
+            if self.expanded.contains(&collection.name) {
+                nodes.extend((0..collection.stations.len()).map(|j| TreeNode::Station(i, j)));
+            }
+        }
+        nodes
+    }
+
+    pub fn selected(&self, collections: &Collections) -> Option<TreeNode> {
+        self.state
+            .selected()
+            .and_then(|i| self.nodes(collections).get(i).copied())
+    }
+
+    pub fn selected_station<'a>(&self, collections: &'a Collections) -> Option<&'a RadioStation> {
+        match self.selected(collections)? {
+            TreeNode::Station(i, j) => collections.collections()[i].stations.get(j),
+            TreeNode::Collection(_) => None,
+        }
+    }
+
+    /// The name of the selected collection, or of the collection of the selected station.
+    pub fn selected_collection<'a>(&self, collections: &'a Collections) -> Option<&'a str> {
+        let (TreeNode::Collection(i) | TreeNode::Station(i, _)) = self.selected(collections)?;
+        Some(collections.collections()[i].name.as_str())
+    }
+
+    pub fn select(&mut self, collections: &Collections, node: TreeNode) {
+        let index = self.nodes(collections).iter().position(|n| *n == node);
+        self.state.select(index);
+    }
+
+    /// Keeps the selection within the tree after collections or stations were removed.
+    pub fn clamp(&mut self, collections: &Collections) {
+        let len = self.nodes(collections).len();
+        let selected = self.state.selected().map(|i| i.min(len.saturating_sub(1)));
+        self.state.select(selected.filter(|_| len > 0));
+    }
+
+    pub fn toggle(&mut self, name: &str) {
+        if !self.expanded.remove(name) {
+            self.expanded.insert(name.to_string());
+        }
+    }
+
+    pub fn expand(&mut self, name: &str) {
+        self.expanded.insert(name.to_string());
+    }
+
+    /// Follows a renamed collection so it stays expanded.
+    pub fn rename(&mut self, name: &str, new_name: &str) {
+        if self.expanded.remove(name) {
+            self.expanded.insert(new_name.to_string());
+        }
+    }
+
+    pub fn next(&mut self, collections: &Collections) {
+        let len = self.nodes(collections).len();
+        if len > 0 {
+            let i = self.state.selected().map_or(0, |i| (i + 1) % len);
+            self.state.select(Some(i));
+        }
+    }
+
+    pub fn previous(&mut self, collections: &Collections) {
+        let len = self.nodes(collections).len();
+        if len > 0 {
+            let i = self.state.selected().map_or(0, |i| (i + len - 1) % len);
+            self.state.select(Some(i));
+        }
+    }
+
+    pub fn rows(
+        &self,
+        collections: &Collections,
+        is_favorite: impl Fn(&RadioStation) -> bool,
+    ) -> Vec<Row<'static>> {
+        self.nodes(collections)
+            .into_iter()
+            .enumerate()
+            .map(|(row_index, node)| {
+                let bg_color = match row_index % 2 {
+                    0 => NORMAL_ROW_COLOR,
+                    _ => ALT_ROW_COLOR,
+                };
+                let line = match node {
+                    TreeNode::Collection(i) => {
+                        let collection = &collections.collections()[i];
+                        let arrow = if self.expanded.contains(&collection.name) {
+                            "▾ "
+                        } else {
+                            "▸ "
+                        };
+                        Line::from(vec![
+                            Span::raw(arrow),
+                            Span::styled(
+                                collection.name.clone(),
+                                Style::default()
+                                    .fg(COLLECTION_COLOR)
+                                    .add_modifier(Modifier::BOLD),
+                            ),
+                            Span::styled(
+                                format!(" ({})", collection.stations.len()),
+                                Style::default().fg(Color::DarkGray),
+                            ),
+                        ])
+                    }
+                    TreeNode::Station(i, j) => {
+                        let station = &collections.collections()[i].stations[j];
+                        let mut spans = vec![Span::raw("    ")];
+                        if is_favorite(station) {
+                            spans.push(Span::styled(
+                                FAVORITE_MARKER,
+                                Style::default().fg(FAVORITE_COLOR),
+                            ));
+                        }
+                        spans.push(Span::raw(station.name.trim().to_string()));
+                        if station.is_custom() {
+                            spans.push(Span::styled(
+                                format!("  {}", station.url),
+                                Style::default().fg(Color::DarkGray),
+                            ));
+                        }
+                        Line::from(spans)
+                    }
+                };
+                Row::new(vec![Cell::from(line)]).fg(TEXT_COLOR).bg(bg_color)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::errors::Error;
+
+    #[test]
+    fn test_nodes_follow_expansion() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(Collections::FILE_NAME);
+        let mut collections = Collections::load(&path)?;
+        collections.add_station("Jazz", RadioStation::custom("http://a", "a"))?;
+        collections.add_station("Jazz", RadioStation::custom("http://b", "b"))?;
+        collections.create("Rock")?;
+
+        let mut tree = CollectionTree::default();
+        assert_eq!(
+            tree.nodes(&collections),
+            vec![TreeNode::Collection(0), TreeNode::Collection(1)]
+        );
+
+        tree.toggle("Jazz");
+        assert_eq!(
+            tree.nodes(&collections),
+            vec![
+                TreeNode::Collection(0),
+                TreeNode::Station(0, 0),
+                TreeNode::Station(0, 1),
+                TreeNode::Collection(1),
+            ]
+        );
+
+        tree.select(&collections, TreeNode::Station(0, 1));
+        assert_eq!(tree.selected_station(&collections).unwrap().name, "b");
+        assert_eq!(tree.selected_collection(&collections), Some("Jazz"));
+
+        tree.rename("Jazz", "Blues");
+        collections.rename("Jazz", "Blues")?;
+        assert_eq!(tree.nodes(&collections).len(), 4);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+//! A small fuzzy matcher used to filter the stations table without a network round trip.
+
+/// The result of a successful [`fuzzy_match`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    /// Higher is better.
+    pub score: i64,
+    /// Character (not byte) indices of the matched characters in the text.
+    pub indices: Vec<usize>,
+}
+
+const MATCH_SCORE: i64 = 1;
+const CONSECUTIVE_BONUS: i64 = 4;
+const WORD_START_BONUS: i64 = 3;
+
+/// Matches `pattern` against `text` as a case-insensitive subsequence.
+///
+/// Whitespace in the pattern is ignored. Returns `None` if not every pattern character could be
+/// found in order, an empty pattern matches everything with a score of `0`.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<FuzzyMatch> {
+    let pattern: Vec<char> = pattern
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    let mut result = FuzzyMatch::default();
+    if pattern.is_empty() {
+        return Some(result);
+    }
+
+    let mut needle = pattern.iter().peekable();
+    let mut previous: Option<char> = None;
+    for (i, c) in text.chars().enumerate() {
+        let Some(wanted) = needle.peek() else {
+            break;
+        };
+        if c.to_lowercase().eq(std::iter::once(**wanted)) {
+            result.score += MATCH_SCORE;
+            if result.indices.last().is_some_and(|last| last + 1 == i) {
+                result.score += CONSECUTIVE_BONUS;
+            }
+            if previous.is_none_or(|p| !p.is_alphanumeric()) {
+                result.score += WORD_START_BONUS;
+            }
+            result.indices.push(i);
+            needle.next();
+        }
+        previous = Some(c);
+    }
+
+    needle.peek().is_none().then_some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_empty_pattern_matches() {
+        assert_eq!(fuzzy_match("", "anything"), Some(FuzzyMatch::default()));
+        assert_eq!(fuzzy_match("  ", ""), Some(FuzzyMatch::default()));
+    }
+
+    #[test]
+    fn test_subsequence() {
+        let m = fuzzy_match("jzz", "Smooth Jazz").unwrap();
+        assert_eq!(m.indices, vec![7, 9, 10]);
+        assert!(fuzzy_match("zzj", "Smooth Jazz").is_none());
+    }
+
+    #[test]
+    fn test_case_and_whitespace_insensitive() {
+        assert!(fuzzy_match("SMOOTH JAZZ", "smoothjazz").is_some());
+    }
+
+    #[test]
+    fn test_consecutive_and_word_start_score_higher() {
+        let tight = fuzzy_match("jazz", "Jazz FM").unwrap();
+        let loose = fuzzy_match("jazz", "Just a zen zone").unwrap();
+        assert!(tight.score > loose.score);
+    }
+
+    #[test]
+    fn test_indices_are_characters() {
+        let m = fuzzy_match("ö3", "Ö3 Hitradio").unwrap();
+        assert_eq!(m.indices, vec![0, 1]);
+    }
+}
@@ -1,30 +1,57 @@
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
 
 use color_eyre::eyre::Result;
+use futures::FutureExt;
 use ratatui::{
     crossterm::event::{KeyCode, KeyEvent},
     layout::{Alignment, Constraint, Layout, Margin, Rect},
     style::{palette::tailwind, Color, Modifier, Style, Stylize},
     text::{Line, Span},
     widgets::{
-        block, Block, BorderType, Borders, Clear, HighlightSpacing, List, ListItem, ListState,
-        Paragraph, Row, Table,
+        block, Block, BorderType, Borders, Cell, Clear, HighlightSpacing, List, ListItem,
+        ListState, Paragraph, Row, Table, TableState,
     },
     Frame,
 };
+use serde::{Deserialize, Serialize};
 use tokio::{
-    sync::{broadcast, mpsc::UnboundedSender, oneshot},
+    sync::{
+        broadcast,
+        mpsc::{self, UnboundedSender},
+        oneshot,
+    },
     task::JoinHandle,
 };
 use tracing::{error, trace};
-use tui_input::{backend::crossterm::EventHandler, Input};
+use tui_input::{backend::crossterm::EventHandler, Input, InputRequest};
 
-use super::Component;
+use super::{
+    collection_tree::{CollectionTree, TreeNode},
+    station_table::{self, SortOrder, StationColumn, StationsList},
+    Component,
+};
 use crate::{
     action::Action,
-    config::key_event_to_string,
+    commands,
+    config::{key_event_to_string, Config},
     errors::Error,
-    models::{RadioApi, RadioStation, SearchParam, State},
+    mode::Mode as AppMode,
+    models::{
+        alternates_details, check_streams, fetch_listing, group_duplicates, is_failing,
+        report_table, resolve_url, BrokenMarks, CheckHistories, CheckHistory, Collections,
+        CustomStations, DirectoryCheck, EndReason, Favorites, History, HistoryEntry, Listings,
+        Mirror, PlaylistFormat, RadioApi, RadioStation, RefreshReport, SearchParam, Session,
+        SongEntry, State, StationRefresh, StreamCheck, VoteLog,
+    },
+    utils::{
+        format_age, format_duration, get_config_dir, get_data_dir, load_or_set_aside, unix_now,
+        write_atomic,
+    },
 };
 
 const TODO_HEADER_BG: Color = tailwind::BLUE.c950;
@@ -38,8 +65,20 @@ pub(crate) const VOLUME_MIN: f32 = 0.0;
 pub(crate) const VOLUME_MAX: f32 = 1.0;
 const VOLUME_INCREMENT: f32 = 0.05;
 
+/// How long a notification or error stays in the footer.
+const MESSAGE_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often the stations file is looked at for changes.
+const CUSTOM_STATIONS_INTERVAL: Duration = Duration::from_secs(2);
+/// How often the listings are looked at for ones due a refresh.
+const LISTINGS_INTERVAL: Duration = Duration::from_secs(60);
+
 pub struct StreamState {
     station: RadioStation,
+    /// Seconds since the unix epoch.
+    started_at: u64,
+    started: Instant,
+    /// The title of the track currently playing, if the station announces it.
+    track: Option<String>,
     stream_handle: JoinHandle<()>,
     shutdown_tx: broadcast::Sender<()>,
 }
@@ -64,79 +103,180 @@ impl StreamState {
     }
 }
 
-#[derive(Default, Copy, Clone, PartialEq, Eq)]
-pub enum Mode {
+/// The progress of the most recent station search, shown in the list title.
+#[derive(Debug, Default)]
+pub enum SearchStatus {
     #[default]
-    Normal,
-    Insert,
-    Processing,
+    Idle,
+    InFlight {
+        id: u64,
+        started: Instant,
+        handle: JoinHandle<()>,
+    },
+    Found(usize),
+    Failed(String),
+    Cancelled,
 }
 
-#[derive(Default)]
-pub struct StationsList {
-    state: ListState,
-    items: Vec<RadioStation>,
-    last_selected: Option<usize>,
+impl SearchStatus {
+    fn title(&self) -> String {
+        match self {
+            SearchStatus::Idle => " Stations ".to_string(),
+            SearchStatus::InFlight { started, .. } => {
+                format!(" Searching... {:.1}s ", started.elapsed().as_secs_f32())
+            }
+            SearchStatus::Found(count) => format!(" Stations ({count}) "),
+            SearchStatus::Failed(error) => format!(" Search failed: {error} "),
+            SearchStatus::Cancelled => " Search cancelled ".to_string(),
+        }
+    }
 }
 
-impl StationsList {
-    fn new(items: Vec<RadioStation>) -> Self {
+/// A short message shown in the footer until it expires.
+#[derive(Debug)]
+pub struct StatusMessage {
+    text: String,
+    is_error: bool,
+    shown: Instant,
+}
+
+impl StatusMessage {
+    fn new(text: String, is_error: bool) -> Self {
         Self {
-            items,
-            ..Default::default()
+            text,
+            is_error,
+            shown: Instant::now(),
         }
     }
 
-    fn next(&mut self) {
-        if self.items.is_empty() {
-            return self.state.select(None);
+    fn is_expired(&self) -> bool {
+        self.shown.elapsed() > MESSAGE_TIMEOUT
+    }
+}
+
+/// A report shown over the list until closed.
+#[derive(Debug, Clone)]
+pub struct Report {
+    title: String,
+    lines: Vec<String>,
+}
+
+/// The lists [`Home`] can show.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HomeView {
+    /// The results of the last search.
+    #[default]
+    Results,
+    Favorites,
+    /// The stations played recently.
+    History,
+    /// The user's collections as a tree.
+    Collections,
+}
+
+impl HomeView {
+    pub fn next(self) -> Self {
+        match self {
+            HomeView::Results => HomeView::Favorites,
+            HomeView::Favorites => HomeView::History,
+            HomeView::History => HomeView::Collections,
+            HomeView::Collections => HomeView::Results,
         }
-        let i = match self.state.selected() {
-            Some(i) => {
-                if i >= self.items.len() - 1 {
-                    0
-                } else {
-                    i + 1
-                }
-            }
-            None => self.last_selected.unwrap_or(0),
-        };
-        self.state.select(Some(i));
     }
+}
+
+#[derive(Default, Copy, Clone, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Normal,
+    Insert,
+    Processing,
+    /// Typing the answer to a [`Prompt`].
+    Prompt,
+}
+
+/// What the answer to a [`Prompt`] is used for.
+#[derive(Debug, Clone)]
+pub enum PromptKind {
+    NewCollection,
+    RenameCollection(String),
+    /// Asks for confirmation before deleting the collection.
+    DeleteCollection(String),
+    AddToCollection(Box<RadioStation>),
+    MoveToCollection {
+        from: String,
+        index: usize,
+    },
+    /// Adds a custom station, given as `url [name]`, to the collection.
+    AddUrl(String),
+    /// Writes the stations to the playlist file answered.
+    Export(Vec<RadioStation>),
+    /// Imports the playlist file answered into a collection.
+    Import,
+    /// Plays the stream or playlist url answered.
+    OpenUrl,
+    /// Saves the station opened by url to the stations file, under the name answered.
+    SaveCustom(Box<RadioStation>),
+}
 
-    fn previous(&mut self) {
-        if self.items.is_empty() {
-            return self.state.select(None);
+impl PromptKind {
+    fn label(&self) -> String {
+        match self {
+            PromptKind::NewCollection => "new collection: ".to_string(),
+            PromptKind::RenameCollection(name) => format!("rename {name} to: "),
+            PromptKind::DeleteCollection(name) => format!("delete {name} and its stations? (y/n) "),
+            PromptKind::AddToCollection(station) => format!("add {} to: ", station.name.trim()),
+            PromptKind::MoveToCollection { from, .. } => format!("move from {from} to: "),
+            PromptKind::AddUrl(name) => format!("add to {name}, url [name]: "),
+            PromptKind::Export(stations) => format!("export {} stations to: ", stations.len()),
+            PromptKind::Import => "import playlist: ".to_string(),
+            PromptKind::OpenUrl => "open url: ".to_string(),
+            PromptKind::SaveCustom(_) => "save as custom station (esc to skip): ".to_string(),
         }
-        let i = match self.state.selected() {
-            Some(i) => {
-                if i == 0 {
-                    self.items.len() - 1
-                } else {
-                    i - 1
-                }
-            }
-            None => self.last_selected.unwrap_or(0),
-        };
-        self.state.select(Some(i));
     }
 
-    fn unselect(&mut self) {
-        let offset = self.state.offset();
-        self.last_selected = self.state.selected();
-        self.state.select(None);
-        *self.state.offset_mut() = offset;
+    /// What failed when answering the prompt fails, for the log.
+    fn failure(&self) -> &'static str {
+        match self {
+            PromptKind::NewCollection => "failed to create collection",
+            PromptKind::RenameCollection(_) => "failed to rename collection",
+            PromptKind::DeleteCollection(_) => "failed to delete collection",
+            PromptKind::AddToCollection(_) | PromptKind::AddUrl(_) => "failed to add to collection",
+            PromptKind::MoveToCollection { .. } => "failed to move to collection",
+            PromptKind::Export(_) => "failed to export playlist",
+            PromptKind::Import => "failed to import playlist",
+            PromptKind::OpenUrl => "failed to open url",
+            PromptKind::SaveCustom(_) => "failed to save custom station",
+        }
     }
 
-    fn select_station(&mut self) -> Option<RadioStation> {
-        self.state.selected().map(|i| self.items[i].clone())
+    /// Whether the answer names a collection, so existing names are offered.
+    fn completes_collections(&self) -> bool {
+        matches!(
+            self,
+            PromptKind::AddToCollection(_) | PromptKind::MoveToCollection { .. }
+        )
     }
 }
 
+/// A one line question shown below the list, answered by typing.
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub input: Input,
+}
+
 pub struct Home {
     pub show_help: bool,
+    pub show_details: bool,
     pub radio_api: Arc<RadioApi>,
     pub stations: StationsList,
+    /// The search results as found, before duplicates and failing stations are hidden.
+    pub all_results: Vec<RadioStation>,
+    /// Whether the duplicate and failing results are shown too.
+    show_all_results: bool,
+    /// The other listings of the results shown, by the `stationuuid` of the one shown.
+    alternates: HashMap<String, Vec<RadioStation>>,
     pub now_playing: Option<StreamState>,
     throbber_state: throbber_widgets_tui::ThrobberState,
     pub counter: usize,
@@ -149,16 +289,143 @@ pub struct Home {
     pub text: Vec<String>,
     pub volume: f32,
     pub volume_tx: Option<broadcast::Sender<f32>>,
+    pub config: Config,
+    pub search_status: SearchStatus,
+    /// The id given to the next search. Responses are only applied if their id is still current.
+    next_search_id: u64,
+    pub vote_log: VoteLog,
+    pub status_message: Option<StatusMessage>,
+    pub favorites: Favorites,
+    /// The favorites as shown in [`HomeView::Favorites`].
+    pub favorites_list: StationsList,
+    pub history: History,
+    /// The stations of the history entries as shown in [`HomeView::History`], newest first.
+    pub history_list: StationsList,
+    pub view: HomeView,
+    pub collections: Collections,
+    /// The stations of the stations file in the config directory.
+    pub custom_stations: CustomStations,
+    /// The cached Icecast and Shoutcast listings, searched along with the directory. Shared
+    /// with the background saves, see [`save_listings`].
+    pub listings: Arc<Mutex<Listings>>,
+    /// The urls of the listings being fetched.
+    listings_pending: HashSet<String>,
+    /// The local copy of the directory, searched instead of it once synced.
+    pub mirror: Arc<RwLock<Mirror>>,
+    /// Loads the local copy of the directory, which is empty until then. The outcome is the
+    /// error that set its file aside, if any.
+    mirror_load: Option<JoinHandle<Result<Option<String>, String>>>,
+    /// Whether the local copy of the directory failed to load, so a sync would replace it.
+    mirror_unreadable: bool,
+    /// Whether the mirror is being synced.
+    syncing: bool,
+    /// Whether the streams of a list are being checked.
+    checking: bool,
+    /// The directory's checks of the stations whose details were shown.
+    pub check_histories: CheckHistories,
+    /// The stations whose checks are being fetched, or could not be this session.
+    checks_pending: HashSet<String>,
+    /// The stations whose votes were sent but not answered yet.
+    votes_pending: HashSet<String>,
+    /// The station whose details were shown at the last tick, its checks were already looked at.
+    checks_looked_at: Option<String>,
+    /// The outcome of the last refresh of the saved stations or check of the streams.
+    pub report: Option<Report>,
+    pub collection_tree: CollectionTree,
+    pub prompt: Option<Prompt>,
+    /// The parameters of the last search, saved with the session.
+    pub last_search: Vec<SearchParam>,
+    last_session_save: Instant,
+    /// When the stations file was last looked at, `None` before the first time.
+    last_custom_stations_check: Option<Instant>,
+    /// When the listings were last looked at, `None` before the first time.
+    last_listings_check: Option<Instant>,
+}
+
+/// Loads the local copy of the directory into `mirror` without blocking, starting it empty if
+/// its file cannot be parsed. Returns the parse error, or the error that kept it from loading.
+fn load_mirror(mirror: Arc<RwLock<Mirror>>) -> JoinHandle<Result<Option<String>, String>> {
+    tokio::task::spawn_blocking(move || {
+        let path = get_data_dir().join(Mirror::FILE_NAME);
+        let (loaded, error) = load_or_set_aside(&path, Mirror::load).map_err(|e| e.to_string())?;
+        if let Ok(mut mirror) = mirror.write() {
+            *mirror = loaded;
+        }
+        Ok(error.map(|e| e.to_string()))
+    })
+}
+
+/// Writes the changes of the listings on a thread where blocking is fine, outside their lock.
+fn save_listings(listings: Arc<Mutex<Listings>>) {
+    tokio::task::spawn_blocking(move || loop {
+        let Some(changes) = listings
+            .lock()
+            .ok()
+            .and_then(|mut listings| listings.take_changes())
+        else {
+            return;
+        };
+        let result = changes.and_then(|(path, data)| Ok(write_atomic(&path, &data)?));
+        if let Err(e) = result {
+            tracing::error!(error = ?e, "failed to save listings");
+        }
+    });
+}
+
+/// Loads a store from the data directory, starting it empty if its file cannot be parsed.
+fn load_store<T: Default>(
+    file_name: &str,
+    load: impl Fn(&Path) -> Result<T, Error>,
+    unreadable: &mut Vec<String>,
+) -> Result<T, Error> {
+    let path = get_data_dir().join(file_name);
+    let (store, error) = load_or_set_aside(&path, load)?;
+    if let Some(error) = error {
+        error!(file = file_name, %error, "failed to parse, starting empty");
+        unreadable.push(file_name.to_string());
+    }
+    Ok(store)
 }
 
 impl Home {
-    pub async fn new() -> Result<Self, Error> {
+    pub async fn new(config: &Config) -> Result<Self, Error> {
+        let mut unreadable = Vec::new();
+        let vote_log = load_store(VoteLog::FILE_NAME, VoteLog::load, &mut unreadable)?;
+        let favorites = load_store(Favorites::FILE_NAME, Favorites::load, &mut unreadable)?;
+        let mut favorites_list = StationsList::default();
+        favorites_list.set_items(favorites.stations().to_vec());
+        let history = load_store(History::FILE_NAME, History::load, &mut unreadable)?;
+        let mut history_list = StationsList::default();
+        history_list.set_items(history_stations(&history));
+        let collections = load_store(Collections::FILE_NAME, Collections::load, &mut unreadable)?;
+        let listings = load_store(Listings::FILE_NAME, Listings::load, &mut unreadable)?;
+        // The copy can be large, searches ask the directory until it is loaded
+        let mirror = Arc::new(RwLock::new(Mirror::default()));
+        let mirror_load = Some(load_mirror(mirror.clone()));
+        let check_histories = load_store(
+            CheckHistories::FILE_NAME,
+            CheckHistories::load,
+            &mut unreadable,
+        )?;
+        let status_message = (!unreadable.is_empty()).then(|| {
+            StatusMessage::new(
+                format!(
+                    "Could not read {}, kept as .broken and started empty",
+                    unreadable.join(", ")
+                ),
+                true,
+            )
+        });
         Ok(Self {
-            radio_api: Arc::new(RadioApi::new().await?),
+            radio_api: commands::radio_api(config)?,
             stations: Default::default(),
+            all_results: Vec::new(),
+            show_all_results: false,
+            alternates: HashMap::new(),
             now_playing: Default::default(),
             throbber_state: Default::default(),
             show_help: Default::default(),
+            show_details: Default::default(),
             counter: Default::default(),
             app_ticker: Default::default(),
             render_ticker: Default::default(),
@@ -169,6 +436,36 @@ impl Home {
             text: Default::default(),
             volume: 1.0,
             volume_tx: None,
+            config: config.clone(),
+            search_status: Default::default(),
+            next_search_id: 0,
+            vote_log,
+            status_message,
+            favorites,
+            favorites_list,
+            history,
+            history_list,
+            view: Default::default(),
+            collections,
+            custom_stations: CustomStations::new(&get_config_dir()),
+            listings: Arc::new(Mutex::new(listings)),
+            listings_pending: HashSet::new(),
+            mirror,
+            mirror_load,
+            mirror_unreadable: false,
+            syncing: false,
+            checking: false,
+            check_histories,
+            checks_pending: HashSet::new(),
+            votes_pending: HashSet::new(),
+            checks_looked_at: None,
+            report: None,
+            collection_tree: Default::default(),
+            prompt: None,
+            last_search: Vec::new(),
+            last_session_save: Instant::now(),
+            last_custom_stations_check: None,
+            last_listings_check: None,
         })
     }
 
@@ -179,6 +476,27 @@ impl Home {
 
     pub fn tick(&mut self) {
         tracing::trace!("Tick");
+        if self
+            .status_message
+            .as_ref()
+            .is_some_and(StatusMessage::is_expired)
+        {
+            self.status_message = None;
+        }
+        let interval = self.config.session.save_interval_secs;
+        if interval > 0 && self.last_session_save.elapsed() >= Duration::from_secs(interval) {
+            self.save_session();
+        }
+        if self
+            .mirror_load
+            .as_ref()
+            .is_some_and(JoinHandle::is_finished)
+        {
+            self.mirror_loaded();
+        }
+        self.reload_custom_stations();
+        self.refresh_listings();
+        self.fetch_selected_checks();
         self.throbber_state.calc_next();
         self.app_ticker = self.app_ticker.saturating_add(1);
     }
@@ -195,33 +513,1114 @@ impl Home {
     pub fn search_stations(&mut self, params: Vec<SearchParam>) {
         let tx = self.action_tx.clone().unwrap();
         let api = self.radio_api.clone();
+        let timeout = self.config.search.timeout();
+        let mirror = self.mirror.clone();
+        // Asking for fresh results also skips the local copy of the directory
+        let offline = self.config.mirror.search_offline
+            && !params.contains(&SearchParam::BypassCache(true))
+            && self.mirror.read().is_ok_and(|mirror| !mirror.is_empty());
+
+        self.cancel_search();
+        self.last_search = params.clone();
+        let id = self.next_search_id;
+        self.next_search_id += 1;
+
+        let handle = tokio::spawn(async move {
+            tracing::info!(?params, id, offline, "Searching stations");
+            let action = if offline {
+                // Scanning the copy takes a while, keep it off the runtime's threads
+                let search = tokio::task::spawn_blocking(move || {
+                    mirror
+                        .read()
+                        .map(|mirror| mirror.search(&params))
+                        .map_err(|e| e.to_string())
+                });
+                match search.await {
+                    Ok(Ok(stations)) => Action::StationsFound(id, stations),
+                    Ok(Err(e)) => Action::SearchFailed(id, e),
+                    Err(e) => Action::SearchFailed(id, e.to_string()),
+                }
+            } else {
+                match tokio::time::timeout(timeout, api.get_stations(params)).await {
+                    Ok(Ok(stations)) => Action::StationsFound(id, stations),
+                    Ok(Err(e)) => Action::SearchFailed(id, e.to_string()),
+                    Err(_) => {
+                        Action::SearchFailed(id, format!("timed out after {}s", timeout.as_secs()))
+                    }
+                }
+            };
+            let _ = tx.send(action);
+        });
+
+        if self.mode == Mode::Normal {
+            self.mode = Mode::Processing;
+        }
+        self.search_status = SearchStatus::InFlight {
+            id,
+            started: Instant::now(),
+            handle,
+        };
+    }
+
+    /// Aborts the in-flight search. Its response, should it still arrive, is ignored.
+    pub fn cancel_search(&mut self) {
+        if let SearchStatus::InFlight { id, handle, .. } = &self.search_status {
+            tracing::info!(id, "Cancelling search");
+            handle.abort();
+            self.search_status = SearchStatus::Cancelled;
+            self.finish_processing();
+        }
+    }
+
+    /// Returns `true` if `id` belongs to the search currently in flight.
+    fn is_current_search(&self, id: u64) -> bool {
+        let current = matches!(self.search_status, SearchStatus::InFlight { id: current, .. } if current == id);
+        if !current {
+            tracing::debug!(id, "dropping late search response");
+        }
+        current
+    }
+
+    pub fn search_found(&mut self, id: u64, mut stations: Vec<RadioStation>) {
+        if self.is_current_search(id) {
+            // The custom stations matching the search come first, the listings' last
+            let mut custom = self.custom_stations.matching(&self.last_search);
+            custom.retain(|c| !stations.iter().any(|s| s.url == c.url));
+            stations.splice(0..0, custom);
+            let listed = self
+                .listings
+                .lock()
+                .map(|listings| listings.search(&self.config.listings.urls, &self.last_search))
+                .unwrap_or_default();
+            for station in listed {
+                if !stations.iter().any(|s| s.url == station.url) {
+                    stations.push(station);
+                }
+            }
+            self.all_results = stations;
+            self.finish_processing();
+            self.show_results();
+        }
+    }
+
+    /// Lists the search results, each station once and without the failing ones as configured,
+    /// unless all of them are asked for.
+    fn show_results(&mut self) {
+        let mut stations = self.all_results.clone();
+        self.alternates.clear();
+        if !self.show_all_results {
+            if self.config.search.hide_failing {
+                stations.retain(|station| !is_failing(station));
+            }
+            if self.config.search.group_duplicates {
+                let groups = group_duplicates(stations, |s| self.check_histories.is_unreliable(s));
+                stations = groups
+                    .into_iter()
+                    .map(|group| {
+                        if !group.alternates.is_empty() {
+                            self.alternates
+                                .insert(group.best.stationuuid.clone(), group.alternates);
+                        }
+                        group.best
+                    })
+                    .collect();
+            }
+        }
+        if self.config.search.sink_unreliable {
+            self.check_histories.sink_unreliable(&mut stations);
+        }
+        self.search_status = SearchStatus::Found(stations.len());
+        self.apply_stations(stations);
+    }
+
+    fn toggle_all_results(&mut self) {
+        self.show_all_results = !self.show_all_results;
+        let text = match self.show_all_results {
+            true => "Showing all results",
+            false => "Hiding duplicate and failing results",
+        };
+        self.status_message = Some(StatusMessage::new(text.to_string(), false));
+        if !self.all_results.is_empty() {
+            self.show_results();
+        }
+    }
+
+    pub fn search_failed(&mut self, id: u64, error: String) {
+        if self.is_current_search(id) {
+            tracing::error!(id, error, "search failed");
+            self.search_status = SearchStatus::Failed(error);
+            self.finish_processing();
+        }
+    }
+
+    /// Leaves [`Mode::Processing`], without interrupting the filter prompt.
+    fn finish_processing(&mut self) {
+        if self.mode == Mode::Processing {
+            self.mode = Mode::Normal;
+        }
+    }
+
+    pub fn apply_stations(&mut self, stations: Vec<RadioStation>) {
+        self.stations.set_items(stations);
+        self.view = HomeView::Results;
+    }
+
+    /// The list of the current view, `None` for the collections which are shown as a tree.
+    pub fn list(&self) -> Option<&StationsList> {
+        match self.view {
+            HomeView::Results => Some(&self.stations),
+            HomeView::Favorites => Some(&self.favorites_list),
+            HomeView::History => Some(&self.history_list),
+            HomeView::Collections => None,
+        }
+    }
+
+    pub fn list_mut(&mut self) -> Option<&mut StationsList> {
+        match self.view {
+            HomeView::Results => Some(&mut self.stations),
+            HomeView::Favorites => Some(&mut self.favorites_list),
+            HomeView::History => Some(&mut self.history_list),
+            HomeView::Collections => None,
+        }
+    }
+
+    /// The station selected in the current view.
+    pub fn selected_station(&self) -> Option<&RadioStation> {
+        match self.list() {
+            Some(list) => list.selected(),
+            None => self.collection_tree.selected_station(&self.collections),
+        }
+    }
+
+    /// Switches to `view`. Open prompts stay with the view they belong to.
+    pub fn show_view(&mut self, view: HomeView) {
+        if self.mode != Mode::Insert && self.mode != Mode::Prompt {
+            self.view = view;
+        }
+    }
+
+    /// Opens the prompt and switches to [`AppMode::Prompt`] so keys reach it.
+    fn open_prompt(&mut self, kind: PromptKind, value: &str) -> Option<Action> {
+        if self.mode == Mode::Insert || self.mode == Mode::Prompt {
+            return None;
+        }
+        self.prompt = Some(Prompt {
+            kind,
+            input: Input::new(value.to_string()),
+        });
+        self.mode = Mode::Prompt;
+        Some(Action::Mode(AppMode::Prompt))
+    }
+
+    fn close_prompt(&mut self) -> Option<Action> {
+        self.prompt = None;
+        self.mode = Mode::Normal;
+        Some(Action::Mode(AppMode::Home))
+    }
+
+    /// Acts on the answer to the open prompt.
+    fn confirm_prompt(&mut self) -> Option<Action> {
+        let prompt = self.prompt.take()?;
+        let answer = prompt.input.value().trim().to_string();
+        let failure = prompt.kind.failure();
+        let result = match prompt.kind {
+            _ if answer.is_empty() => Ok(None),
+            PromptKind::NewCollection => self
+                .collections
+                .create(&answer)
+                .map(|()| Some(format!("Created {answer}"))),
+            PromptKind::RenameCollection(name) => {
+                self.collections.rename(&name, &answer).map(|()| {
+                    self.collection_tree.rename(&name, &answer);
+                    Some(format!("Renamed {name} to {answer}"))
+                })
+            }
+            PromptKind::DeleteCollection(name) if answer.starts_with(['y', 'Y']) => self
+                .collections
+                .delete(&name)
+                .map(|_| Some(format!("Deleted {name}"))),
+            PromptKind::DeleteCollection(_) => Ok(None),
+            PromptKind::AddToCollection(station) => {
+                let message = format!("Added {} to {answer}", station.name.trim());
+                self.collections
+                    .add_station(&answer, *station)
+                    .map(|()| Some(message))
+            }
+            PromptKind::MoveToCollection { from, index } => self
+                .collections
+                .move_station_to(&from, index, &answer)
+                .map(|()| Some(format!("Moved to {answer}"))),
+            PromptKind::AddUrl(name) => {
+                let (url, station_name) = answer.split_once(' ').unwrap_or((&answer, &answer));
+                let station = RadioStation::custom(url, station_name.trim());
+                let message = format!("Added {url} to {name}");
+                self.collections.add_station(&name, station).map(|()| {
+                    self.collection_tree.expand(&name);
+                    Some(message)
+                })
+            }
+            PromptKind::Export(stations) => Self::export_playlist(&stations, Path::new(&answer)),
+            PromptKind::Import => {
+                self.import_playlist(PathBuf::from(answer));
+                Ok(None)
+            }
+            PromptKind::OpenUrl => {
+                self.open_url(answer);
+                Ok(None)
+            }
+            PromptKind::SaveCustom(station) => {
+                let station = RadioStation {
+                    name: answer,
+                    ..*station
+                };
+                self.custom_stations
+                    .append(&station)
+                    .map(|path| Some(format!("Saved {} to {}", station.name, path.display())))
+            }
+        };
+        match result {
+            Ok(Some(message)) => self.status_message = Some(StatusMessage::new(message, false)),
+            Ok(None) => (),
+            Err(e) => {
+                tracing::error!(error = ?e, "{failure}");
+                self.status_message = Some(StatusMessage::new(e.to_string(), true));
+            }
+        }
+        self.collection_tree.clamp(&self.collections);
+        self.close_prompt()
+    }
+
+    /// The collection names starting with the answer typed so far, for prompts asking for one.
+    fn prompt_hints(&self) -> Vec<&str> {
+        let Some(prompt) = self
+            .prompt
+            .as_ref()
+            .filter(|p| p.kind.completes_collections())
+        else {
+            return Vec::new();
+        };
+        let typed = prompt.input.value().trim().to_lowercase();
+        self.collections
+            .collections()
+            .iter()
+            .map(|c| c.name.as_str())
+            .filter(|name| name.to_lowercase().starts_with(&typed))
+            .take(5)
+            .collect()
+    }
+
+    /// Asks where to export the current list to, or the selected collection in the collections
+    /// view.
+    fn export_list(&mut self) -> Option<Action> {
+        let (name, stations) = match self.list() {
+            Some(list) => (
+                format!("{:?}", self.view).to_lowercase(),
+                list.visible_items().map(|(_, s)| s.clone()).collect(),
+            ),
+            None => {
+                let name = self
+                    .collection_tree
+                    .selected_collection(&self.collections)?;
+                let collection = self.collections.get(name)?;
+                (name.to_string(), collection.stations.clone())
+            }
+        };
+        self.open_prompt(PromptKind::Export(stations), &format!("{name}.m3u"))
+    }
+
+    fn export_playlist(stations: &[RadioStation], path: &Path) -> Result<Option<String>, Error> {
+        let format = PlaylistFormat::from_path(path).unwrap_or(PlaylistFormat::ExtM3u);
+        write_atomic(path, format.export(stations)?.as_bytes())?;
+        let mut message = format!("Exported {} stations to {}", stations.len(), path.display());
+        match PlaylistFormat::headers_left_out(stations) {
+            0 => (),
+            n => message.push_str(&format!(", without the request headers of {n}")),
+        }
+        Ok(Some(message))
+    }
+
+    /// Reads the playlist and matches its stations with the directory in the background, they
+    /// arrive with [`Action::PlaylistImported`].
+    fn import_playlist(&mut self, path: PathBuf) {
+        let Some(tx) = self.action_tx.clone() else {
+            return;
+        };
+        let api = self.radio_api.clone();
+        self.status_message = Some(StatusMessage::new(
+            format!("Importing {}...", path.display()),
+            false,
+        ));
         tokio::spawn(async move {
-            tx.send(Action::EnterProcessing).unwrap();
-            tracing::info!(?params, "Searching stations");
-            let stations = api.get_stations(params).await.unwrap();
+            let action = match commands::read_playlist(Some(&api), &path, None).await {
+                Ok((stations, matched)) => {
+                    Action::PlaylistImported(commands::playlist_name(&path), stations, matched)
+                }
+                Err(e) => {
+                    tracing::error!(error = ?e, "failed to import playlist");
+                    Action::Error(format!("Import failed: {e}"))
+                }
+            };
+            let _ = tx.send(action);
+        });
+    }
 
-            tx.send(Action::StationsFound(stations)).unwrap();
-            tx.send(Action::ExitProcessing).unwrap();
+    /// Resolves the url in the background, the station arrives with [`Action::UrlOpened`].
+    fn open_url(&mut self, url: String) {
+        let Some(tx) = self.action_tx.clone() else {
+            return;
+        };
+        let timeout = self.config.search.timeout();
+        self.status_message = Some(StatusMessage::new(format!("Opening {url}..."), false));
+        tokio::spawn(async move {
+            let client = reqwest::Client::new();
+            let action = match tokio::time::timeout(timeout, resolve_url(&client, &url)).await {
+                Ok(Ok(station)) => Action::UrlOpened(Box::new(station)),
+                Ok(Err(e)) => {
+                    tracing::error!(url, error = ?e, "failed to open url");
+                    Action::Error(format!("Failed to open {url}: {e}"))
+                }
+                Err(_) => Action::Error(format!(
+                    "Failed to open {url}: timed out after {}s",
+                    timeout.as_secs()
+                )),
+            };
+            let _ = tx.send(action);
         });
     }
 
-    pub fn apply_stations(&mut self, stations: Vec<RadioStation>) {
-        self.stations = StationsList::new(stations);
+    /// Plays the station of an opened url and offers to save it unless it is saved already.
+    fn url_opened(&mut self, station: RadioStation) -> Option<Action> {
+        self.play_station(station.clone());
+        if self.custom_stations.get(&station.stationuuid).is_some() {
+            return None;
+        }
+        let name = station.name.trim().to_string();
+        self.open_prompt(PromptKind::SaveCustom(Box::new(station)), &name)
+    }
+
+    /// Adds the imported stations to the collection and shows it.
+    fn playlist_imported(&mut self, name: String, stations: Vec<RadioStation>, matched: usize) {
+        let count = stations.len();
+        match self.collections.add_stations(&name, stations) {
+            Ok(()) => {
+                self.status_message = Some(StatusMessage::new(
+                    format!(
+                        "Imported {count} stations into {name}, {matched} found in the directory"
+                    ),
+                    false,
+                ));
+                self.collection_tree.expand(&name);
+                self.show_view(HomeView::Collections);
+                if let Some(i) = self
+                    .collections
+                    .collections()
+                    .iter()
+                    .position(|c| c.name == name)
+                {
+                    self.collection_tree
+                        .select(&self.collections, TreeNode::Collection(i));
+                }
+            }
+            Err(e) => self.status_message = Some(StatusMessage::new(e.to_string(), true)),
+        }
+    }
+
+    /// Asks which collection to add the selected station to, or to move it to if it is in one
+    /// already.
+    fn add_to_collection(&mut self) -> Option<Action> {
+        if self.view == HomeView::Collections {
+            let Some(TreeNode::Station(i, j)) = self.collection_tree.selected(&self.collections)
+            else {
+                return None;
+            };
+            let from = self.collections.collections()[i].name.clone();
+            return self.open_prompt(PromptKind::MoveToCollection { from, index: j }, "");
+        }
+        let station = self.selected_station()?.clone();
+        self.open_prompt(PromptKind::AddToCollection(Box::new(station)), "")
+    }
+
+    fn rename_collection(&mut self) -> Option<Action> {
+        if self.view != HomeView::Collections {
+            return None;
+        }
+        let name = self
+            .collection_tree
+            .selected_collection(&self.collections)?
+            .to_string();
+        self.open_prompt(PromptKind::RenameCollection(name.clone()), &name)
+    }
+
+    fn add_url_to_collection(&mut self) -> Option<Action> {
+        if self.view != HomeView::Collections {
+            return None;
+        }
+        let name = self
+            .collection_tree
+            .selected_collection(&self.collections)
+            .unwrap_or("Custom")
+            .to_string();
+        self.open_prompt(PromptKind::AddUrl(name), "")
+    }
+
+    /// Asks before deleting the selected collection, removes the selected station from its
+    /// collection right away.
+    fn delete_selected(&mut self) -> Option<Action> {
+        if self.view != HomeView::Collections {
+            return None;
+        }
+        match self.collection_tree.selected(&self.collections)? {
+            TreeNode::Collection(i) => {
+                let name = self.collections.collections()[i].name.clone();
+                self.open_prompt(PromptKind::DeleteCollection(name), "")
+            }
+            TreeNode::Station(i, j) => {
+                let name = self.collections.collections()[i].name.clone();
+                let message = match self.collections.remove_station(&name, j) {
+                    Ok(station) => StatusMessage::new(
+                        format!("Removed {} from {name}", station.name.trim()),
+                        false,
+                    ),
+                    Err(e) => StatusMessage::new(e.to_string(), true),
+                };
+                self.status_message = Some(message);
+                self.collection_tree.clamp(&self.collections);
+                None
+            }
+        }
+    }
+
+    /// Moves the selected collection, or the selected station within its collection, `offset`
+    /// places down, or up for negative offsets.
+    fn move_in_collections(&mut self, offset: isize) {
+        let Some(node) = self.collection_tree.selected(&self.collections) else {
+            return;
+        };
+        let result = match node {
+            TreeNode::Collection(i) => {
+                let name = self.collections.collections()[i].name.clone();
+                self.collections.move_collection(&name, offset).map(|_| {
+                    self.collections
+                        .collections()
+                        .iter()
+                        .position(|c| c.name == name)
+                        .map(TreeNode::Collection)
+                })
+            }
+            TreeNode::Station(i, j) => {
+                let name = self.collections.collections()[i].name.clone();
+                self.collections
+                    .move_station(&name, j, offset)
+                    .map(|j| Some(TreeNode::Station(i, j)))
+            }
+        };
+        match result {
+            Ok(Some(node)) => self.collection_tree.select(&self.collections, node),
+            Ok(None) => (),
+            Err(e) => self.status_message = Some(StatusMessage::new(e.to_string(), true)),
+        }
+    }
+
+    /// Adds the selected station to the favorites, or removes it.
+    pub fn toggle_favorite(&mut self) {
+        let Some(station) = self.selected_station().cloned() else {
+            return;
+        };
+        match self.favorites.toggle(&station) {
+            Ok(added) => {
+                let message = if added {
+                    format!("Added {} to favorites", station.name)
+                } else {
+                    format!("Removed {} from favorites", station.name)
+                };
+                self.status_message = Some(StatusMessage::new(message, false));
+                self.refresh_favorites(None);
+            }
+            Err(e) => {
+                tracing::error!(error = ?e, "failed to save favorites");
+                self.status_message = Some(StatusMessage::new(
+                    format!("Failed to save favorites: {e}"),
+                    true,
+                ));
+            }
+        }
+    }
+
+    /// Moves the selected favorite `offset` places down, or up for negative offsets.
+    pub fn move_favorite(&mut self, offset: isize) {
+        if self.view != HomeView::Favorites {
+            return;
+        }
+        if self.favorites_list.sort.is_some() {
+            self.status_message = Some(StatusMessage::new(
+                "Favorites can only be reordered while unsorted".to_string(),
+                true,
+            ));
+            return;
+        }
+        let Some(uuid) = self
+            .favorites_list
+            .selected()
+            .map(|s| s.stationuuid.clone())
+        else {
+            return;
+        };
+        match self.favorites.move_by(&uuid, offset) {
+            Ok(true) => self.refresh_favorites(Some(&uuid)),
+            Ok(false) => (),
+            Err(e) => {
+                tracing::error!(error = ?e, "failed to save favorites");
+                self.status_message = Some(StatusMessage::new(
+                    format!("Failed to save favorites: {e}"),
+                    true,
+                ));
+            }
+        }
+    }
+
+    /// Reads the stations file again if it changed, and shows the custom stations that are not
+    /// favorites after the favorites.
+    fn reload_custom_stations(&mut self) {
+        if self
+            .last_custom_stations_check
+            .is_some_and(|checked| checked.elapsed() < CUSTOM_STATIONS_INTERVAL)
+        {
+            return;
+        }
+        self.last_custom_stations_check = Some(Instant::now());
+        match self.custom_stations.reload_if_changed() {
+            Ok(false) => (),
+            Ok(true) => {
+                self.status_message = Some(StatusMessage::new(
+                    format!(
+                        "Loaded {} custom stations",
+                        self.custom_stations.stations().len()
+                    ),
+                    false,
+                ));
+                self.refresh_favorites(None);
+            }
+            Err(e) => {
+                tracing::error!(error = ?e, "failed to load custom stations");
+                self.status_message = Some(StatusMessage::new(e.to_string(), true));
+            }
+        }
+    }
+
+    fn mirror_loaded(&mut self) {
+        let Some(Some(result)) = self.mirror_load.take().map(FutureExt::now_or_never) else {
+            return;
+        };
+        let message = match result.map_err(|e| e.to_string()).and_then(|loaded| loaded) {
+            Ok(None) => return,
+            Ok(Some(error)) => {
+                error!(%error, "failed to load the local copy of the directory");
+                format!(
+                    "Could not read {}, kept as .broken and started empty",
+                    Mirror::FILE_NAME
+                )
+            }
+            Err(e) => {
+                error!(error = %e, "failed to load the local copy of the directory");
+                self.mirror_unreadable = true;
+                format!("Could not load the local copy of the directory: {e}")
+            }
+        };
+        self.status_message = Some(StatusMessage::new(message, true));
+    }
+
+    /// Brings the local copy of the directory up to date in the background, the outcome arrives
+    /// with [`Action::DirectorySynced`].
+    fn sync_directory(&mut self) {
+        let Some(tx) = self.action_tx.clone() else {
+            return;
+        };
+        if self.mirror_load.is_some() {
+            self.status_message = Some(StatusMessage::new(
+                "The local copy of the directory is still loading".to_string(),
+                false,
+            ));
+            return;
+        }
+        if self.mirror_unreadable {
+            self.status_message = Some(StatusMessage::new(
+                "The local copy of the directory could not be loaded, not syncing over it"
+                    .to_string(),
+                true,
+            ));
+            return;
+        }
+        if self.syncing {
+            self.status_message = Some(StatusMessage::new(
+                "The directory is already being synced".to_string(),
+                false,
+            ));
+            return;
+        }
+        self.syncing = true;
+        self.status_message = Some(StatusMessage::new(
+            "Syncing the directory...".to_string(),
+            false,
+        ));
+        let api = self.radio_api.clone();
+        let mirror = self.mirror.clone();
+        tokio::spawn(async move {
+            // Searches keep using the current copy while the new one downloads
+            let current = mirror.read().map(|m| m.clone()).map_err(|e| e.to_string());
+            let action = match current {
+                Ok(mut synced) => match api.sync_mirror(&mut synced, false).await {
+                    Ok(report) => {
+                        let saved = tokio::task::spawn_blocking(move || {
+                            synced.save().map(|()| synced).map_err(|e| e.to_string())
+                        });
+                        match saved
+                            .await
+                            .map_err(|e| e.to_string())
+                            .and_then(|saved| saved)
+                        {
+                            Ok(synced) => {
+                                // The old copy is dropped after the lock is released
+                                let _previous = mirror
+                                    .write()
+                                    .map(|mut mirror| std::mem::replace(&mut *mirror, synced));
+                                Action::DirectorySynced(format!("Synced {report}"))
+                            }
+                            Err(e) => {
+                                tracing::error!(error = %e, "failed to save the directory");
+                                Action::SyncFailed(e)
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!(error = ?e, "failed to sync the directory");
+                        Action::SyncFailed(e.to_string())
+                    }
+                },
+                Err(e) => Action::SyncFailed(e),
+            };
+            let _ = tx.send(action);
+        });
+    }
+
+    fn sync_finished(&mut self, message: String, failed: bool) {
+        self.syncing = false;
+        let message = match failed {
+            true => format!("Sync failed: {message}"),
+            false => message,
+        };
+        self.status_message = Some(StatusMessage::new(message, failed));
+    }
+
+    /// Looks up the saved directory stations in the background, they arrive with
+    /// [`Action::SavedStationsFetched`].
+    fn refresh_saved(&mut self) {
+        let Some(tx) = self.action_tx.clone() else {
+            return;
+        };
+        let mut uuids: Vec<String> = Vec::new();
+        let saved = self
+            .favorites
+            .stations()
+            .iter()
+            .chain(self.history.entries().iter().map(|entry| &entry.station))
+            .chain(
+                self.collections
+                    .collections()
+                    .iter()
+                    .flat_map(|collection| &collection.stations),
+            );
+        for station in saved {
+            if station.is_directory() && !uuids.contains(&station.stationuuid) {
+                uuids.push(station.stationuuid.clone());
+            }
+        }
+        if uuids.is_empty() {
+            self.status_message = Some(StatusMessage::new(
+                "No saved stations from the directory".to_string(),
+                false,
+            ));
+            return;
+        }
+        self.status_message = Some(StatusMessage::new(
+            format!("Refreshing {} saved stations...", uuids.len()),
+            false,
+        ));
+        let api = self.radio_api.clone();
+        tokio::spawn(async move {
+            let action = match api.stations_by_uuid(&uuids).await {
+                Ok(found) => Action::SavedStationsFetched(uuids, found),
+                Err(e) => {
+                    tracing::error!(error = ?e, "failed to refresh the saved stations");
+                    Action::Error(format!("Refreshing the saved stations failed: {e}"))
+                }
+            };
+            let _ = tx.send(action);
+        });
+    }
+
+    /// Brings the favorites, the history and the collections up to date with the directory and
+    /// shows what changed.
+    fn saved_stations_fetched(&mut self, uuids: Vec<String>, found: Vec<RadioStation>) {
+        let refresh = StationRefresh::new(uuids, found);
+        let mut report = RefreshReport {
+            checked: refresh.checked(),
+            ..Default::default()
+        };
+        let mut update = |station: &mut RadioStation| {
+            let before = station.clone();
+            let changes = refresh.apply(station);
+            report.record(station, changes);
+            *station != before
+        };
+        let saved = self
+            .favorites
+            .update_stations(&mut update)
+            .and_then(|()| self.history.update_stations(&mut update))
+            .and_then(|()| self.collections.update_stations(&mut update));
+        self.refresh_favorites(None);
+        self.history_list.set_items(history_stations(&self.history));
+        self.collection_tree.clamp(&self.collections);
+        let changed = report.changes.len();
+        self.status_message = Some(match saved {
+            Ok(()) => StatusMessage::new(
+                format!(
+                    "Refreshed {} saved stations, {changed} changed",
+                    report.checked
+                ),
+                false,
+            ),
+            Err(e) => {
+                tracing::error!(error = ?e, "failed to save the refreshed stations");
+                StatusMessage::new(e.to_string(), true)
+            }
+        });
+        if changed > 0 {
+            self.report = Some(Report {
+                title: format!(" Refreshed {} stations, {changed} changed ", report.checked),
+                lines: report.lines(),
+            });
+        }
+    }
+
+    /// The stations of the list shown, in [`HomeView::Collections`] those of the selected
+    /// collection or of all of them if none is selected.
+    fn listed_stations(&self) -> Vec<RadioStation> {
+        let mut stations = match self.list() {
+            Some(list) => list.items().to_vec(),
+            None => match self.collection_tree.selected_collection(&self.collections) {
+                Some(name) => self
+                    .collections
+                    .get(name)
+                    .map(|collection| collection.stations.clone())
+                    .unwrap_or_default(),
+                None => self
+                    .collections
+                    .collections()
+                    .iter()
+                    .flat_map(|collection| collection.stations.clone())
+                    .collect(),
+            },
+        };
+        let mut seen = HashSet::new();
+        stations.retain(|station| seen.insert(station.stationuuid.clone()));
+        stations
+    }
+
+    /// Checks the streams of the list in the background, the outcome arrives with
+    /// [`Action::StreamsChecked`].
+    fn check_listed_streams(&mut self) {
+        let Some(tx) = self.action_tx.clone() else {
+            return;
+        };
+        if self.checking {
+            self.status_message = Some(StatusMessage::new(
+                "The streams are already being checked".to_string(),
+                false,
+            ));
+            return;
+        }
+        let stations = self.listed_stations();
+        if stations.is_empty() {
+            self.status_message = Some(StatusMessage::new(
+                "There are no stations to check".to_string(),
+                false,
+            ));
+            return;
+        }
+        self.checking = true;
+        self.status_message = Some(StatusMessage::new(
+            format!("Checking {} streams...", stations.len()),
+            false,
+        ));
+        let (concurrency, timeout) = (self.config.check.concurrency, self.config.check.timeout());
+        tokio::spawn(async move {
+            let client = reqwest::Client::new();
+            let checks = check_streams(&client, stations, concurrency, timeout).await;
+            let _ = tx.send(Action::StreamsChecked(checks));
+        });
+    }
+
+    /// Marks the broken stations wherever they are saved or listed and shows the report.
+    fn streams_checked(&mut self, checks: Vec<StreamCheck>) {
+        self.checking = false;
+        let marks = BrokenMarks::new(&checks);
+        let saved = self
+            .favorites
+            .update_stations(|station| marks.apply(station))
+            .and_then(|()| self.history.update_stations(|station| marks.apply(station)))
+            .and_then(|()| {
+                self.collections
+                    .update_stations(|station| marks.apply(station))
+            });
+        for check in &checks {
+            self.stations
+                .update_station(&check.station.stationuuid, |station| {
+                    marks.apply(station);
+                });
+        }
+        for station in &mut self.all_results {
+            marks.apply(station);
+        }
+        self.refresh_favorites(None);
+        self.history_list.set_items(history_stations(&self.history));
+
+        let broken = checks.iter().filter(|check| !check.is_ok()).count();
+        self.status_message = Some(match saved {
+            Ok(()) => StatusMessage::new(
+                format!("Checked {} streams, {broken} broken", checks.len()),
+                false,
+            ),
+            Err(e) => {
+                tracing::error!(error = ?e, "failed to mark the broken stations");
+                StatusMessage::new(e.to_string(), true)
+            }
+        });
+        self.report = Some(Report {
+            title: format!(" Checked {} streams, {broken} broken ", checks.len()),
+            lines: report_table(&checks),
+        });
+    }
+
+    /// Removes the stations marked broken from the favorites or the selected collection.
+    fn remove_broken(&mut self) {
+        let working = |station: &RadioStation| station.broken.is_none();
+        let removed = match self.view {
+            HomeView::Favorites => self.favorites.retain(working),
+            HomeView::Collections => {
+                let Some(name) = self
+                    .collection_tree
+                    .selected_collection(&self.collections)
+                    .map(str::to_string)
+                else {
+                    return;
+                };
+                self.collections.retain_stations(&name, working)
+            }
+            HomeView::Results | HomeView::History => {
+                self.status_message = Some(StatusMessage::new(
+                    "Broken stations can be removed from the favorites and collections".to_string(),
+                    false,
+                ));
+                return;
+            }
+        };
+        self.status_message = Some(match removed {
+            Ok(removed) => {
+                self.refresh_favorites(None);
+                self.collection_tree.clamp(&self.collections);
+                StatusMessage::new(format!("Removed {removed} broken stations"), false)
+            }
+            Err(e) => StatusMessage::new(e.to_string(), true),
+        });
+    }
+
+    /// Fetches the configured listings that are due in the background, they arrive with
+    /// [`Action::ListingFetched`].
+    fn refresh_listings(&mut self) {
+        let Some(tx) = self.action_tx.clone() else {
+            return;
+        };
+        if self
+            .last_listings_check
+            .is_some_and(|checked| checked.elapsed() < LISTINGS_INTERVAL)
+        {
+            return;
+        }
+        self.last_listings_check = Some(Instant::now());
+        let urls = self.config.listings.urls.clone();
+        let max_age = self.config.listings.refresh_interval();
+        let stale = self
+            .listings
+            .lock()
+            .map(|listings| listings.stale(&urls, max_age, unix_now()))
+            .unwrap_or_default();
+        for url in stale {
+            if !self.listings_pending.insert(url.to_string()) {
+                continue;
+            }
+            let url = url.to_string();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                tracing::info!(url, "fetching listing");
+                let client = reqwest::Client::new();
+                let action = match fetch_listing(&client, &url).await {
+                    Ok(stations) => Action::ListingFetched(url, stations),
+                    Err(e) => {
+                        tracing::error!(url, error = ?e, "failed to fetch listing");
+                        Action::ListingFailed(url, e.to_string())
+                    }
+                };
+                let _ = tx.send(action);
+            });
+        }
+    }
+
+    fn listing_fetched(&mut self, url: String, stations: Vec<RadioStation>) {
+        self.listings_pending.remove(&url);
+        let count = stations.len();
+        tracing::info!(url, count, "updated listing");
+        self.update_listings(|listings| listings.update(&url, stations, unix_now()));
+    }
+
+    /// Changes the listings with `update` and saves them in the background.
+    fn update_listings(&mut self, update: impl FnOnce(&mut Listings)) {
+        let Ok(mut listings) = self.listings.lock() else {
+            return;
+        };
+        update(&mut listings);
+        if listings.begin_save() {
+            save_listings(self.listings.clone());
+        }
+    }
+
+    /// Fetches the directory's checks of the station whose details are shown in the background
+    /// when the details open or another station is selected, unless they were fetched recently.
+    /// They arrive with [`Action::StationChecksFetched`].
+    fn fetch_selected_checks(&mut self) {
+        let Some(tx) = self.action_tx.clone() else {
+            return;
+        };
+        let shown = self.show_details.then(|| self.selected_station()).flatten();
+        let is_directory = shown.is_some_and(RadioStation::is_directory);
+        let shown = shown.map(|station| station.stationuuid.clone());
+        if shown == self.checks_looked_at {
+            return;
+        }
+        self.checks_looked_at.clone_from(&shown);
+        let Some(uuid) = shown.filter(|_| is_directory) else {
+            return;
+        };
+        if self.check_histories.is_fresh(&uuid, unix_now())
+            || !self.checks_pending.insert(uuid.clone())
+        {
+            return;
+        }
+        let api = self.radio_api.clone();
+        let seconds = self.config.directory.check_history_secs();
+        tokio::spawn(async move {
+            let action = match api.station_checks(&uuid, seconds).await {
+                Ok(checks) => Action::StationChecksFetched(uuid, checks),
+                Err(e) => {
+                    tracing::warn!(uuid, error = ?e, "failed to fetch station checks");
+                    Action::StationChecksFailed(uuid, e.to_string())
+                }
+            };
+            let _ = tx.send(action);
+        });
+    }
+
+    fn station_checks_fetched(&mut self, uuid: String, checks: Vec<DirectoryCheck>) {
+        self.checks_pending.remove(&uuid);
+        let history = CheckHistory::new(checks, unix_now());
+        if let Err(e) = self.check_histories.insert(&uuid, history) {
+            tracing::error!(uuid, error = ?e, "failed to save station checks");
+        }
+    }
+
+    /// Keeps the stations fetched before and tries again after the refresh interval.
+    fn listing_failed(&mut self, url: String, error: String) {
+        self.listings_pending.remove(&url);
+        self.update_listings(|listings| listings.mark_checked(&url, unix_now()));
+        self.status_message = Some(StatusMessage::new(
+            format!("Failed to fetch the listing {url}: {error}"),
+            true,
+        ));
+    }
+
+    /// The favorites, with custom stations as currently defined in the stations file, followed
+    /// by the custom stations that are not favorites.
+    fn favorite_stations(&self) -> Vec<RadioStation> {
+        let mut stations: Vec<RadioStation> = self
+            .favorites
+            .stations()
+            .iter()
+            .map(|s| {
+                self.custom_stations
+                    .get(&s.stationuuid)
+                    .unwrap_or(s)
+                    .clone()
+            })
+            .collect();
+        stations.extend(
+            self.custom_stations
+                .stations()
+                .iter()
+                .filter(|s| !self.favorites.contains(&s.stationuuid))
+                .cloned(),
+        );
+        stations
+    }
+
+    /// Rebuilds the favorites list after the favorites changed, selecting `uuid` or keeping the
+    /// current selection.
+    fn refresh_favorites(&mut self, uuid: Option<&str>) {
+        let selected = uuid.map(str::to_string).or_else(|| {
+            self.favorites_list
+                .selected()
+                .map(|s| s.stationuuid.clone())
+        });
+        let filter = self.favorites_list.filter().to_string();
+        self.favorites_list.set_items(self.favorite_stations());
+        self.favorites_list.set_filter(&filter);
+        if let Some(uuid) = selected {
+            self.favorites_list.select_uuid(&uuid);
+        }
     }
 
     pub fn next_item(&mut self) {
-        self.stations.next();
+        match self.list_mut() {
+            Some(list) => list.next(),
+            None => self.collection_tree.next(&self.collections),
+        }
     }
 
     pub fn previous_item(&mut self) {
-        self.stations.previous();
+        match self.list_mut() {
+            Some(list) => list.previous(),
+            None => self.collection_tree.previous(&self.collections),
+        }
     }
 
     pub fn select_station(&mut self) {
-        if let Some(station) = self.stations.select_station() {
+        let station = match self.list_mut() {
+            Some(list) => list.select_station(),
+            None => match self.collection_tree.selected(&self.collections) {
+                // <enter> on a collection folds it open or closed
+                Some(TreeNode::Collection(i)) => {
+                    let name = self.collections.collections()[i].name.clone();
+                    self.collection_tree.toggle(&name);
+                    None
+                }
+                _ => self
+                    .collection_tree
+                    .selected_station(&self.collections)
+                    .cloned(),
+            },
+        };
+        if let Some(station) = station {
             if let Some(now_paying) = self.now_playing.as_ref() {
                 if station.stationuuid == now_paying.station.stationuuid {
-                    self.stop_station();
+                    self.stop_station(EndReason::Stopped);
                     return;
                 }
             }
@@ -229,53 +1628,307 @@ impl Home {
         }
     }
 
-    fn play_station(&mut self, station: RadioStation) {
-        if let Some(tx) = &self.action_tx {
-            let mut play_station = station.clone();
-
-            let (shutdown_tx, mut _shutdown_rx) = broadcast::channel(1);
-            let download_shutdown_rx = shutdown_tx.subscribe();
-            let play_shutdown_rx = shutdown_tx.subscribe();
-            let volume_shutdown_rx = shutdown_tx.subscribe();
+    fn play_station(&mut self, station: RadioStation) {
+        if self.now_playing.is_some() {
+            self.stop_station(EndReason::Switched);
+        }
+        if self.config.directory.report_clicks {
+            self.report_click(&station);
+        }
+        if let Some(tx) = &self.action_tx {
+            let mut play_station = station.clone();
+
+            let (shutdown_tx, mut _shutdown_rx) = broadcast::channel(1);
+            let download_shutdown_rx = shutdown_tx.subscribe();
+            let play_shutdown_rx = shutdown_tx.subscribe();
+            let volume_shutdown_rx = shutdown_tx.subscribe();
+
+            let volume = self.volume;
+            let (volume_tx, volume_rx) = broadcast::channel::<f32>(10);
+            self.volume_tx = Some(volume_tx);
+
+            let play_shutdown_tx = shutdown_tx.clone();
+            let failed_tx = tx.clone();
+
+            let (title_tx, mut title_rx) = mpsc::unbounded_channel::<String>();
+            let track_tx = tx.clone();
+            let track_station = station.clone();
+            tokio::spawn(async move {
+                while let Some(title) = title_rx.recv().await {
+                    let entry = SongEntry::new(&track_station, &title);
+                    if track_tx.send(Action::TrackChanged(entry)).is_err() {
+                        break;
+                    }
+                }
+            });
+
+            let handle = tokio::spawn(async move {
+                tracing::info!("Starting play");
+                let result = play_station
+                    .play(
+                        download_shutdown_rx,
+                        play_shutdown_rx,
+                        volume,
+                        volume_rx,
+                        volume_shutdown_rx,
+                        title_tx,
+                    )
+                    .await;
+                if let Err(e) = result {
+                    tracing::error!(error = ?e, "failed to play station");
+                    let _ = failed_tx.send(Action::PlaybackFailed(
+                        play_station.stationuuid.clone(),
+                        e.to_string(),
+                    ));
+                }
+                tracing::info!("Done playing");
+            });
+
+            self.now_playing = Some(StreamState {
+                station,
+                started_at: unix_now(),
+                started: Instant::now(),
+                track: None,
+                stream_handle: handle,
+                shutdown_tx,
+            });
+
+            self.finish_processing();
+        }
+    }
+
+    /// Tells the directory the station was played. Failures are reported but never interrupt
+    /// playback.
+    fn report_click(&self, station: &RadioStation) {
+        let Some(tx) = self.action_tx.clone() else {
+            return;
+        };
+        if !station.is_directory() {
+            return;
+        }
+        let api = self.radio_api.clone();
+        let uuid = station.stationuuid.clone();
+        tokio::spawn(async move {
+            if let Err(e) = api.click(&uuid).await {
+                tracing::warn!(uuid, error = ?e, "failed to report click");
+                let _ = tx.send(Action::Error(format!("Failed to report click: {e}")));
+            }
+        });
+    }
+
+    /// Upvotes the selected station, unless it was voted for within the configured cooldown.
+    pub fn vote_selected_station(&mut self) {
+        let Some(tx) = self.action_tx.clone() else {
+            return;
+        };
+        let Some(station) = self.selected_station().cloned() else {
+            return;
+        };
+        if !station.is_directory() {
+            self.status_message = Some(StatusMessage::new(
+                format!("{} is not in the directory", station.name),
+                true,
+            ));
+            return;
+        }
+        let cooldown = self.config.directory.vote_cooldown();
+        if let Some(remaining) = self
+            .vote_log
+            .cooldown_remaining(&station.stationuuid, cooldown)
+        {
+            self.status_message = Some(StatusMessage::new(
+                format!(
+                    "Already voted for {}, try again in {}m",
+                    station.name,
+                    remaining.as_secs().div_ceil(60)
+                ),
+                true,
+            ));
+            return;
+        }
+        // The cooldown starts once the directory answers, until then the vote is pending
+        if !self.votes_pending.insert(station.stationuuid.clone()) {
+            self.status_message = Some(StatusMessage::new(
+                format!("Already voting for {}", station.name),
+                false,
+            ));
+            return;
+        }
+
+        let api = self.radio_api.clone();
+        let uuid = station.stationuuid.clone();
+        let name = station.name.clone();
+        tokio::spawn(async move {
+            let action = match api.vote(&uuid).await {
+                Ok(()) => {
+                    let _ = tx.send(Action::Notify(format!("Voted for {name}")));
+                    Action::VoteRegistered(uuid)
+                }
+                Err(e) => Action::VoteFailed(uuid, format!("Failed to vote for {name}: {e}")),
+            };
+            let _ = tx.send(action);
+        });
+    }
+
+    fn vote_failed(&mut self, uuid: String, error: String) {
+        self.votes_pending.remove(&uuid);
+        self.status_message = Some(StatusMessage::new(error, true));
+    }
+
+    pub fn vote_registered(&mut self, uuid: String) {
+        self.votes_pending.remove(&uuid);
+        for list in [&mut self.stations, &mut self.favorites_list] {
+            list.update_station(&uuid, |station| station.votes += 1);
+        }
+        if let Err(e) = self
+            .vote_log
+            .record(&uuid, self.config.directory.vote_cooldown())
+        {
+            tracing::error!(error = ?e, "failed to save vote log");
+        }
+    }
+
+    pub fn stop_station(&mut self, reason: EndReason) {
+        if let Some(state) = self.now_playing.take() {
+            // Nobody listens anymore if playback already failed
+            let _ = state.shutdown_tx.send(());
+            state.stream_handle.abort();
+            self.record_history(state, reason);
+        }
+        self.volume_tx = None;
+    }
+
+    /// Stops the station that failed to play, unless another one was started meanwhile.
+    pub fn playback_failed(&mut self, uuid: String, error: String) {
+        let playing = self
+            .now_playing
+            .as_ref()
+            .is_some_and(|state| state.station.stationuuid == uuid);
+        if playing {
+            self.stop_station(EndReason::Failed);
+            self.status_message = Some(StatusMessage::new(
+                format!("Playback failed: {error}"),
+                true,
+            ));
+        }
+    }
 
-            let volume = self.volume;
-            let (volume_tx, volume_rx) = broadcast::channel::<f32>(10);
-            self.volume_tx = Some(volume_tx);
+    fn session(&self) -> Session {
+        Session {
+            station: self.now_playing.as_ref().map(|state| state.station.clone()),
+            volume: self.volume,
+            search: self.last_search.clone(),
+            results: self.stations.items().to_vec(),
+            selected: match self.list() {
+                Some(list) => list.state.selected(),
+                None => self.collection_tree.state.selected(),
+            },
+            view: self.view,
+            ..Default::default()
+        }
+    }
 
-            let play_shutdown_tx = shutdown_tx.clone();
+    pub fn save_session(&mut self) {
+        self.last_session_save = Instant::now();
+        if let Err(e) = self
+            .session()
+            .save(&get_data_dir().join(Session::FILE_NAME))
+        {
+            tracing::error!(error = ?e, "failed to save session");
+        }
+    }
 
-            let handle = tokio::spawn(async move {
-                tracing::info!("Starting play");
-                play_station
-                    .play(
-                        download_shutdown_rx,
-                        play_shutdown_rx,
-                        volume,
-                        volume_rx,
-                        volume_shutdown_rx,
-                    )
-                    .await
-                    .unwrap();
-                tracing::info!("Done playing");
-            });
+    /// Restores the view, results, selection and volume of the last session, and starts the
+    /// last station if `autoplay` is set.
+    pub fn restore_session(&mut self, autoplay: bool) {
+        let session = match Session::load(&get_data_dir().join(Session::FILE_NAME)) {
+            Ok(Some(session)) => session,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::error!(error = ?e, "failed to load session");
+                self.status_message = Some(StatusMessage::new(
+                    format!("Failed to restore the session: {e}"),
+                    true,
+                ));
+                return;
+            }
+        };
+        tracing::info!(view = ?session.view, "restoring session");
+        self.volume = session.volume.clamp(VOLUME_MIN, VOLUME_MAX);
+        self.last_search = session.search;
+        self.all_results = session.results;
+        if !self.all_results.is_empty() {
+            self.show_results();
+        }
+        self.view = session.view;
+        // The results may be fewer now that duplicates are grouped or failing ones hidden
+        match self.list_mut() {
+            Some(list) => {
+                let len = list.visible_len();
+                let selected = session.selected.map(|i| i.min(len.saturating_sub(1)));
+                list.state.select(selected.filter(|_| len > 0));
+            }
+            None => {
+                self.collection_tree.state.select(session.selected);
+                self.collection_tree.clamp(&self.collections);
+            }
+        }
+        if autoplay {
+            if let Some(station) = session.station {
+                self.play_station(station);
+            }
+        }
+    }
 
-            self.now_playing = Some(StreamState {
-                station,
-                stream_handle: handle,
-                shutdown_tx,
-            });
+    /// Shows the new track if it belongs to the station playing.
+    pub fn track_changed(&mut self, entry: SongEntry) {
+        if let Some(state) = self.now_playing.as_mut() {
+            if state.station.stationuuid == entry.stationuuid {
+                state.track = Some(entry.raw);
+            }
+        }
+    }
 
-            tx.send(Action::ExitProcessing).unwrap();
+    fn record_history(&mut self, state: StreamState, end_reason: EndReason) {
+        if !self.config.history.enabled {
+            return;
+        }
+        let entry = HistoryEntry {
+            station: state.station,
+            started_at: state.started_at,
+            duration_secs: state.started.elapsed().as_secs(),
+            end_reason,
+        };
+        if let Err(e) = self.history.record(entry, self.config.history.retention()) {
+            tracing::error!(error = ?e, "failed to save history");
         }
+        self.refresh_history();
     }
 
-    pub fn stop_station(&mut self) {
-        if let Some(state) = self.now_playing.as_ref() {
-            state.shutdown_tx.send(()).expect("failed to send stop");
-            state.stream_handle.abort()
+    pub fn clear_history(&mut self) {
+        if self.view != HomeView::History {
+            return;
         }
-        self.now_playing = None;
-        self.volume_tx = None;
+        match self.history.clear() {
+            Ok(()) => {
+                self.status_message = Some(StatusMessage::new("History cleared".to_string(), false))
+            }
+            Err(e) => {
+                tracing::error!(error = ?e, "failed to clear history");
+                self.status_message = Some(StatusMessage::new(
+                    format!("Failed to clear history: {e}"),
+                    true,
+                ));
+            }
+        }
+        self.refresh_history();
+    }
+
+    /// Rebuilds the history list, keeping the filter. The newest entry is selected.
+    fn refresh_history(&mut self) {
+        let filter = self.history_list.filter().to_string();
+        self.history_list.set_items(history_stations(&self.history));
+        self.history_list.set_filter(&filter);
     }
 
     /// Increase volume to a max of `1.0`
@@ -299,47 +1952,360 @@ impl Home {
     }
 }
 
+impl Home {
+    fn draw_filter_prompt(&self, f: &mut Frame<'_>, area: Rect) {
+        let Some(list) = self.list() else {
+            return;
+        };
+        let prefix = "filter: ";
+        let matches = format!("  {}/{}", list.visible_len(), list.items().len());
+        let line = Line::from(vec![
+            Span::styled(prefix, Style::default().fg(Color::Yellow)),
+            Span::raw(list.filter().to_string()),
+            Span::styled(matches, Style::default().fg(Color::DarkGray)),
+        ]);
+        f.render_widget(Paragraph::new(line).bg(NORMAL_ROW_COLOR), area);
+
+        if self.mode == Mode::Insert {
+            f.set_cursor(
+                (area.x + prefix.len() as u16 + self.input.visual_cursor() as u16)
+                    .min(area.x + area.width.saturating_sub(1)),
+                area.y,
+            )
+        }
+    }
+
+    fn draw_prompt(&self, f: &mut Frame<'_>, area: Rect) {
+        let Some(prompt) = &self.prompt else {
+            return;
+        };
+        let label = prompt.kind.label();
+        let hints = self.prompt_hints().join("  ");
+        let line = Line::from(vec![
+            Span::styled(label.clone(), Style::default().fg(Color::Yellow)),
+            Span::raw(prompt.input.value().to_string()),
+            Span::styled(format!("  {hints}"), Style::default().fg(Color::DarkGray)),
+        ]);
+        f.render_widget(Paragraph::new(line).bg(NORMAL_ROW_COLOR), area);
+        f.set_cursor(
+            (area.x + label.chars().count() as u16 + prompt.input.visual_cursor() as u16)
+                .min(area.x + area.width.saturating_sub(1)),
+            area.y,
+        )
+    }
+
+    /// The list title: the search progress for the results, the count for the favorites.
+    fn view_title(&self) -> String {
+        match self.view {
+            HomeView::Results => {
+                let hidden = self
+                    .all_results
+                    .len()
+                    .saturating_sub(self.stations.items().len());
+                match (&self.search_status, hidden) {
+                    (SearchStatus::Found(count), 1..) => {
+                        format!(" Stations ({count}, {hidden} hidden) ")
+                    }
+                    (status, _) => status.title(),
+                }
+            }
+            HomeView::Favorites => format!(" Favorites ({}) ", self.favorites_list.items().len()),
+            HomeView::History => format!(" History ({}) ", self.history.entries().len()),
+            HomeView::Collections => {
+                format!(" Collections ({}) ", self.collections.collections().len())
+            }
+        }
+    }
+
+    /// Builds the rows of the history table: when, which station, for how long and why it ended.
+    fn history_rows(&self) -> Vec<Row<'static>> {
+        let now = unix_now();
+        self.history_list
+            .visible_items()
+            .enumerate()
+            .filter_map(|(row_index, (i, _))| {
+                let entry = self.history.entries().get(i)?;
+                let bg_color = match row_index % 2 {
+                    0 => NORMAL_ROW_COLOR,
+                    _ => ALT_ROW_COLOR,
+                };
+                let reason_color = match entry.end_reason {
+                    EndReason::Failed => Color::Red,
+                    _ => Color::DarkGray,
+                };
+                Some(
+                    Row::new(vec![
+                        Cell::from(format_age(entry.started_at, now)),
+                        Cell::from(entry.station.name.trim().to_string()),
+                        Cell::from(format_duration(entry.duration_secs)),
+                        Cell::from(entry.end_reason.to_string()).fg(reason_color),
+                    ])
+                    .fg(TEXT_COLOR)
+                    .bg(bg_color),
+                )
+            })
+            .collect()
+    }
+
+    fn draw_details(&self, f: &mut Frame<'_>, area: Rect) {
+        let block = Block::default()
+            .borders(Borders::LEFT)
+            .title(" Details ")
+            .fg(TEXT_COLOR)
+            .bg(NORMAL_ROW_COLOR);
+
+        let lines = match self.selected_station() {
+            Some(station) => {
+                let mut lines = station.to_details();
+                if let Some(history) = self.check_histories.get(&station.stationuuid) {
+                    lines.push(Line::default());
+                    lines.extend(
+                        history.to_details(self.config.directory.check_history_secs(), unix_now()),
+                    );
+                }
+                let alternates = self
+                    .alternates
+                    .get(&station.stationuuid)
+                    .filter(|_| self.view == HomeView::Results);
+                if let Some(alternates) = alternates {
+                    lines.push(Line::default());
+                    lines.extend(alternates_details(alternates));
+                }
+                lines
+            }
+            None => vec![Line::styled(
+                "Select a station to see its details",
+                Style::default().fg(Color::DarkGray),
+            )],
+        };
+
+        let details = Paragraph::new(lines)
+            .block(block)
+            .wrap(ratatui::widgets::Wrap { trim: false });
+        f.render_widget(details, area);
+    }
+
+    /// The report of the last refresh or check, over the list.
+    fn draw_report(&self, f: &mut Frame<'_>, area: Rect) {
+        let Some(report) = &self.report else {
+            return;
+        };
+        let area = area.inner(Margin {
+            horizontal: 2,
+            vertical: 1,
+        });
+        f.render_widget(Clear, area);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(report.title.clone())
+            .title_bottom(Line::styled(
+                " esc close ",
+                Style::default().fg(Color::DarkGray),
+            ))
+            .fg(TEXT_COLOR)
+            .bg(NORMAL_ROW_COLOR);
+        let lines: Vec<Line> = report
+            .lines
+            .iter()
+            .map(|line| Line::from(line.clone()))
+            .collect();
+        let paragraph = Paragraph::new(lines)
+            .block(block)
+            .wrap(ratatui::widgets::Wrap { trim: false });
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// The stations of the history entries, in the same order.
+fn history_stations(history: &History) -> Vec<RadioStation> {
+    history
+        .entries()
+        .iter()
+        .map(|entry| entry.station.clone())
+        .collect()
+}
+
 impl Component for Home {
     fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
         self.action_tx = Some(tx);
         Ok(())
     }
 
-    // fn handle_key_events(&mut self, key: KeyEvent) -> Result<Option<Action>> {
-    //     let action = match self.mode {
-    //         Mode::Normal | Mode::Processing => return Ok(None),
-    //         Mode::Insert => match key.code {
-    //             KeyCode::Esc => Action::EnterNormal,
-    //             KeyCode::Enter => {
-    //                 if let Some(sender) = &self.action_tx {
-    //                     if let Err(e) =
-    //                         sender.send(Action::CompleteInput(self.input.value().to_string()))
-    //                     {
-    //                         error!("Failed to send action: {:?}", e);
-    //                     }
-    //                 }
-    //                 Action::EnterNormal
-    //             }
-    //             _ => {
-    //                 self.input.handle_event(&crossterm::event::Event::Key(key));
-    //                 Action::Update
-    //             }
-    //         },
-    //     };
-    //     Ok(Some(action))
-    // }
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.stations.columns = config.stations.columns.clone();
+        self.favorites_list.columns = config.stations.columns.clone();
+        self.stations
+            .sort_by(config.stations.sort.map(|column| SortOrder {
+                column,
+                descending: config.stations.sort_descending,
+            }));
+        self.config = config;
+        Ok(())
+    }
+
+    fn init(&mut self, _area: Rect) -> Result<()> {
+        self.reload_custom_stations();
+        if self.config.session.restore {
+            self.restore_session(self.config.session.autoplay);
+        }
+        Ok(())
+    }
+
+    fn handle_key_events(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        let action = match self.mode {
+            Mode::Normal | Mode::Processing => return Ok(None),
+            // <esc> and <enter> are bound to actions in `AppMode::Prompt`.
+            Mode::Prompt => match (key.code, self.prompt.as_mut()) {
+                (KeyCode::Esc | KeyCode::Enter, _) | (_, None) => return Ok(None),
+                (_, Some(prompt)) => {
+                    prompt
+                        .input
+                        .handle_event(&crossterm::event::Event::Key(key));
+                    Action::Update
+                }
+            },
+            // <esc>, <enter> and the arrow keys are bound to actions in `AppMode::Filter`.
+            Mode::Insert => match key.code {
+                KeyCode::Esc | KeyCode::Enter | KeyCode::Up | KeyCode::Down => return Ok(None),
+                _ => {
+                    self.input.handle_event(&crossterm::event::Event::Key(key));
+                    let filter = self.input.value().to_string();
+                    if let Some(list) = self.list_mut() {
+                        list.set_filter(&filter);
+                    }
+                    Action::Update
+                }
+            },
+        };
+        Ok(Some(action))
+    }
+
+    fn handle_paste_events(&mut self, text: String) -> Result<Option<Action>> {
+        // Inputs are one line, a pasted url may come with a line break
+        let text = text.lines().next().unwrap_or_default().trim();
+        let input = match (self.mode, self.prompt.as_mut()) {
+            (Mode::Prompt, Some(prompt)) => &mut prompt.input,
+            (Mode::Insert, _) => &mut self.input,
+            _ => return Ok(None),
+        };
+        for c in text.chars() {
+            input.handle(InputRequest::InsertChar(c));
+        }
+        if self.mode == Mode::Insert {
+            let filter = self.input.value().to_string();
+            if let Some(list) = self.list_mut() {
+                list.set_filter(&filter);
+            }
+        }
+        Ok(Some(Action::Update))
+    }
 
     fn update(&mut self, action: Action) -> Result<Option<Action>> {
         match action {
             Action::Tick => self.tick(),
             Action::Render => self.render_tick(),
             Action::ToggleShowHelp => self.show_help = !self.show_help,
+            Action::ToggleStationDetails => self.show_details = !self.show_details,
+            // The history keeps its order, rows are matched to entries by position
+            Action::CycleSortColumn if self.view != HomeView::History => {
+                if let Some(list) = self.list_mut() {
+                    list.cycle_sort()
+                }
+            }
+            Action::EnterFilter if self.mode == Mode::Normal => {
+                let Some(filter) = self.list().map(|list| list.filter().to_string()) else {
+                    return Ok(None);
+                };
+                self.mode = Mode::Insert;
+                self.input = Input::new(filter);
+                return Ok(Some(Action::Mode(AppMode::Filter)));
+            }
+            Action::ApplyFilter => {
+                self.mode = Mode::Normal;
+                return Ok(Some(Action::Mode(AppMode::Home)));
+            }
+            Action::ClearFilter => {
+                self.input.reset();
+                if let Some(list) = self.list_mut() {
+                    list.clear_filter();
+                }
+                if self.mode == Mode::Insert {
+                    self.mode = Mode::Normal;
+                    return Ok(Some(Action::Mode(AppMode::Home)));
+                }
+            }
+            Action::ReverseSort if self.view != HomeView::History => {
+                if let Some(list) = self.list_mut() {
+                    list.reverse_sort()
+                }
+            }
             Action::NextItem => self.next_item(),
             Action::PreviousItem => self.previous_item(),
             Action::Search(s) => self.search_stations(s),
-            Action::StationsFound(stations) => self.apply_stations(stations),
+            Action::CancelSearch if self.report.is_some() => self.report = None,
+            Action::CancelSearch => self.cancel_search(),
+            Action::StationsFound(id, stations) => self.search_found(id, stations),
+            Action::SearchFailed(id, error) => self.search_failed(id, error),
             Action::PlaySelectedStation => self.select_station(),
-            Action::StopPlayingStation => self.stop_station(),
+            Action::StopPlayingStation => self.stop_station(EndReason::Stopped),
+            Action::PlaybackFailed(uuid, error) => self.playback_failed(uuid, error),
+            Action::TrackChanged(entry) => self.track_changed(entry),
+            Action::ShowHistory => self.show_view(HomeView::History),
+            Action::ClearHistory => self.clear_history(),
+            Action::Quit => {
+                self.save_session();
+                self.stop_station(EndReason::Stopped);
+            }
+            Action::Suspend => self.save_session(),
+            Action::VoteSelectedStation => self.vote_selected_station(),
+            Action::ToggleFavorite => self.toggle_favorite(),
+            Action::MoveItemUp if self.view == HomeView::Collections => {
+                self.move_in_collections(-1)
+            }
+            Action::MoveItemDown if self.view == HomeView::Collections => {
+                self.move_in_collections(1)
+            }
+            Action::MoveItemUp => self.move_favorite(-1),
+            Action::MoveItemDown => self.move_favorite(1),
+            Action::ShowCollections => self.show_view(HomeView::Collections),
+            Action::NewCollection => return Ok(self.open_prompt(PromptKind::NewCollection, "")),
+            Action::RenameCollection => return Ok(self.rename_collection()),
+            Action::DeleteSelected => return Ok(self.delete_selected()),
+            Action::AddToCollection => return Ok(self.add_to_collection()),
+            Action::AddUrlToCollection => return Ok(self.add_url_to_collection()),
+            Action::ExportList => return Ok(self.export_list()),
+            Action::ImportPlaylist => return Ok(self.open_prompt(PromptKind::Import, "")),
+            Action::PlaylistImported(name, stations, matched) => {
+                self.playlist_imported(name, stations, matched)
+            }
+            Action::OpenUrl => return Ok(self.open_prompt(PromptKind::OpenUrl, "")),
+            Action::UrlOpened(station) => return Ok(self.url_opened(*station)),
+            Action::ListingFetched(url, stations) => self.listing_fetched(url, stations),
+            Action::ListingFailed(url, error) => self.listing_failed(url, error),
+            Action::StationChecksFetched(uuid, checks) => self.station_checks_fetched(uuid, checks),
+            // Left pending, so that it is not asked for again until the next start
+            Action::StationChecksFailed(..) => (),
+            Action::SyncDirectory => self.sync_directory(),
+            Action::DirectorySynced(report) => self.sync_finished(report, false),
+            Action::SyncFailed(error) => self.sync_finished(error, true),
+            Action::RefreshSaved => self.refresh_saved(),
+            Action::SavedStationsFetched(uuids, found) => self.saved_stations_fetched(uuids, found),
+            Action::ToggleAllResults => self.toggle_all_results(),
+            Action::CheckStreams => self.check_listed_streams(),
+            Action::StreamsChecked(checks) => self.streams_checked(checks),
+            Action::RemoveBroken => self.remove_broken(),
+            Action::ConfirmPrompt => return Ok(self.confirm_prompt()),
+            Action::CancelPrompt => return Ok(self.close_prompt()),
+            Action::NextView => self.show_view(self.view.next()),
+            Action::ShowResults => self.show_view(HomeView::Results),
+            Action::ShowFavorites => self.show_view(HomeView::Favorites),
+            Action::VoteRegistered(uuid) => self.vote_registered(uuid),
+            Action::VoteFailed(uuid, error) => self.vote_failed(uuid, error),
+            Action::Notify(message) => {
+                self.status_message = Some(StatusMessage::new(message, false))
+            }
+            Action::Error(message) => self.status_message = Some(StatusMessage::new(message, true)),
             // Action::StreamStarted(station) => self.start_stream(station),
             Action::EnterNormal => {
                 self.mode = Mode::Normal;
@@ -351,8 +2317,7 @@ impl Component for Home {
                 self.mode = Mode::Processing;
             }
             Action::ExitProcessing => {
-                // TODO: Make this go to previous mode instead
-                self.mode = Mode::Normal;
+                self.finish_processing();
             }
             Action::IncreaseVolume => {
                 self.increase_volume();
@@ -372,10 +2337,16 @@ impl Component for Home {
             min = 4
         }
 
+        // The track title gets a line of its own
+        let now_playing_height = match &self.now_playing {
+            Some(state) if state.track.is_some() => 4,
+            _ => 3,
+        };
+
         let rects = Layout::default()
             .constraints(
                 [
-                    Constraint::Min(3),
+                    Constraint::Min(now_playing_height),
                     Constraint::Percentage(100),
                     Constraint::Min(min),
                 ]
@@ -416,13 +2387,30 @@ impl Component for Home {
             .bg(NORMAL_ROW_COLOR);
 
         if let Some(radio_station) = self.now_playing.as_ref() {
+            let station = &radio_station.station;
+            let details = [
+                format!("{} {}k", station.codec, station.bitrate),
+                station.country.clone(),
+                station.tags.join(", "),
+            ]
+            .into_iter()
+            .filter(|detail| !detail.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join(" · ");
             lines.push(Line::from(vec![
                 throbber,
                 Span::styled(
                     radio_station.get_name().to_owned(),
                     Style::default().fg(Color::Red),
                 ),
+                Span::styled(format!("  {details}"), Style::default().fg(Color::DarkGray)),
             ]));
+            if let Some(track) = &radio_station.track {
+                lines.push(Line::from(vec![
+                    Span::raw("  ♪ "),
+                    Span::styled(track.clone(), Style::default().fg(TEXT_COLOR)),
+                ]));
+            }
         } else {
             lines.push(Line::from(vec![Span::styled(
                 "Nothing...",
@@ -436,20 +2424,63 @@ impl Component for Home {
 
         let inner_block = Block::new()
             .borders(Borders::NONE)
+            .title(self.view_title())
             .fg(TEXT_COLOR)
             .bg(NORMAL_ROW_COLOR);
 
-        // Iterate through all elements in the `items` and stylize them.
-        let items: Vec<ListItem> = self
-            .stations
-            .items
-            .iter()
-            .enumerate()
-            .map(|(i, station)| station.to_list_item(i))
-            .collect();
+        let list_area = if self.show_details {
+            let panes =
+                Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                    .split(rects[1]);
+            self.draw_details(f, panes[1]);
+            panes[0]
+        } else {
+            rects[1]
+        };
 
-        // Create a List from all list items and highlight the currently selected one
-        let items = List::new(items)
+        // The filter prompt or the open prompt take the last line of the list
+        let filtering =
+            self.mode == Mode::Insert || self.list().is_some_and(|list| !list.filter().is_empty());
+        let list_area = if filtering || self.prompt.is_some() {
+            let [list_area, prompt_area] =
+                Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(list_area);
+            if self.prompt.is_some() {
+                self.draw_prompt(f, prompt_area);
+            } else {
+                self.draw_filter_prompt(f, prompt_area);
+            }
+            list_area
+        } else {
+            list_area
+        };
+
+        let width = list_area.width;
+        let is_favorite = |s: &RadioStation| self.favorites.contains(&s.stationuuid);
+        let table = if let Some(list) = self.list().filter(|_| self.view != HomeView::History) {
+            let columns = station_table::visible_columns(&list.columns, width);
+            let rows = list.rows(&columns, width, is_favorite);
+            Table::new(rows, station_table::widths(&columns, width))
+                .header(station_table::header(&columns, list.sort))
+        } else if self.view == HomeView::History {
+            let header = Row::new(vec!["When", "Station", "Duration", "Ended"]).style(
+                Style::default()
+                    .fg(tailwind::SLATE.c400)
+                    .add_modifier(Modifier::BOLD),
+            );
+            let widths = [
+                Constraint::Length(10),
+                Constraint::Fill(1),
+                Constraint::Length(9),
+                Constraint::Length(9),
+            ];
+            Table::new(self.history_rows(), widths).header(header)
+        } else {
+            let rows = self.collection_tree.rows(&self.collections, is_favorite);
+            Table::new(rows, [Constraint::Fill(1)])
+        };
+
+        // Highlight the currently selected row
+        let table = table
             .block(inner_block)
             .highlight_style(
                 Style::default()
@@ -460,20 +2491,31 @@ impl Component for Home {
             .highlight_symbol(">")
             .highlight_spacing(HighlightSpacing::Always);
 
-        f.render_stateful_widget(items, rects[1], &mut self.stations.state);
+        let state = match self.view {
+            HomeView::Results => &mut self.stations.state,
+            HomeView::Favorites => &mut self.favorites_list.state,
+            HomeView::History => &mut self.history_list.state,
+            HomeView::Collections => &mut self.collection_tree.state,
+        };
+        f.render_stateful_widget(table, list_area, state);
+        self.draw_report(f, list_area);
 
         // BOTTOM
-        if self.mode == Mode::Insert {
-            f.set_cursor(
-                (rects[1].x + 1 + self.input.cursor() as u16).min(rects[1].x + rects[1].width - 2),
-                rects[1].y + 1,
-            )
-        }
-
         let width = rects[1].width.max(5) - 3; // keep 2 for borders and 1 for cursor
         let mut lines = vec![];
 
         let mut help_block = Block::default().borders(Borders::ALL).bg(NORMAL_ROW_COLOR);
+        if let Some(message) = &self.status_message {
+            let color = if message.is_error {
+                Color::Red
+            } else {
+                COMPLETED_TEXT_COLOR
+            };
+            help_block = help_block.title_bottom(
+                Line::styled(format!(" {} ", message.text), Style::default().fg(color))
+                    .right_aligned(),
+            );
+        }
         let spacer = Span::raw("   ");
 
         let default_help = Line::from(vec![
@@ -513,6 +2555,51 @@ impl Component for Home {
             Span::raw(" "),
             Span::styled("play/stop", Style::default().fg(Color::DarkGray)),
             spacer.clone(),
+            Span::styled(
+                "i",
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .fg(Color::Gray),
+            ),
+            Span::raw(" "),
+            Span::styled("details", Style::default().fg(Color::DarkGray)),
+            spacer.clone(),
+            Span::styled(
+                "s",
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .fg(Color::Gray),
+            ),
+            Span::raw(" "),
+            Span::styled("sort", Style::default().fg(Color::DarkGray)),
+            spacer.clone(),
+            Span::styled(
+                "v",
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .fg(Color::Gray),
+            ),
+            Span::raw(" "),
+            Span::styled("vote", Style::default().fg(Color::DarkGray)),
+            spacer.clone(),
+            Span::styled(
+                "*",
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .fg(Color::Gray),
+            ),
+            Span::raw(" "),
+            Span::styled("favorite", Style::default().fg(Color::DarkGray)),
+            spacer.clone(),
+            Span::styled(
+                "tab",
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .fg(Color::Gray),
+            ),
+            Span::raw(" "),
+            Span::styled("views", Style::default().fg(Color::DarkGray)),
+            spacer.clone(),
             Span::styled(
                 "q",
                 Style::default()
@@ -562,6 +2649,86 @@ impl Component for Home {
                 Span::raw(" "),
                 Span::styled("Next Field", Style::default().fg(Color::DarkGray)),
                 spacer.clone(),
+                Span::styled(
+                    "c",
+                    Style::default()
+                        .add_modifier(Modifier::BOLD)
+                        .fg(Color::Gray),
+                ),
+                Span::raw(" "),
+                Span::styled("add to collection", Style::default().fg(Color::DarkGray)),
+                spacer.clone(),
+                Span::styled(
+                    "n/r/d",
+                    Style::default()
+                        .add_modifier(Modifier::BOLD)
+                        .fg(Color::Gray),
+                ),
+                Span::raw(" "),
+                Span::styled("new/rename/delete", Style::default().fg(Color::DarkGray)),
+                spacer.clone(),
+                Span::styled(
+                    "u",
+                    Style::default()
+                        .add_modifier(Modifier::BOLD)
+                        .fg(Color::Gray),
+                ),
+                Span::raw(" "),
+                Span::styled("add url", Style::default().fg(Color::DarkGray)),
+                spacer.clone(),
+                Span::styled(
+                    "e/o",
+                    Style::default()
+                        .add_modifier(Modifier::BOLD)
+                        .fg(Color::Gray),
+                ),
+                Span::raw(" "),
+                Span::styled("export/import", Style::default().fg(Color::DarkGray)),
+                spacer.clone(),
+                Span::styled(
+                    "O",
+                    Style::default()
+                        .add_modifier(Modifier::BOLD)
+                        .fg(Color::Gray),
+                ),
+                Span::raw(" "),
+                Span::styled("open url", Style::default().fg(Color::DarkGray)),
+                spacer.clone(),
+                Span::styled(
+                    "D",
+                    Style::default()
+                        .add_modifier(Modifier::BOLD)
+                        .fg(Color::Gray),
+                ),
+                Span::raw(" "),
+                Span::styled("sync directory", Style::default().fg(Color::DarkGray)),
+                spacer.clone(),
+                Span::styled(
+                    "R",
+                    Style::default()
+                        .add_modifier(Modifier::BOLD)
+                        .fg(Color::Gray),
+                ),
+                Span::raw(" "),
+                Span::styled("refresh saved", Style::default().fg(Color::DarkGray)),
+                spacer.clone(),
+                Span::styled(
+                    "H/X",
+                    Style::default()
+                        .add_modifier(Modifier::BOLD)
+                        .fg(Color::Gray),
+                ),
+                Span::raw(" "),
+                Span::styled("check/remove broken", Style::default().fg(Color::DarkGray)),
+                spacer.clone(),
+                Span::styled(
+                    "a",
+                    Style::default()
+                        .add_modifier(Modifier::BOLD)
+                        .fg(Color::Gray),
+                ),
+                Span::raw(" "),
+                Span::styled("all results", Style::default().fg(Color::DarkGray)),
             ]));
             lines.push(default_help);
         } else {
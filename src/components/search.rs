@@ -1,43 +1,115 @@
-use std::{collections::HashMap, str::FromStr, time::Instant};
+use std::{collections::HashMap, str::FromStr, time::Duration, time::Instant};
 
 use color_eyre::eyre::Result;
 use color_eyre::owo_colors::OwoColorize;
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{prelude::*, widgets::*};
-use tokio::sync::mpsc::UnboundedSender;
+use tokio::{sync::mpsc::UnboundedSender, task::JoinHandle};
 use tui_input::{backend::crossterm::EventHandler, Input};
 
 use super::Component;
 use crate::mode::Mode as AppMode;
-use crate::models::{Order, SearchParam};
-use crate::{action::Action, tui::Frame};
+use crate::models::{
+    parse_query, query_hints, to_query, Order, QueryError, SavedSearches, SearchParam,
+};
+use crate::utils::{get_data_dir, load_or_set_aside};
+use crate::{action::Action, config::Config, tui::Frame};
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 enum InputMode {
     #[default]
     None,
+    Query,
     Name,
     Country,
     Language,
+    CountryCode,
     Tags,
+    Codec,
+    BitrateMin,
+    BitrateMax,
     Limit,
     Order,
     Reverse,
+    NameExact,
+    IsHttps,
+    HasGeoInfo,
 }
 
-#[derive(Debug, Clone)]
+impl InputMode {
+    /// The order in which <Tab> moves through the form fields.
+    const FIELDS: [InputMode; 15] = [
+        InputMode::Query,
+        InputMode::Name,
+        InputMode::Country,
+        InputMode::Language,
+        InputMode::CountryCode,
+        InputMode::Tags,
+        InputMode::Codec,
+        InputMode::BitrateMin,
+        InputMode::BitrateMax,
+        InputMode::Limit,
+        InputMode::Order,
+        InputMode::Reverse,
+        InputMode::NameExact,
+        InputMode::IsHttps,
+        InputMode::HasGeoInfo,
+    ];
+
+    fn position(&self) -> Option<usize> {
+        Self::FIELDS.iter().position(|mode| mode == self)
+    }
+
+    fn next(&self) -> Self {
+        match self.position() {
+            Some(i) => Self::FIELDS[(i + 1) % Self::FIELDS.len()].clone(),
+            None => InputMode::None,
+        }
+    }
+
+    fn previous(&self) -> Self {
+        match self.position() {
+            Some(i) => Self::FIELDS[(i + Self::FIELDS.len() - 1) % Self::FIELDS.len()].clone(),
+            None => InputMode::None,
+        }
+    }
+}
+
+#[derive(Debug)]
 pub struct Search {
     pub action_tx: Option<UnboundedSender<Action>>,
     show_search: bool,
+    /// Whether edits to the query or name fields search as you type.
+    live: bool,
+    live_search_handle: Option<JoinHandle<()>>,
+    live_debounce: Duration,
     pub keymap: HashMap<KeyEvent, Action>,
     input_mode: InputMode,
+    query_error: Option<QueryError>,
+    search_query: Input,
     search_name: Input,
     search_country: Input,
     search_language: Input,
+    search_country_code: Input,
     search_tags: Input,
+    search_codec: Input,
+    search_bitrate_min: Input,
+    search_bitrate_max: Input,
     search_limit: Input,
     search_order: Input,
     search_reverse: Input,
+    search_name_exact: Input,
+    search_is_https: Input,
+    search_has_geo_info: Input,
+    searches: SavedSearches,
+    /// How many recent searches are remembered.
+    history_size: usize,
+    /// The index into the recent searches recalled with <Up>/<Down>.
+    recall: Option<usize>,
+    /// The name being typed to save the form as a named search.
+    save_as: Option<Input>,
+    show_saved: bool,
+    saved_state: ListState,
 }
 
 impl Default for Search {
@@ -51,38 +123,123 @@ impl Search {
         Self {
             action_tx: None,
             show_search: false,
+            live: false,
+            live_search_handle: None,
+            live_debounce: Default::default(),
             keymap: Default::default(),
             input_mode: Default::default(),
+            query_error: None,
+            search_query: Default::default(),
             search_name: Default::default(),
             search_country: Default::default(),
             search_language: Default::default(),
+            search_country_code: Default::default(),
             search_tags: Default::default(),
+            search_codec: Default::default(),
+            search_bitrate_min: Default::default(),
+            search_bitrate_max: Default::default(),
             search_limit: Default::default(),
             search_order: Default::default(),
             search_reverse: Default::default(),
+            search_name_exact: Default::default(),
+            search_is_https: Default::default(),
+            search_has_geo_info: Default::default(),
+            searches: Default::default(),
+            history_size: 0,
+            recall: None,
+            save_as: None,
+            show_saved: false,
+            saved_state: Default::default(),
+        }
+    }
+
+    fn input(&self, mode: &InputMode) -> Option<&Input> {
+        match mode {
+            InputMode::None => None,
+            InputMode::Query => Some(&self.search_query),
+            InputMode::Name => Some(&self.search_name),
+            InputMode::Country => Some(&self.search_country),
+            InputMode::Language => Some(&self.search_language),
+            InputMode::CountryCode => Some(&self.search_country_code),
+            InputMode::Tags => Some(&self.search_tags),
+            InputMode::Codec => Some(&self.search_codec),
+            InputMode::BitrateMin => Some(&self.search_bitrate_min),
+            InputMode::BitrateMax => Some(&self.search_bitrate_max),
+            InputMode::Limit => Some(&self.search_limit),
+            InputMode::Order => Some(&self.search_order),
+            InputMode::Reverse => Some(&self.search_reverse),
+            InputMode::NameExact => Some(&self.search_name_exact),
+            InputMode::IsHttps => Some(&self.search_is_https),
+            InputMode::HasGeoInfo => Some(&self.search_has_geo_info),
+        }
+    }
+
+    fn input_mut(&mut self, mode: &InputMode) -> Option<&mut Input> {
+        match mode {
+            InputMode::None => None,
+            InputMode::Query => Some(&mut self.search_query),
+            InputMode::Name => Some(&mut self.search_name),
+            InputMode::Country => Some(&mut self.search_country),
+            InputMode::Language => Some(&mut self.search_language),
+            InputMode::CountryCode => Some(&mut self.search_country_code),
+            InputMode::Tags => Some(&mut self.search_tags),
+            InputMode::Codec => Some(&mut self.search_codec),
+            InputMode::BitrateMin => Some(&mut self.search_bitrate_min),
+            InputMode::BitrateMax => Some(&mut self.search_bitrate_max),
+            InputMode::Limit => Some(&mut self.search_limit),
+            InputMode::Order => Some(&mut self.search_order),
+            InputMode::Reverse => Some(&mut self.search_reverse),
+            InputMode::NameExact => Some(&mut self.search_name_exact),
+            InputMode::IsHttps => Some(&mut self.search_is_https),
+            InputMode::HasGeoInfo => Some(&mut self.search_has_geo_info),
         }
     }
 
-    fn get_search_param(&mut self) -> Vec<SearchParam> {
-        let mut result = Vec::new();
+    fn reset_inputs(&mut self) {
+        self.query_error = None;
+        for mode in InputMode::FIELDS.iter() {
+            if let Some(input) = self.input_mut(mode) {
+                input.reset();
+            }
+        }
+    }
+
+    /// Collects the parameters from the query line followed by the individual form fields and
+    /// resets the form.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`QueryError`] if the query line cannot be parsed. The form is left untouched.
+    fn get_search_param(&mut self) -> Result<Vec<SearchParam>, QueryError> {
+        let result = self.collect_search_param()?;
+        self.reset_inputs();
+        Ok(result)
+    }
+
+    /// Collects the parameters from the query line followed by the individual form fields.
+    fn collect_search_param(&self) -> Result<Vec<SearchParam>, QueryError> {
+        let mut result = parse_query(self.search_query.value())?;
 
         if !self.search_name.value().is_empty() {
             result.push(SearchParam::Name(self.search_name.value().to_string()));
-            self.search_name.reset();
         };
 
         if !self.search_country.value().is_empty() {
             result.push(SearchParam::Country(
                 self.search_country.value().to_string(),
             ));
-            self.search_country.reset();
         };
 
         if !self.search_language.value().is_empty() {
             result.push(SearchParam::Language(
                 self.search_language.value().to_string(),
             ));
-            self.search_language.reset();
+        };
+
+        if !self.search_country_code.value().is_empty() {
+            result.push(SearchParam::CountryCode(
+                self.search_country_code.value().trim().to_uppercase(),
+            ));
         };
 
         if !self.search_tags.value().is_empty() {
@@ -93,35 +250,195 @@ impl Search {
                 .map(|s| s.trim().to_string())
                 .collect();
             result.push(SearchParam::Tags(tags));
+        };
 
-            self.search_tags.reset();
+        if !self.search_codec.value().is_empty() {
+            result.push(SearchParam::Codec(
+                self.search_codec.value().trim().to_string(),
+            ));
+        };
+
+        if let Ok(bitrate) = self.search_bitrate_min.value().trim().parse::<u32>() {
+            result.push(SearchParam::BitrateMin(bitrate));
+        };
+
+        if let Ok(bitrate) = self.search_bitrate_max.value().trim().parse::<u32>() {
+            result.push(SearchParam::BitrateMax(bitrate));
         };
 
         if !self.search_limit.value().is_empty() {
             let limit = self.search_limit.value().parse::<usize>().unwrap_or(30);
 
             result.push(SearchParam::Limit(limit));
-            self.search_limit.reset();
         };
 
         if !self.search_order.value().is_empty() {
             let order = Order::from_str(self.search_order.value()).unwrap_or(Order::Name);
             result.push(SearchParam::Order(order));
-            self.search_order.reset();
         };
 
         if !self.search_reverse.value().is_empty() {
             let reverse = bool::from_str(self.search_reverse.value()).unwrap_or(true);
             result.push(SearchParam::Reverse(reverse));
-            self.search_reverse.reset();
         };
 
-        result
+        if let Ok(exact) = bool::from_str(self.search_name_exact.value().trim()) {
+            result.push(SearchParam::NameExact(exact));
+        };
+
+        if let Ok(https) = bool::from_str(self.search_is_https.value().trim()) {
+            result.push(SearchParam::IsHttps(https));
+        };
+
+        if let Ok(geo) = bool::from_str(self.search_has_geo_info.value().trim()) {
+            result.push(SearchParam::HasGeoInfo(geo));
+        };
+
+        Ok(result)
+    }
+
+    fn cancel_live_search(&mut self) {
+        if let Some(handle) = self.live_search_handle.take() {
+            handle.abort();
+        }
+    }
+
+    /// Sends the current form as a search once typing has paused for the configured debounce.
+    ///
+    /// Every call restarts the timer, so only the last edit in a burst of typing is searched.
+    fn schedule_live_search(&mut self) {
+        self.cancel_live_search();
+
+        if self.search_query.value().trim().is_empty() && self.search_name.value().is_empty() {
+            return;
+        }
+        // Incomplete queries are expected while typing, the error is shown on <Enter>.
+        let Ok(params) = self.collect_search_param() else {
+            return;
+        };
+        let Some(tx) = self.action_tx.clone() else {
+            return;
+        };
+        let debounce = self.live_debounce;
+
+        self.live_search_handle = Some(tokio::spawn(async move {
+            tokio::time::sleep(debounce).await;
+            tracing::debug!(?params, "sending live search");
+            if let Err(e) = tx.send(Action::Search(params)) {
+                tracing::error!("Failed to send action: {:?}", e);
+            }
+        }));
+    }
+
+    /// Replaces the form with the recent search `offset` steps older than the one recalled,
+    /// negative offsets step towards newer searches and finally back to an empty form.
+    fn recall(&mut self, offset: isize) {
+        let recent = self.searches.recent();
+        if recent.is_empty() {
+            return;
+        }
+        let next = match self.recall {
+            None if offset > 0 => Some(0),
+            None => return,
+            Some(i) => i.checked_add_signed(offset),
+        };
+        let next = next.map(|i| i.min(recent.len() - 1));
+        let query = next.map(|i| to_query(&recent[i])).unwrap_or_default();
+
+        self.reset_inputs();
+        self.recall = next;
+        self.search_query = Input::new(query);
+        self.input_mode = InputMode::Query;
+    }
+
+    /// Saves the form under the name typed into the save prompt.
+    fn save_search(&mut self) {
+        let Some(name) = self.save_as.take() else {
+            return;
+        };
+        let name = name.value().trim().to_string();
+        if name.is_empty() {
+            return;
+        }
+        let message = match self.collect_search_param() {
+            Ok(params) => match self.searches.save_search(&name, params) {
+                Ok(()) => Action::Notify(format!("Saved search {name}")),
+                Err(e) => Action::Error(format!("Failed to save search {name}: {e}")),
+            },
+            Err(e) => {
+                self.query_error = Some(e);
+                return;
+            }
+        };
+        if let Some(tx) = &self.action_tx {
+            let _ = tx.send(message);
+        }
+    }
+
+    /// Runs the selected saved search.
+    fn run_saved_search(&mut self) -> Action {
+        let Some(search) = self
+            .saved_state
+            .selected()
+            .and_then(|i| self.searches.saved().get(i))
+        else {
+            return Action::Update;
+        };
+        tracing::info!(name = search.name, "running saved search");
+        if let Some(tx) = &self.action_tx {
+            let _ = tx.send(Action::Search(search.params.clone()));
+        }
+        Action::HomeMode
+    }
+
+    fn remove_saved_search(&mut self) {
+        let Some(name) = self
+            .saved_state
+            .selected()
+            .and_then(|i| self.searches.saved().get(i))
+            .map(|s| s.name.clone())
+        else {
+            return;
+        };
+        if let Err(e) = self.searches.remove(&name) {
+            tracing::error!(error = ?e, "failed to remove saved search");
+        }
+        let len = self.searches.saved().len();
+        self.saved_state.select(
+            self.saved_state
+                .selected()
+                .filter(|_| len > 0)
+                .map(|i| i.min(len.saturating_sub(1))),
+        );
+    }
+
+    fn select_saved(&mut self, offset: isize) {
+        let len = self.searches.saved().len();
+        if len == 0 {
+            return;
+        }
+        let i = match self.saved_state.selected() {
+            Some(i) => (i as isize + offset).rem_euclid(len as isize) as usize,
+            None => 0,
+        };
+        self.saved_state.select(Some(i));
     }
 
     fn send_search_params(&mut self) -> Action {
-        let params = self.get_search_param();
+        self.cancel_live_search();
+        let params = match self.get_search_param() {
+            Ok(params) => params,
+            Err(e) => {
+                tracing::debug!(error = %e, "invalid search query");
+                self.input_mode = InputMode::Query;
+                self.query_error = Some(e);
+                return Action::Update;
+            }
+        };
         tracing::info!(?params, "sending search");
+        if let Err(e) = self.searches.push_recent(params.clone(), self.history_size) {
+            tracing::error!(error = ?e, "failed to save recent searches");
+        }
         if let Some(sender) = &self.action_tx {
             if let Err(e) = sender.send(Action::Search(params)) {
                 tracing::error!("Failed to send action: {:?}", e);
@@ -138,6 +455,72 @@ impl Search {
     fn tick(&mut self) {}
 
     fn render_tick(&mut self) {}
+
+    /// The error or completion hints shown underneath the query line.
+    fn query_status(&self) -> Line<'static> {
+        if let Some(name) = &self.save_as {
+            return Line::from(vec![
+                Span::styled("save as: ", Style::default().fg(Color::Yellow)),
+                Span::raw(format!("{}_", name.value())),
+            ]);
+        }
+        if let Some(error) = &self.query_error {
+            return Line::from(Span::styled(
+                error.to_string(),
+                Style::default().fg(Color::Red),
+            ));
+        }
+        if self.input_mode != InputMode::Query {
+            return Line::default();
+        }
+        let hints = query_hints(self.search_query.value());
+        Line::from(Span::styled(
+            hints.join(" "),
+            Style::default().fg(Color::DarkGray),
+        ))
+    }
+
+    /// Renders a single form field, highlighting it when it has focus.
+    fn render_input(&self, f: &mut Frame<'_>, mode: InputMode, title: &str, area: Rect) {
+        let Some(input) = self.input(&mode) else {
+            return;
+        };
+        let width = area.width.max(3) - 3; // keep 2 for borders and 1 for cursor
+        let scroll = input.visual_scroll(width as usize);
+
+        let block = Paragraph::new(input.value())
+            .style(if self.input_mode == mode {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            })
+            .scroll((0, scroll as u16))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(Line::from(vec![Span::styled(
+                        title.to_string(),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    )]))
+                    .title_bottom(if mode == InputMode::Query {
+                        self.query_status()
+                    } else {
+                        Line::default()
+                    }),
+            );
+
+        f.render_widget(block, area);
+
+        if self.input_mode == mode {
+            // Make the cursor visible and ask tui-rs to put it at the specified coordinates after rendering
+            f.set_cursor(
+                // Put cursor past the end of the input text
+                area.x + ((input.visual_cursor()).max(scroll) - scroll) as u16 + 1,
+                // Move one line down, from the border to the input line
+                area.y + 1,
+            )
+        }
+    }
 }
 
 impl Component for Search {
@@ -146,6 +529,28 @@ impl Component for Search {
         Ok(())
     }
 
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.live_debounce = config.search.live_debounce();
+        self.history_size = config.search.history_size;
+        Ok(())
+    }
+
+    fn init(&mut self, _area: Rect) -> Result<()> {
+        let path = get_data_dir().join(SavedSearches::FILE_NAME);
+        let (searches, error) = load_or_set_aside(&path, SavedSearches::load)?;
+        self.searches = searches;
+        if let Some(error) = error {
+            tracing::error!(%error, "failed to parse saved searches, starting empty");
+            if let Some(sender) = &self.action_tx {
+                let _ = sender.send(Action::Error(format!(
+                    "Could not read {}, kept as .broken and started empty",
+                    SavedSearches::FILE_NAME
+                )));
+            }
+        }
+        Ok(())
+    }
+
     fn update(&mut self, action: Action) -> Result<Option<Action>> {
         let mut result = None;
         match action {
@@ -153,19 +558,35 @@ impl Component for Search {
             Action::Render => self.render_tick(),
             Action::SearchMode => {
                 self.show_search = true;
-                self.input_mode = InputMode::Name;
+                self.input_mode = InputMode::Query;
                 result = Some(Action::Mode(AppMode::Search));
             }
+            Action::ToggleLiveSearch if self.show_search => {
+                self.live = !self.live;
+                if self.live {
+                    self.schedule_live_search();
+                } else {
+                    self.cancel_live_search();
+                }
+            }
+            Action::SaveSearch if self.show_search && !self.show_saved => {
+                self.save_as = Some(Input::default());
+            }
+            Action::ToggleSavedSearches if self.show_search => {
+                self.show_saved = !self.show_saved;
+                self.save_as = None;
+                if self.show_saved && self.saved_state.selected().is_none() {
+                    self.select_saved(0);
+                }
+            }
             Action::HomeMode => {
                 self.show_search = false;
+                self.show_saved = false;
+                self.save_as = None;
+                self.recall = None;
                 self.input_mode = InputMode::None;
-                self.search_name.reset();
-                self.search_country.reset();
-                self.search_language.reset();
-                self.search_tags.reset();
-                self.search_limit.reset();
-                self.search_order.reset();
-                self.search_reverse.reset();
+                self.cancel_live_search();
+                self.reset_inputs();
                 result = Some(Action::Mode(AppMode::Home));
             }
             _ => (),
@@ -174,120 +595,68 @@ impl Component for Search {
     }
 
     fn handle_key_events(&mut self, key: KeyEvent) -> Result<Option<Action>> {
-        let action = match self.input_mode {
-            InputMode::None => return Ok(None),
-            InputMode::Name => match key.code {
-                KeyCode::Enter => self.send_search_params(),
-                KeyCode::Tab => {
-                    self.input_mode = InputMode::Country;
-                    Action::Update
-                }
-                KeyCode::BackTab => {
-                    self.input_mode = InputMode::Reverse;
-                    Action::Update
-                }
-                _ => {
-                    self.search_name
-                        .handle_event(&crossterm::event::Event::Key(key));
-                    Action::Update
-                }
-            },
-            InputMode::Country => match key.code {
-                KeyCode::Enter => self.send_search_params(),
-                KeyCode::Tab => {
-                    self.input_mode = InputMode::Language;
-                    Action::Update
-                }
-                KeyCode::BackTab => {
-                    self.input_mode = InputMode::Name;
-                    Action::Update
-                }
-                _ => {
-                    self.search_country
-                        .handle_event(&crossterm::event::Event::Key(key));
-                    Action::Update
-                }
-            },
-            InputMode::Language => match key.code {
-                KeyCode::Enter => self.send_search_params(),
-                KeyCode::Tab => {
-                    self.input_mode = InputMode::Tags;
-                    Action::Update
-                }
-                KeyCode::BackTab => {
-                    self.input_mode = InputMode::Country;
-                    Action::Update
-                }
-                _ => {
-                    self.search_language
-                        .handle_event(&crossterm::event::Event::Key(key));
-                    Action::Update
-                }
-            },
-            InputMode::Tags => match key.code {
-                KeyCode::Enter => self.send_search_params(),
-                KeyCode::Tab => {
-                    self.input_mode = InputMode::Limit;
-                    Action::Update
-                }
-                KeyCode::BackTab => {
-                    self.input_mode = InputMode::Language;
-                    Action::Update
-                }
-                _ => {
-                    self.search_tags
-                        .handle_event(&crossterm::event::Event::Key(key));
-                    Action::Update
-                }
-            },
-            InputMode::Limit => match key.code {
-                KeyCode::Enter => self.send_search_params(),
-                KeyCode::Tab => {
-                    self.input_mode = InputMode::Order;
-                    Action::Update
-                }
-                KeyCode::BackTab => {
-                    self.input_mode = InputMode::Tags;
-                    Action::Update
-                }
-                _ => {
-                    self.search_limit
-                        .handle_event(&crossterm::event::Event::Key(key));
+        if self.input_mode == InputMode::None {
+            return Ok(None);
+        }
+        if self.show_saved {
+            let action = match key.code {
+                KeyCode::Enter => self.run_saved_search(),
+                KeyCode::Down => {
+                    self.select_saved(1);
                     Action::Update
                 }
-            },
-            InputMode::Order => match key.code {
-                KeyCode::Enter => self.send_search_params(),
-                KeyCode::Tab => {
-                    self.input_mode = InputMode::Reverse;
+                KeyCode::Up => {
+                    self.select_saved(-1);
                     Action::Update
                 }
-                KeyCode::BackTab => {
-                    self.input_mode = InputMode::Limit;
+                KeyCode::Delete => {
+                    self.remove_saved_search();
                     Action::Update
                 }
+                _ => return Ok(None),
+            };
+            return Ok(Some(action));
+        }
+        if let Some(name) = self.save_as.as_mut() {
+            match key.code {
+                KeyCode::Enter => self.save_search(),
                 _ => {
-                    self.search_order
-                        .handle_event(&crossterm::event::Event::Key(key));
-                    Action::Update
+                    name.handle_event(&crossterm::event::Event::Key(key));
                 }
-            },
-            InputMode::Reverse => match key.code {
-                KeyCode::Enter => self.send_search_params(),
-                KeyCode::Tab => {
-                    self.input_mode = InputMode::Name;
-                    Action::Update
+            }
+            return Ok(Some(Action::Update));
+        }
+        let action = match key.code {
+            KeyCode::Enter => self.send_search_params(),
+            KeyCode::Up => {
+                self.recall(1);
+                Action::Update
+            }
+            KeyCode::Down => {
+                self.recall(-1);
+                Action::Update
+            }
+            KeyCode::Tab => {
+                self.input_mode = self.input_mode.next();
+                Action::Update
+            }
+            KeyCode::BackTab => {
+                self.input_mode = self.input_mode.previous();
+                Action::Update
+            }
+            _ => {
+                let mode = self.input_mode.clone();
+                if let Some(input) = self.input_mut(&mode) {
+                    input.handle_event(&crossterm::event::Event::Key(key));
                 }
-                KeyCode::BackTab => {
-                    self.input_mode = InputMode::Order;
-                    Action::Update
+                if mode == InputMode::Query {
+                    self.query_error = None;
                 }
-                _ => {
-                    self.search_reverse
-                        .handle_event(&crossterm::event::Event::Key(key));
-                    Action::Update
+                if self.live && matches!(mode, InputMode::Query | InputMode::Name) {
+                    self.schedule_live_search();
                 }
-            },
+                Action::Update
+            }
         };
         Ok(Some(action))
     }
@@ -303,7 +672,7 @@ impl Component for Search {
 
             let wrapper = Layout::new(
                 Direction::Vertical,
-                [Constraint::Max(15), Constraint::Min(0)],
+                [Constraint::Max(24), Constraint::Min(0)],
             )
             .horizontal_margin(2)
             .vertical_margin(1)
@@ -311,284 +680,135 @@ impl Component for Search {
 
             f.render_widget(Clear, wrapper[0]);
 
-            let block = Block::default()
-                .title(Line::from(vec![Span::styled(
-                    "Search",
-                    Style::default().add_modifier(Modifier::BOLD),
-                )]))
-                .bg(Color::Black);
+            let mut title = vec![Span::styled(
+                "Search",
+                Style::default().add_modifier(Modifier::BOLD),
+            )];
+            if self.live {
+                title.push(Span::styled(" (live)", Style::default().fg(Color::Green)));
+            }
+            let block = Block::default().title(Line::from(title)).bg(Color::Black);
 
             f.render_widget(block, rect);
 
-            let layout = Layout::new(
-                Direction::Vertical,
-                [
-                    Constraint::Length(3),
-                    Constraint::Length(3),
-                    Constraint::Length(3),
-                    Constraint::Length(3),
-                ],
-            )
-            .split(wrapper[0]);
-
-            let first_row =
-                Layout::new(Direction::Horizontal, [Constraint::Percentage(100)]).split(layout[0]);
-
-            let second_row = Layout::new(
-                Direction::Horizontal,
-                [
-                    Constraint::Percentage(50),
-                    Constraint::Min(0),
-                    Constraint::Percentage(50),
-                ],
-            )
-            .split(layout[1]);
-
-            let third_row =
-                Layout::new(Direction::Horizontal, [Constraint::Percentage(100)]).split(layout[2]);
-
-            let fourth_row = Layout::new(
-                Direction::Horizontal,
-                [
-                    Constraint::Percentage(33),
-                    Constraint::Min(0),
-                    Constraint::Percentage(34),
-                    Constraint::Min(0),
-                    Constraint::Percentage(33),
-                ],
-            )
-            .split(layout[3]);
-
-            // f.render_widget(Clear, rect);
-
-            let width = first_row[0].width.max(3) - 3; // keep 2 for borders and 1 for cursor
-            let name_block = Paragraph::new(self.search_name.value())
-                .style(match self.input_mode {
-                    InputMode::Name => Style::default().fg(Color::Yellow),
-                    _ => Style::default(),
-                })
-                .scroll((0, self.search_name.visual_scroll(width as usize) as u16))
-                .block(
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .title(Line::from(vec![Span::styled(
-                            "name",
-                            Style::default().add_modifier(Modifier::BOLD),
-                        )])),
-                );
-
-            f.render_widget(name_block, first_row[0]);
-
-            let country_block = Paragraph::new(self.search_country.value())
-                .style(match self.input_mode {
-                    InputMode::Country => Style::default().fg(Color::Yellow),
-                    _ => Style::default(),
-                })
-                .scroll((0, self.search_country.visual_scroll(width as usize) as u16))
-                .block(
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .title(Line::from(vec![Span::styled(
-                            "country",
-                            Style::default().add_modifier(Modifier::BOLD),
-                        )])),
-                );
-
-            f.render_widget(country_block, second_row[0]);
-
-            let language_block = Paragraph::new(self.search_language.value())
-                .style(match self.input_mode {
-                    InputMode::Language => Style::default().fg(Color::Yellow),
-                    _ => Style::default(),
-                })
-                .scroll((0, self.search_language.visual_scroll(width as usize) as u16))
-                .block(
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .title(Line::from(vec![Span::styled(
-                            "language",
-                            Style::default().add_modifier(Modifier::BOLD),
-                        )])),
-                );
-
-            f.render_widget(language_block, second_row[2]);
-
-            let tags_block = Paragraph::new(self.search_tags.value())
-                .style(match self.input_mode {
-                    InputMode::Tags => Style::default().fg(Color::Yellow),
-                    _ => Style::default(),
-                })
-                .scroll((0, self.search_tags.visual_scroll(width as usize) as u16))
-                .block(
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .title(Line::from(vec![Span::styled(
-                            "tags",
-                            Style::default().add_modifier(Modifier::BOLD),
-                        )])),
-                );
-
-            f.render_widget(tags_block, third_row[0]);
-
-            let limit_block = Paragraph::new(self.search_limit.value())
-                .style(match self.input_mode {
-                    InputMode::Limit => Style::default().fg(Color::Yellow),
-                    _ => Style::default(),
-                })
-                .scroll((0, self.search_limit.visual_scroll(width as usize) as u16))
-                .block(
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .title(Line::from(vec![Span::styled(
-                            "limit",
-                            Style::default().add_modifier(Modifier::BOLD),
-                        )])),
-                );
-            f.render_widget(limit_block, fourth_row[0]);
-
-            let order_block = Paragraph::new(self.search_order.value())
-                .style(match self.input_mode {
-                    InputMode::Order => Style::default().fg(Color::Yellow),
-                    _ => Style::default(),
-                })
-                .scroll((0, self.search_order.visual_scroll(width as usize) as u16))
-                .block(
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .title(Line::from(vec![Span::styled(
-                            "order",
-                            Style::default().add_modifier(Modifier::BOLD),
-                        )])),
-                );
-
-            f.render_widget(order_block, fourth_row[2]);
-
-            let reverse_block = Paragraph::new(self.search_reverse.value())
-                .style(match self.input_mode {
-                    InputMode::Reverse => Style::default().fg(Color::Yellow),
-                    _ => Style::default(),
-                })
-                .scroll((0, self.search_reverse.visual_scroll(width as usize) as u16))
-                .block(
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .title(Line::from(vec![Span::styled(
-                            "reverse",
-                            Style::default().add_modifier(Modifier::BOLD),
-                        )])),
-                );
-
-            f.render_widget(reverse_block, fourth_row[4]);
-
-            let width = first_row[0].width.max(3) - 3; // keep 2 for borders and 1 for cursor
-
-            match self.input_mode {
-                InputMode::None =>
-                    // Hide the cursor. `Frame` does this by default, so we don't need to do anything here
-                    {}
-
-                InputMode::Name => {
-                    let scroll = self.search_name.visual_scroll(width as usize);
-                    // Make the cursor visible and ask tui-rs to put it at the specified coordinates after rendering
-                    f.set_cursor(
-                        // Put cursor past the end of the input text
-                        first_row[0].x
-                            + ((self.search_name.visual_cursor()).max(scroll) - scroll) as u16
-                            + 1,
-                        // Move one line down, from the border to the input line
-                        first_row[0].y + 1,
-                    )
-                }
-                InputMode::Country => {
-                    let scroll = self.search_country.visual_scroll(width as usize);
-                    // Make the cursor visible and ask tui-rs to put it at the specified coordinates after rendering
-                    f.set_cursor(
-                        // Put cursor past the end of the input text
-                        second_row[0].x
-                            + ((self.search_country.visual_cursor()).max(scroll) - scroll) as u16
-                            + 1,
-                        // Move one line down, from the border to the input line
-                        second_row[0].y + 1,
-                    )
-                }
-                InputMode::Language => {
-                    let scroll = self.search_language.visual_scroll(width as usize);
-                    // Make the cursor visible and ask tui-rs to put it at the specified coordinates after rendering
-                    f.set_cursor(
-                        // Put cursor past the end of the input text
-                        second_row[2].x
-                            + ((self.search_language.visual_cursor()).max(scroll) - scroll) as u16
-                            + 1,
-                        // Move one line down, from the border to the input line
-                        second_row[2].y + 1,
-                    )
-                }
-                InputMode::Tags => {
-                    let scroll = self.search_tags.visual_scroll(width as usize);
-                    // Make the cursor visible and ask tui-rs to put it at the specified coordinates after rendering
-                    f.set_cursor(
-                        // Put cursor past the end of the input text
-                        third_row[0].x
-                            + ((self.search_tags.visual_cursor()).max(scroll) - scroll) as u16
-                            + 1,
-                        // Move one line down, from the border to the input line
-                        third_row[0].y + 1,
-                    )
-                }
-                InputMode::Limit => {
-                    let scroll = self.search_limit.visual_scroll(width as usize);
-                    // Make the cursor visible and ask tui-rs to put it at the specified coordinates after rendering
-                    f.set_cursor(
-                        // Put cursor past the end of the input text
-                        fourth_row[0].x
-                            + ((self.search_limit.visual_cursor()).max(scroll) - scroll) as u16
-                            + 1,
-                        // Move one line down, from the border to the input line
-                        fourth_row[0].y + 1,
-                    )
-                }
-                InputMode::Order => {
-                    let scroll = self.search_order.visual_scroll(width as usize);
-                    // Make the cursor visible and ask tui-rs to put it at the specified coordinates after rendering
-                    f.set_cursor(
-                        // Put cursor past the end of the input text
-                        fourth_row[2].x
-                            + ((self.search_order.visual_cursor()).max(scroll) - scroll) as u16
-                            + 1,
-                        // Move one line down, from the border to the input line
-                        fourth_row[2].y + 1,
-                    )
-                }
-                InputMode::Reverse => {
-                    let scroll = self.search_reverse.visual_scroll(width as usize);
-                    // Make the cursor visible and ask tui-rs to put it at the specified coordinates after rendering
-                    f.set_cursor(
-                        // Put cursor past the end of the input text
-                        fourth_row[4].x
-                            + ((self.search_reverse.visual_cursor()).max(scroll) - scroll) as u16
-                            + 1,
-                        // Move one line down, from the border to the input line
-                        fourth_row[4].y + 1,
-                    )
-                }
+            if self.show_saved {
+                self.draw_saved(f, wrapper[0]);
+                return Ok(());
             }
+
+            let layout =
+                Layout::new(Direction::Vertical, [Constraint::Length(3); 7]).split(wrapper[0]);
+
+            let full_row = |area: Rect| {
+                Layout::new(Direction::Horizontal, [Constraint::Percentage(100)]).split(area)
+            };
+            let split_row = |area: Rect| {
+                Layout::new(
+                    Direction::Horizontal,
+                    [
+                        Constraint::Percentage(33),
+                        Constraint::Min(0),
+                        Constraint::Percentage(34),
+                        Constraint::Min(0),
+                        Constraint::Percentage(33),
+                    ],
+                )
+                .split(area)
+            };
+
+            let query_row = full_row(layout[0]);
+            let first_row = full_row(layout[1]);
+            let second_row = split_row(layout[2]);
+            let third_row = full_row(layout[3]);
+            let fourth_row = split_row(layout[4]);
+            let fifth_row = split_row(layout[5]);
+            let sixth_row = split_row(layout[6]);
+
+            self.render_input(f, InputMode::Query, "query", query_row[0]);
+
+            self.render_input(f, InputMode::Name, "name", first_row[0]);
+
+            self.render_input(f, InputMode::Country, "country", second_row[0]);
+            self.render_input(f, InputMode::Language, "language", second_row[2]);
+            self.render_input(f, InputMode::CountryCode, "country code", second_row[4]);
+
+            self.render_input(f, InputMode::Tags, "tags", third_row[0]);
+
+            self.render_input(f, InputMode::Codec, "codec", fourth_row[0]);
+            self.render_input(f, InputMode::BitrateMin, "min bitrate", fourth_row[2]);
+            self.render_input(f, InputMode::BitrateMax, "max bitrate", fourth_row[4]);
+
+            self.render_input(f, InputMode::Limit, "limit", fifth_row[0]);
+            self.render_input(f, InputMode::Order, "order", fifth_row[2]);
+            self.render_input(f, InputMode::Reverse, "reverse", fifth_row[4]);
+
+            self.render_input(f, InputMode::NameExact, "exact name", sixth_row[0]);
+            self.render_input(f, InputMode::IsHttps, "https only", sixth_row[2]);
+            self.render_input(f, InputMode::HasGeoInfo, "has geo info", sixth_row[4]);
         };
 
         Ok(())
     }
 }
 
+impl Search {
+    /// Lists the saved searches in place of the form.
+    fn draw_saved(&mut self, f: &mut Frame<'_>, area: Rect) {
+        let items: Vec<ListItem> = self
+            .searches
+            .saved()
+            .iter()
+            .map(|search| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{:<20} ", search.name),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(
+                        to_query(&search.params),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ]))
+            })
+            .collect();
+        let empty = items.is_empty();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" Saved searches ")
+                    .title_bottom(Line::styled(
+                        " enter run  del remove  ctrl-r back ",
+                        Style::default().fg(Color::DarkGray),
+                    )),
+            )
+            .highlight_style(Style::default().fg(Color::Yellow))
+            .highlight_symbol("> ");
+        f.render_stateful_widget(list, area, &mut self.saved_state);
+        if empty {
+            f.render_widget(
+                Paragraph::new("No saved searches yet, save the form with ctrl-s")
+                    .fg(Color::DarkGray),
+                area.inner(Margin {
+                    horizontal: 2,
+                    vertical: 1,
+                }),
+            );
+        }
+    }
+}
+
 /// helper function to create a centered rect using up certain percentage of the available rect `r`
 fn search_popup(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::vertical({
         if r.height > 60 {
             [
                 Constraint::Percentage((100 - percent_y) / 2),
-                Constraint::Max(14),
+                Constraint::Max(23),
                 Constraint::Percentage((100 - percent_y) / 2),
             ]
         } else {
-            [Constraint::Min(5), Constraint::Min(14), Constraint::Min(0)]
+            [Constraint::Min(5), Constraint::Min(23), Constraint::Min(0)]
         }
     })
     .split(r);
@@ -0,0 +1,289 @@
+//! A popup listing the tracks announced by the stations played, searchable and exportable.
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use color_eyre::eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    prelude::*,
+    style::palette::tailwind,
+    widgets::{Block, Borders, Cell, Clear, HighlightSpacing, Paragraph, Row, Table, TableState},
+};
+use tokio::sync::mpsc::UnboundedSender;
+use tui_input::{backend::crossterm::EventHandler, Input};
+
+use super::Component;
+use crate::{
+    action::Action,
+    config::{Config, SongLogConfig},
+    errors::Error,
+    mode::Mode as AppMode,
+    models::{ExportFormat, SongEntry, SongLog},
+    utils::{format_age, get_data_dir, load_or_set_aside, unix_now, write_atomic},
+};
+
+const NORMAL_ROW_COLOR: Color = tailwind::SLATE.c950;
+const ALT_ROW_COLOR: Color = tailwind::SLATE.c900;
+const TEXT_COLOR: Color = tailwind::SLATE.c200;
+const HEADER_COLOR: Color = tailwind::SLATE.c400;
+const SELECTED_STYLE_FG: Color = tailwind::BLUE.c300;
+
+pub struct SongLogPanel {
+    action_tx: Option<UnboundedSender<Action>>,
+    show: bool,
+    /// Shared with the background saves, see [`save_log`].
+    log: Arc<Mutex<SongLog>>,
+    config: SongLogConfig,
+    /// Narrows the songs shown, see [`SongEntry::matches`].
+    search: Input,
+    state: TableState,
+    /// Feedback on loading the log or on the last export, shown in the bottom title.
+    status: Option<Line<'static>>,
+}
+
+impl SongLogPanel {
+    pub fn new() -> Result<Self, Error> {
+        let (log, error) =
+            load_or_set_aside(&get_data_dir().join(SongLog::FILE_NAME), SongLog::load)?;
+        let status = error.map(|error| {
+            tracing::error!(%error, "failed to parse song log, starting empty");
+            Line::styled(
+                format!(
+                    " Could not read {}, kept as .broken and started empty ",
+                    SongLog::FILE_NAME
+                ),
+                Style::default().fg(Color::Red),
+            )
+        });
+        Ok(Self {
+            action_tx: None,
+            show: false,
+            log: Arc::new(Mutex::new(log)),
+            config: Default::default(),
+            search: Input::default(),
+            state: TableState::default(),
+            status,
+        })
+    }
+
+    fn matching(&self) -> Vec<SongEntry> {
+        self.log
+            .lock()
+            .map(|log| log.search(self.search.value()).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn record(&mut self, entry: SongEntry) {
+        if !self.config.enabled {
+            return;
+        }
+        let Ok(mut log) = self.log.lock() else {
+            return;
+        };
+        if log.record(entry, self.config.max_entries) && log.begin_save() {
+            save_log(self.log.clone());
+        }
+    }
+
+    fn next(&mut self) {
+        let len = self.matching().len();
+        if len > 0 {
+            let i = self.state.selected().map_or(0, |i| (i + 1) % len);
+            self.state.select(Some(i));
+        }
+    }
+
+    fn previous(&mut self) {
+        let len = self.matching().len();
+        if len > 0 {
+            let i = self.state.selected().map_or(0, |i| (i + len - 1) % len);
+            self.state.select(Some(i));
+        }
+    }
+
+    /// Writes the songs matching the search to a file in the export directory.
+    fn export(&mut self, format: ExportFormat) {
+        let dir = self.config.export_dir.clone().unwrap_or_else(get_data_dir);
+        let path = dir.join(format!("songs-{}.{format}", unix_now()));
+        let result = SongLog::export(&self.matching(), format)
+            .and_then(|data| Ok(write_atomic(&path, data.as_bytes())?));
+        self.status = Some(match result {
+            Ok(()) => Line::styled(
+                format!(" Exported to {} ", path.display()),
+                Style::default().fg(Color::Green),
+            ),
+            Err(e) => {
+                tracing::error!(error = ?e, "failed to export song log");
+                Line::styled(
+                    format!(" Export failed: {e} "),
+                    Style::default().fg(Color::Red),
+                )
+            }
+        });
+    }
+
+    fn rows(entries: &[SongEntry]) -> Vec<Row<'static>> {
+        let now = unix_now();
+        entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let bg_color = match i % 2 {
+                    0 => NORMAL_ROW_COLOR,
+                    _ => ALT_ROW_COLOR,
+                };
+                Row::new(vec![
+                    Cell::from(format_age(entry.timestamp, now)),
+                    Cell::from(entry.artist.clone().unwrap_or_default()),
+                    Cell::from(entry.title.clone()),
+                    Cell::from(entry.station.clone()).fg(HEADER_COLOR),
+                ])
+                .fg(TEXT_COLOR)
+                .bg(bg_color)
+            })
+            .collect()
+    }
+}
+
+impl Component for SongLogPanel {
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
+        self.action_tx = Some(tx);
+        Ok(())
+    }
+
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.config = config.song_log;
+        Ok(())
+    }
+
+    fn handle_key_events(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        if !self.show {
+            return Ok(None);
+        }
+        match key.code {
+            KeyCode::Down => self.next(),
+            KeyCode::Up => self.previous(),
+            _ => {
+                self.search.handle_event(&crossterm::event::Event::Key(key));
+                self.state.select(None);
+            }
+        }
+        Ok(Some(Action::Update))
+    }
+
+    fn update(&mut self, action: Action) -> Result<Option<Action>> {
+        match action {
+            Action::TrackChanged(entry) => self.record(entry),
+            Action::ToggleSongLog => {
+                self.show = !self.show;
+                self.status = None;
+                self.search.reset();
+                self.state.select(None);
+                let mode = if self.show {
+                    AppMode::SongLog
+                } else {
+                    AppMode::Home
+                };
+                return Ok(Some(Action::Mode(mode)));
+            }
+            Action::ExportSongLogCsv if self.show => self.export(ExportFormat::Csv),
+            Action::ExportSongLogJson if self.show => self.export(ExportFormat::Json),
+            _ => (),
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, rect: Rect) -> Result<()> {
+        if !self.show {
+            return Ok(());
+        }
+        let area = popup(80, 70, rect);
+        f.render_widget(Clear, area);
+
+        let matching = self.matching();
+        let count = matching.len();
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(Line::from(format!(" Songs ({count}) ")).bold())
+            .title_bottom(self.status.clone().unwrap_or_else(|| {
+                Line::styled(
+                    " type to search  ↑↓ select  ctrl-e csv  ctrl-o json  esc close ",
+                    Style::default().fg(Color::DarkGray),
+                )
+            }))
+            .bg(NORMAL_ROW_COLOR);
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let [search_area, table_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(inner);
+        let prefix = "search: ";
+        f.render_widget(
+            Paragraph::new(Line::from(vec![
+                Span::styled(prefix, Style::default().fg(Color::Yellow)),
+                Span::raw(self.search.value().to_string()),
+            ])),
+            search_area,
+        );
+        f.set_cursor(
+            (search_area.x + prefix.len() as u16 + self.search.visual_cursor() as u16)
+                .min(search_area.x + search_area.width.saturating_sub(1)),
+            search_area.y,
+        );
+
+        let header = Row::new(vec!["When", "Artist", "Title", "Station"]).style(
+            Style::default()
+                .fg(HEADER_COLOR)
+                .add_modifier(Modifier::BOLD),
+        );
+        let widths = [
+            Constraint::Length(9),
+            Constraint::Fill(2),
+            Constraint::Fill(3),
+            Constraint::Fill(2),
+        ];
+        let table = Table::new(Self::rows(&matching), widths)
+            .header(header)
+            .highlight_style(
+                Style::default()
+                    .add_modifier(Modifier::REVERSED)
+                    .fg(SELECTED_STYLE_FG),
+            )
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+        f.render_stateful_widget(table, table_area, &mut self.state);
+        Ok(())
+    }
+}
+
+/// Writes the changes of the log on a thread where blocking is fine, outside its lock.
+fn save_log(log: Arc<Mutex<SongLog>>) {
+    tokio::task::spawn_blocking(move || loop {
+        let Some(changes) = log.lock().ok().and_then(|mut log| log.take_changes()) else {
+            return;
+        };
+        let result = changes.and_then(|(path, data)| Ok(write_atomic(&path, &data)?));
+        if let Err(e) = result {
+            tracing::error!(error = ?e, "failed to save song log");
+        }
+    });
+}
+
+/// A rectangle of the given percentages of `r`, centered in it.
+fn popup(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+    let [_, area, _] = Layout::vertical([
+        Constraint::Percentage((100 - percent_y) / 2),
+        Constraint::Percentage(percent_y),
+        Constraint::Percentage((100 - percent_y) / 2),
+    ])
+    .areas(r);
+    let [_, area, _] = Layout::horizontal([
+        Constraint::Percentage((100 - percent_x) / 2),
+        Constraint::Percentage(percent_x),
+        Constraint::Percentage((100 - percent_x) / 2),
+    ])
+    .areas(area);
+    area
+}
@@ -0,0 +1,666 @@
+//! The stations table in [`super::home::Home`]: its rows, columns, sorting and filtering.
+use std::{cmp::Ordering, collections::HashMap};
+
+use ratatui::{
+    layout::Constraint,
+    style::{palette::tailwind, Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Cell, Row, TableState},
+};
+use serde::{Deserialize, Serialize};
+use strum::Display;
+
+use super::fuzzy::fuzzy_match;
+use crate::models::RadioStation;
+
+const NORMAL_ROW_COLOR: Color = tailwind::SLATE.c950;
+const ALT_ROW_COLOR: Color = tailwind::SLATE.c900;
+const TEXT_COLOR: Color = tailwind::SLATE.c200;
+const HEADER_COLOR: Color = tailwind::SLATE.c400;
+const MATCH_COLOR: Color = tailwind::AMBER.c400;
+const FAVORITE_COLOR: Color = tailwind::YELLOW.c400;
+
+/// Shown in front of the names of favorite stations.
+pub const FAVORITE_MARKER: &str = "★ ";
+/// Marks stations the directory deleted, whose stream failed its last check or that were marked
+/// broken by a check of their stream.
+pub const BROKEN_MARKER: &str = "✗ ";
+
+/// A column of the stations table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Display)]
+#[serde(rename_all = "lowercase")]
+pub enum StationColumn {
+    Name,
+    Country,
+    Codec,
+    Bitrate,
+    Votes,
+    Tags,
+    /// Where the station was found, the directory or one of the listings.
+    Source,
+}
+
+impl StationColumn {
+    pub const ALL: [StationColumn; 7] = [
+        StationColumn::Name,
+        StationColumn::Country,
+        StationColumn::Codec,
+        StationColumn::Bitrate,
+        StationColumn::Votes,
+        StationColumn::Tags,
+        StationColumn::Source,
+    ];
+
+    /// Columns are hidden in this order, highest first, when the terminal is too narrow.
+    fn hide_priority(&self) -> u8 {
+        match self {
+            StationColumn::Name => 0,
+            StationColumn::Bitrate => 1,
+            StationColumn::Codec => 2,
+            StationColumn::Country => 3,
+            StationColumn::Votes => 4,
+            StationColumn::Tags => 5,
+            StationColumn::Source => 6,
+        }
+    }
+
+    /// The narrowest the column can get while still being useful.
+    fn min_width(&self) -> u16 {
+        match self {
+            StationColumn::Name => 20,
+            StationColumn::Country => 4,
+            StationColumn::Codec => 5,
+            StationColumn::Bitrate => 7,
+            StationColumn::Votes => 6,
+            StationColumn::Tags => 12,
+            StationColumn::Source => 13,
+        }
+    }
+
+    fn constraint(&self, width: u16) -> Constraint {
+        match self {
+            StationColumn::Name => Constraint::Fill(3),
+            StationColumn::Tags => Constraint::Fill(2),
+            // Full country names only fit on wide terminals, otherwise show the code.
+            StationColumn::Country if width >= 120 => Constraint::Length(16),
+            column => Constraint::Length(column.min_width()),
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            StationColumn::Name => "Name",
+            StationColumn::Country => "Country",
+            StationColumn::Codec => "Codec",
+            StationColumn::Bitrate => "Bitrate",
+            StationColumn::Votes => "Votes",
+            StationColumn::Tags => "Tags",
+            StationColumn::Source => "Source",
+        }
+    }
+
+    fn text(&self, station: &RadioStation, width: u16) -> String {
+        match self {
+            StationColumn::Name => station.name.trim().to_string(),
+            StationColumn::Country if width >= 120 => station.country_name(),
+            StationColumn::Country => station.countrycode.clone(),
+            StationColumn::Codec => station.codec.clone(),
+            StationColumn::Bitrate if station.bitrate == 0 => String::new(),
+            StationColumn::Bitrate => format!("{}k", station.bitrate),
+            StationColumn::Votes => station.votes.to_string(),
+            StationColumn::Tags => station.tags.join(", "),
+            StationColumn::Source => station.source().to_string(),
+        }
+    }
+
+    /// Compares two stations by this column, text columns ignore case.
+    pub fn compare(&self, a: &RadioStation, b: &RadioStation) -> Ordering {
+        let text = |s: &RadioStation| self.text(s, 0).to_lowercase();
+        match self {
+            StationColumn::Bitrate => a.bitrate.cmp(&b.bitrate),
+            StationColumn::Votes => a.votes.cmp(&b.votes),
+            _ => text(a).cmp(&text(b)),
+        }
+    }
+}
+
+/// How the stations table is sorted locally.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SortOrder {
+    pub column: StationColumn,
+    pub descending: bool,
+}
+
+impl SortOrder {
+    /// Sorts `stations` in place. The sort is stable, so equal stations keep the directory order.
+    pub fn sort(&self, stations: &mut [RadioStation]) {
+        stations.sort_by(|a, b| {
+            let ordering = self.column.compare(a, b);
+            if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+}
+
+/// Returns the configured columns that fit into `width`, dropping the least important first.
+pub fn visible_columns(columns: &[StationColumn], width: u16) -> Vec<StationColumn> {
+    let mut visible = columns.to_vec();
+    // one column of spacing between cells plus the highlight symbol
+    let required = |cols: &[StationColumn]| cols.iter().map(|c| c.min_width() + 1).sum::<u16>() + 2;
+
+    while visible.len() > 1 && required(&visible) > width {
+        let Some((i, _)) = visible
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, column)| column.hide_priority())
+        else {
+            break;
+        };
+        visible.remove(i);
+    }
+    visible
+}
+
+pub fn widths(columns: &[StationColumn], width: u16) -> Vec<Constraint> {
+    columns.iter().map(|c| c.constraint(width)).collect()
+}
+
+pub fn header(columns: &[StationColumn], sort: Option<SortOrder>) -> Row<'static> {
+    let cells = columns.iter().map(|column| {
+        let indicator = match sort {
+            Some(sort) if sort.column == *column && sort.descending => " ▼",
+            Some(sort) if sort.column == *column => " ▲",
+            _ => "",
+        };
+        Cell::from(format!("{}{indicator}", column.title()))
+    });
+    Row::new(cells).style(
+        Style::default()
+            .fg(HEADER_COLOR)
+            .add_modifier(Modifier::BOLD),
+    )
+}
+
+/// Styles the characters at `indices` of `text` as matched by the filter.
+fn highlight(text: String, indices: &[usize]) -> Line<'static> {
+    if indices.is_empty() {
+        return Line::from(text);
+    }
+    let matched = Style::default()
+        .fg(MATCH_COLOR)
+        .add_modifier(Modifier::BOLD);
+    let spans: Vec<Span> = text
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            if indices.contains(&i) {
+                Span::styled(c.to_string(), matched)
+            } else {
+                Span::raw(c.to_string())
+            }
+        })
+        .collect();
+    Line::from(spans)
+}
+
+pub fn row(
+    station: &RadioStation,
+    index: usize,
+    columns: &[StationColumn],
+    width: u16,
+    matched: Option<&StationMatch>,
+    favorite: bool,
+) -> Row<'static> {
+    let bg_color = match index % 2 {
+        0 => NORMAL_ROW_COLOR,
+        _ => ALT_ROW_COLOR,
+    };
+    let cells = columns.iter().map(|column| {
+        let text = column.text(station, width);
+        let mut line = match (column, matched) {
+            (StationColumn::Name, Some(m)) => highlight(text, &m.name),
+            (StationColumn::Tags, Some(m)) => highlight(text, &m.tags),
+            _ => Line::from(text),
+        };
+        if *column == StationColumn::Name {
+            if station.deleted || station.broken.is_some() {
+                line.spans.insert(
+                    0,
+                    Span::styled(BROKEN_MARKER, Style::default().fg(Color::Red)),
+                );
+            } else if station.is_directory() && !station.lastcheckok {
+                line.spans.insert(
+                    0,
+                    Span::styled(BROKEN_MARKER, Style::default().fg(Color::Yellow)),
+                );
+            }
+        }
+        if favorite && *column == StationColumn::Name {
+            line.spans.insert(
+                0,
+                Span::styled(FAVORITE_MARKER, Style::default().fg(FAVORITE_COLOR)),
+            );
+        }
+        Cell::from(line)
+    });
+    let fg = match station.deleted {
+        true => Color::DarkGray,
+        false => TEXT_COLOR,
+    };
+    Row::new(cells).fg(fg).bg(bg_color)
+}
+
+/// The characters of a station that matched the filter.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StationMatch {
+    pub score: i64,
+    /// Character indices into the station name, as shown in the table.
+    pub name: Vec<usize>,
+    /// Character indices into the comma joined tags, as shown in the table.
+    pub tags: Vec<usize>,
+}
+
+/// Fuzzy matches `filter` against the name and the tags of `station`.
+pub fn match_station(filter: &str, station: &RadioStation) -> Option<StationMatch> {
+    let name = fuzzy_match(filter, StationColumn::Name.text(station, 0).as_str());
+    let tags = fuzzy_match(filter, StationColumn::Tags.text(station, 0).as_str());
+    if name.is_none() && tags.is_none() {
+        return None;
+    }
+    let (name, tags) = (name.unwrap_or_default(), tags.unwrap_or_default());
+    Some(StationMatch {
+        score: name.score.max(tags.score),
+        name: name.indices,
+        tags: tags.indices,
+    })
+}
+
+pub struct StationsList {
+    pub state: TableState,
+    items: Vec<RadioStation>,
+    /// Indices into `items` of the rows shown, only the matches while a filter is set.
+    visible: Vec<usize>,
+    matches: HashMap<usize, StationMatch>,
+    filter: String,
+    /// The station selected before filtering started, restored when the filter is cleared.
+    selection_before_filter: Option<String>,
+    last_selected: Option<usize>,
+    pub columns: Vec<StationColumn>,
+    pub sort: Option<SortOrder>,
+}
+
+impl Default for StationsList {
+    fn default() -> Self {
+        Self {
+            state: Default::default(),
+            items: Default::default(),
+            visible: Default::default(),
+            matches: Default::default(),
+            filter: Default::default(),
+            selection_before_filter: None,
+            last_selected: None,
+            columns: StationColumn::ALL.to_vec(),
+            sort: None,
+        }
+    }
+}
+
+impl StationsList {
+    pub fn items(&self) -> &[RadioStation] {
+        &self.items
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.visible.is_empty()
+    }
+
+    /// The number of stations shown, which is less than all items while a filter is set.
+    pub fn visible_len(&self) -> usize {
+        self.visible.len()
+    }
+
+    /// Replaces the stations, keeping the columns, the local sort order and the filter.
+    pub fn set_items(&mut self, items: Vec<RadioStation>) {
+        self.items = items;
+        self.state = Default::default();
+        self.last_selected = None;
+        self.selection_before_filter = None;
+        if let Some(sort) = self.sort {
+            sort.sort(&mut self.items);
+        }
+        self.refresh_visible();
+    }
+
+    /// Recomputes the visible rows after the items or the filter changed, keeping the selected
+    /// station selected if it is still visible.
+    fn refresh_visible(&mut self) {
+        let selected = self.selected().map(|s| s.stationuuid.clone());
+
+        self.matches.clear();
+        if self.filter.trim().is_empty() {
+            self.visible = (0..self.items.len()).collect();
+        } else {
+            for (i, station) in self.items.iter().enumerate() {
+                if let Some(m) = match_station(&self.filter, station) {
+                    self.matches.insert(i, m);
+                }
+            }
+            self.visible = (0..self.items.len())
+                .filter(|i| self.matches.contains_key(i))
+                .collect();
+        }
+
+        match selected {
+            Some(uuid) => self.select_uuid(&uuid),
+            None => self.state.select(None),
+        }
+        if self.state.selected().is_none() && !self.filter.is_empty() && !self.is_empty() {
+            self.state.select(Some(0));
+        }
+    }
+
+    /// Applies `f` to every station with the given uuid, e.g. to reflect a vote locally.
+    pub fn update_station(&mut self, uuid: &str, mut f: impl FnMut(&mut RadioStation)) {
+        self.items
+            .iter_mut()
+            .filter(|station| station.stationuuid == uuid)
+            .for_each(&mut f);
+    }
+
+    /// Selects the visible station with the given uuid, or nothing if it is not visible.
+    pub fn select_uuid(&mut self, uuid: &str) {
+        let index = self
+            .visible
+            .iter()
+            .position(|i| self.items[*i].stationuuid == uuid);
+        self.state.select(index);
+    }
+
+    pub fn filter(&self) -> &str {
+        &self.filter
+    }
+
+    pub fn set_filter(&mut self, filter: &str) {
+        if self.filter.is_empty() && self.selection_before_filter.is_none() {
+            self.selection_before_filter = self.selected().map(|s| s.stationuuid.clone());
+        }
+        self.filter = filter.to_string();
+        self.refresh_visible();
+    }
+
+    /// Shows all stations again and restores the selection from before filtering.
+    pub fn clear_filter(&mut self) {
+        self.filter.clear();
+        self.refresh_visible();
+        if let Some(uuid) = self.selection_before_filter.take() {
+            self.select_uuid(&uuid);
+        }
+    }
+
+    /// Sorts the stations locally, keeping the selected station selected.
+    ///
+    /// Passing `None` leaves the current order alone, the directory order is not restored.
+    pub fn sort_by(&mut self, sort: Option<SortOrder>) {
+        self.sort = sort;
+        let Some(sort) = sort else {
+            return;
+        };
+        let selected = self.selected().map(|s| s.stationuuid.clone());
+        sort.sort(&mut self.items);
+        self.refresh_visible();
+        if let Some(uuid) = selected {
+            self.select_uuid(&uuid);
+        }
+    }
+
+    /// Sorts by the next visible column, ascending, and finally returns to the search order.
+    pub fn cycle_sort(&mut self) {
+        let next = match self.sort {
+            None => self.columns.first().copied(),
+            Some(sort) => self
+                .columns
+                .iter()
+                .skip_while(|column| **column != sort.column)
+                .nth(1)
+                .copied(),
+        };
+        self.sort_by(next.map(|column| SortOrder {
+            column,
+            descending: false,
+        }));
+    }
+
+    pub fn reverse_sort(&mut self) {
+        if let Some(sort) = self.sort {
+            self.sort_by(Some(SortOrder {
+                descending: !sort.descending,
+                ..sort
+            }));
+        }
+    }
+
+    pub fn next(&mut self) {
+        if self.visible.is_empty() {
+            return self.state.select(None);
+        }
+        let i = match self.state.selected() {
+            Some(i) => {
+                if i >= self.visible.len() - 1 {
+                    0
+                } else {
+                    i + 1
+                }
+            }
+            None => self.last_selected.unwrap_or(0).min(self.visible.len() - 1),
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn previous(&mut self) {
+        if self.visible.is_empty() {
+            return self.state.select(None);
+        }
+        let i = match self.state.selected() {
+            Some(i) => {
+                if i == 0 {
+                    self.visible.len() - 1
+                } else {
+                    i - 1
+                }
+            }
+            None => self.last_selected.unwrap_or(0).min(self.visible.len() - 1),
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn unselect(&mut self) {
+        let offset = self.state.offset();
+        self.last_selected = self.state.selected();
+        self.state.select(None);
+        *self.state.offset_mut() = offset;
+    }
+
+    pub fn select_station(&mut self) -> Option<RadioStation> {
+        self.selected().cloned()
+    }
+
+    pub fn selected(&self) -> Option<&RadioStation> {
+        self.state
+            .selected()
+            .and_then(|i| self.visible.get(i))
+            .and_then(|i| self.items.get(*i))
+    }
+
+    /// The visible stations in display order, each with its index into [`Self::items`].
+    pub fn visible_items(&self) -> impl Iterator<Item = (usize, &RadioStation)> {
+        self.visible.iter().map(|i| (*i, &self.items[*i]))
+    }
+
+    /// Builds the table rows of the visible stations, marking those for which `is_favorite`
+    /// returns `true`.
+    pub fn rows(
+        &self,
+        columns: &[StationColumn],
+        width: u16,
+        is_favorite: impl Fn(&RadioStation) -> bool,
+    ) -> Vec<Row<'static>> {
+        self.visible
+            .iter()
+            .enumerate()
+            .map(|(row_index, i)| {
+                row(
+                    &self.items[*i],
+                    row_index,
+                    columns,
+                    width,
+                    self.matches.get(i),
+                    is_favorite(&self.items[*i]),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn station(name: &str, bitrate: u32, votes: i32) -> RadioStation {
+        RadioStation {
+            name: name.to_string(),
+            stationuuid: name.to_string(),
+            bitrate,
+            votes,
+            ..Default::default()
+        }
+    }
+
+    fn names(stations: &[RadioStation]) -> Vec<&str> {
+        stations.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sort_by_name_ignores_case() {
+        let mut stations = vec![station("b", 0, 0), station("A", 0, 0), station("c", 0, 0)];
+        SortOrder {
+            column: StationColumn::Name,
+            descending: false,
+        }
+        .sort(&mut stations);
+        assert_eq!(names(&stations), vec!["A", "b", "c"]);
+    }
+
+    #[test]
+    fn test_sort_numeric_descending() {
+        let mut stations = vec![
+            station("low", 64, 10),
+            station("high", 320, 1),
+            station("mid", 128, 5),
+        ];
+        SortOrder {
+            column: StationColumn::Bitrate,
+            descending: true,
+        }
+        .sort(&mut stations);
+        assert_eq!(names(&stations), vec!["high", "mid", "low"]);
+    }
+
+    fn list(stations: Vec<RadioStation>) -> StationsList {
+        let mut list = StationsList::default();
+        list.set_items(stations);
+        list
+    }
+
+    fn visible_names(list: &StationsList) -> Vec<String> {
+        list.visible
+            .iter()
+            .map(|i| list.items[*i].name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_matches_name_and_tags() {
+        let mut jazz = station("Radio One", 128, 0);
+        jazz.tags = vec!["jazz".to_string()];
+        let mut list = list(vec![
+            station("Jazz FM", 128, 0),
+            station("Rock Antenne", 128, 0),
+            jazz,
+        ]);
+        list.set_filter("jazz");
+        assert_eq!(visible_names(&list), vec!["Jazz FM", "Radio One"]);
+        assert_eq!(list.selected().unwrap().name, "Jazz FM");
+    }
+
+    #[test]
+    fn test_navigation_only_visits_matches() {
+        let mut list = list(vec![
+            station("abc", 0, 0),
+            station("xyz", 0, 0),
+            station("abd", 0, 0),
+        ]);
+        list.set_filter("ab");
+        list.next();
+        assert_eq!(list.selected().unwrap().name, "abd");
+        list.next();
+        assert_eq!(list.selected().unwrap().name, "abc");
+    }
+
+    #[test]
+    fn test_clear_filter_restores_selection() {
+        let mut list = list(vec![
+            station("abc", 0, 0),
+            station("xyz", 0, 0),
+            station("abd", 0, 0),
+        ]);
+        list.next();
+        list.next();
+        assert_eq!(list.selected().unwrap().name, "xyz");
+        list.set_filter("ab");
+        list.set_filter("abd");
+        assert_eq!(list.selected().unwrap().name, "abd");
+        list.clear_filter();
+        assert_eq!(visible_names(&list).len(), 3);
+        assert_eq!(list.selected().unwrap().name, "xyz");
+    }
+
+    #[test]
+    fn test_sort_keeps_selection() {
+        let mut list = list(vec![
+            station("b", 0, 0),
+            station("c", 0, 0),
+            station("a", 0, 0),
+        ]);
+        list.next();
+        list.next();
+        assert_eq!(list.selected().unwrap().name, "c");
+        list.cycle_sort();
+        assert_eq!(visible_names(&list), vec!["a", "b", "c"]);
+        assert_eq!(list.selected().unwrap().name, "c");
+    }
+
+    #[test]
+    fn test_visible_columns_fit() {
+        assert_eq!(
+            visible_columns(&StationColumn::ALL, 200),
+            StationColumn::ALL.to_vec()
+        );
+        assert_eq!(
+            visible_columns(&StationColumn::ALL, 40),
+            vec![
+                StationColumn::Name,
+                StationColumn::Codec,
+                StationColumn::Bitrate
+            ]
+        );
+        assert_eq!(
+            visible_columns(&StationColumn::ALL, 5),
+            vec![StationColumn::Name]
+        );
+    }
+}
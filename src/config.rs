@@ -11,7 +11,12 @@ use serde::{
 };
 use serde_json::Value as JsonValue;
 
-use crate::{action::Action, mode::Mode};
+use crate::{
+    action::Action,
+    components::station_table::StationColumn,
+    mode::Mode,
+    models::{CacheLimits, Retention},
+};
 
 const CONFIG: &str = include_str!("../.config/config.json5");
 
@@ -23,11 +28,307 @@ pub struct AppConfig {
     pub _config_dir: PathBuf,
 }
 
+/// Settings for station searches.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SearchConfig {
+    /// Seconds after which an unanswered station search is abandoned.
+    pub timeout_secs: u64,
+    /// Milliseconds typing has to pause before a live search is sent.
+    pub live_debounce_ms: u64,
+    /// How many recent searches can be recalled with <Up> and <Down>.
+    pub history_size: usize,
+    /// Move the stations that fail the directory's checks after the others in the results.
+    pub sink_unreliable: bool,
+    /// Show each station once in the results, the other listings of it in its details.
+    pub group_duplicates: bool,
+    /// Leave the stations whose last check failed out of the results.
+    pub hide_failing: bool,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 15,
+            live_debounce_ms: 350,
+            history_size: 20,
+            sink_unreliable: false,
+            group_duplicates: true,
+            hide_failing: false,
+        }
+    }
+}
+
+impl SearchConfig {
+    pub fn timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.timeout_secs)
+    }
+
+    pub fn live_debounce(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.live_debounce_ms)
+    }
+}
+
+/// Settings for the cache of directory responses.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    /// Minutes a response is used without asking the directory. Older ones are still used, but
+    /// fetched again in the background.
+    pub ttl_mins: u64,
+    /// The most responses kept.
+    pub max_entries: usize,
+    /// The most megabytes of responses kept.
+    pub max_size_mb: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ttl_mins: 60,
+            max_entries: 500,
+            max_size_mb: 20,
+        }
+    }
+}
+
+impl CacheConfig {
+    pub fn limits(&self) -> CacheLimits {
+        CacheLimits {
+            ttl: std::time::Duration::from_secs(self.ttl_mins * 60),
+            max_entries: self.max_entries,
+            max_bytes: self.max_size_mb * 1024 * 1024,
+        }
+    }
+}
+
+/// Settings for checking that the streams of a list of stations play.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct CheckConfig {
+    /// How many streams are connected to at the same time.
+    pub concurrency: usize,
+    /// Seconds a stream has to send its first audio before it counts as broken.
+    pub timeout_secs: u64,
+}
+
+impl Default for CheckConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 8,
+            timeout_secs: 10,
+        }
+    }
+}
+
+impl CheckConfig {
+    pub fn timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.timeout_secs)
+    }
+}
+
+/// Settings for talking to the radio-browser directory.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct DirectoryConfig {
+    /// Base url of the radio-browser server asked for searches, lookups, clicks, votes and
+    /// syncs.
+    pub api_url: String,
+    /// Report a click to the directory whenever a station starts playing.
+    pub report_clicks: bool,
+    /// Seconds before the same station can be voted for again.
+    pub vote_cooldown_secs: u64,
+    /// Days of the directory's checks of a station shown in its details.
+    pub check_history_days: u64,
+}
+
+impl Default for DirectoryConfig {
+    fn default() -> Self {
+        Self {
+            api_url: crate::models::DEFAULT_API_URL.to_string(),
+            report_clicks: true,
+            vote_cooldown_secs: 600,
+            check_history_days: 7,
+        }
+    }
+}
+
+impl DirectoryConfig {
+    pub fn vote_cooldown(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.vote_cooldown_secs)
+    }
+
+    /// Seconds of the directory's checks of a station shown in its details.
+    pub fn check_history_secs(&self) -> u64 {
+        self.check_history_days * 24 * 60 * 60
+    }
+}
+
+/// Settings for the listening history.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// Whether played stations are recorded at all.
+    pub enabled: bool,
+    /// The most entries kept, `0` keeps all.
+    pub max_entries: usize,
+    /// Entries older than this many days are dropped, `0` keeps them forever.
+    pub max_age_days: u64,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_entries: 1000,
+            max_age_days: 90,
+        }
+    }
+}
+
+impl HistoryConfig {
+    pub fn retention(&self) -> Retention {
+        Retention {
+            max_entries: self.max_entries,
+            max_age_days: self.max_age_days,
+        }
+    }
+}
+
+/// Settings for saving and restoring the session between runs.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SessionConfig {
+    /// Restore the last station, search results, selection and view at startup.
+    pub restore: bool,
+    /// Start playing the last station when restoring.
+    pub autoplay: bool,
+    /// Seconds between saves while running, `0` only saves on quit and suspend.
+    pub save_interval_secs: u64,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            restore: false,
+            autoplay: false,
+            save_interval_secs: 60,
+        }
+    }
+}
+
+/// Settings for the log of the tracks heard.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SongLogConfig {
+    /// Whether the tracks announced by stations are recorded.
+    pub enabled: bool,
+    /// The most tracks kept, `0` keeps all.
+    pub max_entries: usize,
+    /// Where exports are written, the data directory if unset.
+    pub export_dir: Option<PathBuf>,
+}
+
+impl Default for SongLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_entries: 5000,
+            export_dir: None,
+        }
+    }
+}
+
+/// Settings for the Icecast and Shoutcast style listings searched along with the directory.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ListingsConfig {
+    /// The urls of the listings, each either an Icecast `yp.xml` or a Shoutcast station list.
+    /// None by default, the listings are large and fetched on the first tick.
+    pub urls: Vec<String>,
+    /// Hours after which a listing is fetched again.
+    pub refresh_hours: u64,
+}
+
+impl Default for ListingsConfig {
+    fn default() -> Self {
+        Self {
+            urls: Vec::new(),
+            refresh_hours: 24,
+        }
+    }
+}
+
+impl ListingsConfig {
+    pub fn refresh_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.refresh_hours * 60 * 60)
+    }
+}
+
+/// Settings for the local copy of the directory made by `voxide sync`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct MirrorConfig {
+    /// Search the copy instead of the directory once there is one.
+    pub search_offline: bool,
+}
+
+impl Default for MirrorConfig {
+    fn default() -> Self {
+        Self {
+            search_offline: true,
+        }
+    }
+}
+
+/// Settings for the stations table.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct StationsConfig {
+    /// The columns to show, in order. Columns are hidden when the terminal is too narrow.
+    pub columns: Vec<StationColumn>,
+    /// The column to sort search results by locally, `None` keeps the directory's order.
+    pub sort: Option<StationColumn>,
+    pub sort_descending: bool,
+}
+
+impl Default for StationsConfig {
+    fn default() -> Self {
+        Self {
+            columns: StationColumn::ALL.to_vec(),
+            sort: None,
+            sort_descending: false,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, Deserialize)]
 pub struct Config {
     #[serde(default, flatten)]
     pub config: AppConfig,
     #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub check: CheckConfig,
+    #[serde(default)]
+    pub directory: DirectoryConfig,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    #[serde(default)]
+    pub listings: ListingsConfig,
+    #[serde(default)]
+    pub mirror: MirrorConfig,
+    #[serde(default)]
+    pub search: SearchConfig,
+    #[serde(default)]
+    pub session: SessionConfig,
+    #[serde(default)]
+    pub song_log: SongLogConfig,
+    #[serde(default)]
+    pub stations: StationsConfig,
+    #[serde(default)]
     pub keybindings: KeyBindings,
     #[serde(default)]
     pub styles: Styles,
@@ -13,4 +13,37 @@ pub enum Error {
     /// Error related to locking resources.
     #[error("LockError: {0}")]
     Lock(String),
+    /// The directory rejected a request, e.g. a vote.
+    #[error("ApiError: {0}")]
+    Api(String),
+    /// Error reading or writing local data.
+    #[error("IoError: {0}")]
+    Io(#[from] std::io::Error),
+    /// A file was written by a newer version of voxide.
+    #[error(
+        "UnsupportedVersion: {file} has version {version}, this build reads up to {supported}"
+    )]
+    UnsupportedVersion {
+        file: String,
+        version: u32,
+        supported: u32,
+    },
+    /// An operation on the collections was not possible.
+    #[error("CollectionError: {0}")]
+    Collection(String),
+    /// The custom stations file is invalid.
+    #[error("CustomStationsError: {0}")]
+    CustomStations(String),
+    /// A url could not be turned into a playable stream.
+    #[error("StreamError: {0}")]
+    Stream(String),
+    /// A station listing could not be read.
+    #[error("ListingError: {0}")]
+    Listing(String),
+    /// A playlist could not be read.
+    #[error("PlaylistError: {0}")]
+    Playlist(String),
+    /// Error (de)serializing local data.
+    #[error("JsonError: {0}")]
+    Json(#[from] serde_json::Error),
 }
@@ -5,6 +5,7 @@
 pub mod action;
 pub mod app;
 pub mod cli;
+pub mod commands;
 pub mod components;
 pub mod config;
 pub mod errors;
@@ -28,6 +29,9 @@ async fn tokio_main() -> Result<()> {
     initialize_panic_handler()?;
 
     let args = Cli::parse();
+    if let Some(command) = args.command {
+        return commands::run(command).await;
+    }
     let mut app = App::new(args.tick_rate, args.frame_rate).await?;
     app.run().await?;
 
@@ -8,4 +8,10 @@ pub enum Mode {
     Home,
     /// The search mode, used for searching functionality.
     Search,
+    /// Typing a filter for the stations list.
+    Filter,
+    /// Browsing the song log.
+    SongLog,
+    /// Answering a prompt of the home screen.
+    Prompt,
 }
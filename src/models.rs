@@ -1,6 +1,49 @@
 mod audio_stream;
+mod check_history;
+mod collections;
+mod countries;
+mod custom_stations;
+mod duplicates;
+mod favorites;
+mod history;
+mod icy;
+mod listings;
+mod mirror;
+mod playlist;
 mod radio_api;
 mod radio_station;
+mod response_cache;
+mod saved_searches;
+mod search_query;
+mod session;
+mod song_log;
+mod station_refresh;
+mod stream_check;
+mod stream_probe;
+#[cfg(test)]
+mod test_server;
+mod vote_log;
 
+pub use check_history::{CheckHistories, CheckHistory, DirectoryCheck};
+pub use collections::{Collection, Collections};
+pub use countries::country_name;
+pub use custom_stations::CustomStations;
+pub use duplicates::{alternates_details, group_duplicates, is_failing, StationGroup};
+pub use favorites::Favorites;
+pub use history::{EndReason, History, HistoryEntry, Retention};
+pub use listings::{fetch_listing, parse_listing, Listing, Listings};
+pub use mirror::{Mirror, SyncReport};
+pub use playlist::PlaylistFormat;
 pub use radio_api::*;
-pub use radio_station::{RadioStation, State};
+pub use radio_station::{RadioStation, Source, State};
+pub use response_cache::{CacheLimits, ResponseCache};
+pub use saved_searches::{SavedSearch, SavedSearches};
+pub use search_query::{parse_query, query_hints, to_query, QueryError};
+pub use session::Session;
+pub use song_log::{ExportFormat, SongEntry, SongLog};
+pub use station_refresh::{RefreshReport, StationChange, StationRefresh};
+pub use stream_check::{
+    check_stream, check_streams, report_table, BrokenMarks, StreamCheck, StreamStats,
+};
+pub use stream_probe::resolve_url;
+pub use vote_log::VoteLog;
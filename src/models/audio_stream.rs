@@ -1,6 +1,6 @@
 use std::{
     collections::VecDeque,
-    io::{Read, Seek},
+    io::{Read, Seek, SeekFrom},
     sync::Arc,
 };
 
@@ -8,15 +8,27 @@ use tracing::debug;
 
 use crate::errors::Error;
 
+/// How many bytes from the start of the stream are kept so decoders can rewind while they probe
+/// its format.
+const REWIND_LIMIT: usize = 256 * 1024;
+
 pub struct AudioStream {
     buf: Arc<std::sync::Mutex<VecDeque<u8>>>,
+    /// The first bytes read, while fewer than [`REWIND_LIMIT`].
+    head: Vec<u8>,
+    /// How many bytes were read from the start of the stream.
+    pos: usize,
 }
 
 impl AudioStream {
     pub fn new() -> Self {
         let buf = Arc::new(std::sync::Mutex::new(VecDeque::<u8>::new()));
 
-        Self { buf }
+        Self {
+            buf,
+            head: Vec::new(),
+            pos: 0,
+        }
     }
 
     pub fn get_buf(&self) -> Arc<std::sync::Mutex<VecDeque<u8>>> {
@@ -32,17 +44,78 @@ impl AudioStream {
     }
 }
 
-// Seeking is not allowed
+// Seeking is only allowed back into the start of the stream, to probe its format
 impl Seek for AudioStream {
-    fn seek(&mut self, _pos: std::io::SeekFrom) -> std::io::Result<u64> {
-        Ok(0)
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(n) => n as i64,
+            SeekFrom::Current(n) => self.pos as i64 + n,
+            SeekFrom::End(_) => -1,
+        };
+        let target = usize::try_from(target)
+            .ok()
+            .filter(|target| *target <= self.head.len() || *target == self.pos)
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "a stream can only be rewound to its start",
+                )
+            })?;
+        self.pos = target;
+        Ok(target as u64)
     }
 }
 
 impl Read for AudioStream {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos < self.head.len() {
+            let n = buf.len().min(self.head.len() - self.pos);
+            buf[..n].copy_from_slice(&self.head[self.pos..self.pos + n]);
+            self.pos += n;
+            return Ok(n);
+        }
         let mut guard = self.buf.lock().expect("failed to lock buffer");
         debug!("reading: {}", buf.len());
-        guard.read(buf)
+        let n = guard.read(buf)?;
+        if self.head.len() == self.pos && self.pos + n <= REWIND_LIMIT {
+            self.head.extend_from_slice(&buf[..n]);
+        }
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_rewind_to_start() -> std::io::Result<()> {
+        let mut stream = AudioStream::new();
+        stream.get_buf().lock().unwrap().extend(b"OggS and more");
+        let mut probe = [0; 4];
+        stream.read_exact(&mut probe)?;
+        assert_eq!(&probe, b"OggS");
+        assert_eq!(stream.stream_position()?, 4);
+
+        stream.seek(SeekFrom::Start(0))?;
+        let mut all = Vec::new();
+        stream.read_to_end(&mut all)?;
+        assert_eq!(all, b"OggS and more");
+        assert!(stream.seek(SeekFrom::End(0)).is_err());
+        assert!(stream.seek(SeekFrom::Start(100)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decoder_probes_format() {
+        let stream = AudioStream::new();
+        // Silent MPEG-1 Layer III frames, which the WAV, FLAC and Vorbis probes read first
+        let mut frame = vec![0; 417];
+        frame[..4].copy_from_slice(&[0xff, 0xfb, 0x90, 0x64]);
+        stream.get_buf().lock().unwrap().extend(frame.repeat(30));
+        assert!(rodio::Decoder::new(stream).is_ok());
     }
 }
@@ -0,0 +1,358 @@
+//! The directory's history of checks of a station's stream, which tells how reliable it is.
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use chrono::DateTime;
+use ratatui::{
+    style::{palette::tailwind, Style},
+    text::{Line, Span},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{radio_station::label, RadioStation};
+use crate::{
+    errors::Error,
+    utils::{format_age, write_atomic},
+};
+
+/// Stations passing a smaller share of their checks are unreliable.
+pub const RELIABLE_UPTIME: f64 = 0.8;
+/// The bars of a sparkline, from no failures to only failures.
+const SPARK_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+/// How many spans of time the failure sparkline shows.
+const SPARK_WIDTH: usize = 28;
+
+/// A check of a station's stream by the directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirectoryCheck {
+    pub ok: bool,
+    /// Seconds since the unix epoch.
+    pub checked_at: u64,
+    /// The codec and bitrate the check found.
+    pub codec: String,
+    pub bitrate: u32,
+}
+
+/// A check as the `checks` endpoint of the directory returns it.
+#[derive(Debug, Deserialize)]
+pub(super) struct ApiCheck {
+    ok: u8,
+    timestamp_iso8601: String,
+    #[serde(default)]
+    codec: String,
+    #[serde(default)]
+    bitrate: u32,
+}
+
+impl ApiCheck {
+    /// The check, or `None` if its time cannot be read.
+    pub(super) fn into_check(self) -> Option<DirectoryCheck> {
+        Some(DirectoryCheck {
+            ok: self.ok == 1,
+            checked_at: DateTime::parse_from_rfc3339(&self.timestamp_iso8601)
+                .ok()?
+                .timestamp()
+                .try_into()
+                .ok()?,
+            codec: self.codec,
+            bitrate: self.bitrate,
+        })
+    }
+}
+
+/// The recent checks of a station.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CheckHistory {
+    /// Seconds since the unix epoch when the checks were fetched.
+    pub fetched_at: u64,
+    /// The checks, oldest first.
+    pub checks: Vec<DirectoryCheck>,
+}
+
+impl CheckHistory {
+    pub fn new(mut checks: Vec<DirectoryCheck>, fetched_at: u64) -> Self {
+        checks.sort_by_key(|check| check.checked_at);
+        Self { fetched_at, checks }
+    }
+
+    /// The share of the checks that passed, `None` if there are none.
+    pub fn uptime(&self) -> Option<f64> {
+        if self.checks.is_empty() {
+            return None;
+        }
+        let passed = self.checks.iter().filter(|check| check.ok).count();
+        Some(passed as f64 / self.checks.len() as f64)
+    }
+
+    pub fn is_unreliable(&self) -> bool {
+        self.uptime().is_some_and(|uptime| uptime < RELIABLE_UPTIME)
+    }
+
+    /// The share of failed checks in each of `buckets` equal spans of the `span` seconds up to
+    /// `now`, oldest first, `None` for spans without checks.
+    pub fn failure_rates(&self, buckets: usize, span: u64, now: u64) -> Vec<Option<f64>> {
+        let start = now.saturating_sub(span);
+        let mut counts = vec![(0, 0); buckets];
+        for check in &self.checks {
+            if check.checked_at < start || check.checked_at > now || span == 0 {
+                continue;
+            }
+            let bucket = ((check.checked_at - start) * buckets as u64 / span) as usize;
+            let (failed, total) = &mut counts[bucket.min(buckets - 1)];
+            *failed += usize::from(!check.ok);
+            *total += 1;
+        }
+        counts
+            .into_iter()
+            .map(|(failed, total)| (total > 0).then(|| failed as f64 / total as f64))
+            .collect()
+    }
+
+    /// The failure rates as bars, blank for spans without checks.
+    pub fn sparkline(&self, buckets: usize, span: u64, now: u64) -> String {
+        self.failure_rates(buckets, span, now)
+            .into_iter()
+            .map(|rate| match rate {
+                Some(rate) => SPARK_BARS[(rate * (SPARK_BARS.len() - 1) as f64).round() as usize],
+                None => ' ',
+            })
+            .collect()
+    }
+
+    /// The uptime and the failures of the `span` seconds up to `now`, for the details of the
+    /// station.
+    pub fn to_details(&self, span: u64, now: u64) -> Vec<Line<'static>> {
+        let days = span.div_ceil(86400);
+        let Some(uptime) = self.uptime() else {
+            return vec![Line::from(vec![
+                label("Uptime"),
+                Span::raw(format!("no checks in the last {days} days")),
+            ])];
+        };
+        let color = match uptime < RELIABLE_UPTIME {
+            true => tailwind::RED.c500,
+            false => tailwind::GREEN.c500,
+        };
+        let mut lines = vec![
+            Line::from(vec![
+                label("Uptime"),
+                Span::styled(
+                    format!("{:.1}%", uptime * 100.0),
+                    Style::default().fg(color),
+                ),
+                Span::raw(format!(" of {} checks", self.checks.len())),
+            ]),
+            Line::from(vec![
+                label("Failures"),
+                Span::styled(
+                    self.sparkline(SPARK_WIDTH, span, now),
+                    Style::default().fg(tailwind::RED.c400),
+                ),
+                Span::raw(format!(" last {days}d")),
+            ]),
+        ];
+        if let Some(failed) = self.checks.iter().rev().find(|check| !check.ok) {
+            lines.push(Line::from(vec![
+                label("Last fail"),
+                Span::raw(format_age(failed.checked_at, now)),
+            ]));
+        }
+        lines
+    }
+}
+
+/// The check histories fetched, by `stationuuid`.
+///
+/// Persisted as JSON in the data directory.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CheckHistories {
+    /// The version of the file format, see [`CheckHistories::VERSION`].
+    version: u32,
+    histories: HashMap<String, CheckHistory>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Default for CheckHistories {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            histories: HashMap::new(),
+            path: PathBuf::new(),
+        }
+    }
+}
+
+impl CheckHistories {
+    pub const FILE_NAME: &'static str = "check_history.json";
+    /// The newest file format this build reads.
+    pub const VERSION: u32 = 1;
+    /// Seconds a history is used before it is fetched again.
+    pub const MAX_AGE: u64 = 6 * 60 * 60;
+    /// The most histories kept, the ones fetched longest ago are dropped first.
+    pub const MAX_ENTRIES: usize = 500;
+
+    /// Loads the histories from `path`, starting empty if the file does not exist yet.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut histories = match std::fs::read(path) {
+            Ok(data) => serde_json::from_slice::<Self>(&data)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e.into()),
+        };
+        if histories.version > Self::VERSION {
+            return Err(Error::UnsupportedVersion {
+                file: path.display().to_string(),
+                version: histories.version,
+                supported: Self::VERSION,
+            });
+        }
+        histories.version = Self::VERSION;
+        histories.path = path.to_path_buf();
+        Ok(histories)
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        write_atomic(&self.path, &serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, stationuuid: &str) -> Option<&CheckHistory> {
+        self.histories.get(stationuuid)
+    }
+
+    /// Whether the history of the station was fetched less than [`CheckHistories::MAX_AGE`]
+    /// ago.
+    pub fn is_fresh(&self, stationuuid: &str, now: u64) -> bool {
+        self.get(stationuuid)
+            .is_some_and(|history| now.saturating_sub(history.fetched_at) < Self::MAX_AGE)
+    }
+
+    /// Stores the history of a station, dropping the oldest beyond
+    /// [`CheckHistories::MAX_ENTRIES`], and saves.
+    pub fn insert(&mut self, stationuuid: &str, history: CheckHistory) -> Result<(), Error> {
+        self.histories.insert(stationuuid.to_string(), history);
+        while self.histories.len() > Self::MAX_ENTRIES {
+            let oldest = self
+                .histories
+                .iter()
+                .min_by_key(|(_, history)| history.fetched_at)
+                .map(|(uuid, _)| uuid.clone());
+            if let Some(uuid) = oldest {
+                self.histories.remove(&uuid);
+            }
+        }
+        self.save()
+    }
+
+    /// Whether the station failed too many of its checks, or the directory's last one if its
+    /// history was not fetched.
+    pub fn is_unreliable(&self, station: &RadioStation) -> bool {
+        match self.get(&station.stationuuid) {
+            Some(history) => history.is_unreliable(),
+            None => station.is_directory() && !station.lastcheckok,
+        }
+    }
+
+    /// Moves the unreliable stations after the others, keeping the order otherwise.
+    pub fn sink_unreliable(&self, stations: &mut [RadioStation]) {
+        stations.sort_by_key(|station| self.is_unreliable(station));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn check(ok: bool, checked_at: u64) -> DirectoryCheck {
+        DirectoryCheck {
+            ok,
+            checked_at,
+            codec: "MP3".to_string(),
+            bitrate: 128,
+        }
+    }
+
+    #[test]
+    fn test_uptime_and_sparkline() -> Result<(), Error> {
+        let json = r#"[
+            {"ok": 1, "timestamp_iso8601": "2024-05-01T12:30:00Z", "codec": "MP3", "bitrate": 128},
+            {"ok": 0, "timestamp_iso8601": "2024-05-01T12:00:00Z"},
+            {"ok": 1, "timestamp_iso8601": "not a time"}
+        ]"#;
+        let checks: Vec<ApiCheck> = serde_json::from_slice(json.as_bytes())?;
+        let checks: Vec<_> = checks
+            .into_iter()
+            .filter_map(ApiCheck::into_check)
+            .collect();
+        assert_eq!(
+            CheckHistory::new(checks, 0).checks,
+            vec![
+                DirectoryCheck {
+                    ok: false,
+                    checked_at: 1_714_564_800,
+                    codec: String::new(),
+                    bitrate: 0
+                },
+                check(true, 1_714_566_600),
+            ]
+        );
+
+        let history = CheckHistory::new(
+            vec![
+                check(true, 10),
+                check(false, 15),
+                check(true, 25),
+                check(false, 35),
+                check(false, 36),
+                check(true, 100),
+            ],
+            0,
+        );
+        assert_eq!(history.uptime(), Some(0.5));
+        assert!(history.is_unreliable());
+        assert_eq!(
+            history.failure_rates(4, 40, 40),
+            vec![None, Some(0.5), Some(0.0), Some(1.0)]
+        );
+        assert_eq!(history.sparkline(4, 40, 40), " ▅▁█");
+        assert_eq!(CheckHistory::default().uptime(), None);
+        assert!(!CheckHistory::default().is_unreliable());
+        Ok(())
+    }
+
+    #[test]
+    fn test_histories_sink_unreliable() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(CheckHistories::FILE_NAME);
+        let mut histories = CheckHistories::load(&path)?;
+        histories.insert("flaky", CheckHistory::new(vec![check(false, 1)], 100))?;
+        histories.insert("steady", CheckHistory::new(vec![check(true, 1)], 100))?;
+        let histories = CheckHistories::load(&path)?;
+        assert!(histories.is_fresh("flaky", 100 + CheckHistories::MAX_AGE - 1));
+        assert!(!histories.is_fresh("flaky", 100 + CheckHistories::MAX_AGE));
+        assert!(!histories.is_fresh("unknown", 100));
+
+        let station = |uuid: &str, lastcheckok: bool| RadioStation {
+            lastcheckok,
+            ..RadioStation::new("http://a.example", uuid, uuid)
+        };
+        let mut stations = vec![
+            station("flaky", true),
+            station("failing", false),
+            station("steady", false),
+            station("unknown", true),
+            RadioStation::custom("http://b.example", "custom"),
+        ];
+        histories.sink_unreliable(&mut stations);
+        let names: Vec<_> = stations.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["steady", "unknown", "custom", "flaky", "failing"]
+        );
+        Ok(())
+    }
+}
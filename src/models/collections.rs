@@ -0,0 +1,389 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::RadioStation;
+use crate::{errors::Error, utils::write_atomic};
+
+/// A named, ordered group of stations, like a playlist or a folder.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Collection {
+    pub name: String,
+    /// Stations from the directory as well as custom ones, see [`RadioStation::custom`].
+    pub stations: Vec<RadioStation>,
+}
+
+/// The user's collections, in the order the user arranged them.
+///
+/// Stations are addressed by their position, so a collection may hold custom stations without a
+/// directory uuid and the same station more than once.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Collections {
+    /// The version of the file format, see [`Collections::VERSION`].
+    version: u32,
+    collections: Vec<Collection>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Default for Collections {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            collections: Vec::new(),
+            path: PathBuf::new(),
+        }
+    }
+}
+
+impl Collections {
+    pub const FILE_NAME: &'static str = "collections.json";
+    /// The newest file format this build reads. Older files are upgraded when loaded.
+    pub const VERSION: u32 = 1;
+
+    /// Loads the collections from `path`, starting with none if the file does not exist yet.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut collections = match std::fs::read(path) {
+            Ok(data) => serde_json::from_slice::<Self>(&data)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e.into()),
+        };
+        if collections.version > Self::VERSION {
+            return Err(Error::UnsupportedVersion {
+                file: path.display().to_string(),
+                version: collections.version,
+                supported: Self::VERSION,
+            });
+        }
+        collections.version = Self::VERSION;
+        collections.path = path.to_path_buf();
+        Ok(collections)
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        write_atomic(&self.path, &serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn collections(&self) -> &[Collection] {
+        &self.collections
+    }
+
+    /// Calls `update` on every station of the collections, which returns whether it changed the
+    /// station, and saves if any changed.
+    pub fn update_stations(
+        &mut self,
+        mut update: impl FnMut(&mut RadioStation) -> bool,
+    ) -> Result<(), Error> {
+        let mut changed = false;
+        for station in self.collections.iter_mut().flat_map(|c| &mut c.stations) {
+            changed |= update(station);
+        }
+        if changed {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Collection> {
+        self.collections.iter().find(|c| c.name == name)
+    }
+
+    fn position(&self, name: &str) -> Result<usize, Error> {
+        self.collections
+            .iter()
+            .position(|c| c.name == name)
+            .ok_or_else(|| Error::Collection(format!("there is no collection named {name}")))
+    }
+
+    /// Checks that `name` can be used for a new collection and returns it trimmed.
+    fn new_name(&self, name: &str) -> Result<String, Error> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(Error::Collection("collection names cannot be empty".into()));
+        }
+        if self.get(name).is_some() {
+            return Err(Error::Collection(format!("{name} already exists")));
+        }
+        Ok(name.to_string())
+    }
+
+    pub fn create(&mut self, name: &str) -> Result<(), Error> {
+        let name = self.new_name(name)?;
+        self.collections.push(Collection {
+            name,
+            stations: Vec::new(),
+        });
+        self.save()
+    }
+
+    pub fn rename(&mut self, name: &str, new_name: &str) -> Result<(), Error> {
+        let i = self.position(name)?;
+        let new_name = self.new_name(new_name)?;
+        self.collections[i].name = new_name;
+        self.save()
+    }
+
+    pub fn delete(&mut self, name: &str) -> Result<Collection, Error> {
+        let i = self.position(name)?;
+        let collection = self.collections.remove(i);
+        self.save()?;
+        Ok(collection)
+    }
+
+    /// Moves the collection `offset` places down, or up for negative offsets. Returns `false`
+    /// if it is already at the edge.
+    pub fn move_collection(&mut self, name: &str, offset: isize) -> Result<bool, Error> {
+        let from = self.position(name)?;
+        let to = from
+            .saturating_add_signed(offset)
+            .min(self.collections.len() - 1);
+        if to == from {
+            return Ok(false);
+        }
+        let collection = self.collections.remove(from);
+        self.collections.insert(to, collection);
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Appends the station to the collection, creating the collection if it does not exist.
+    pub fn add_station(&mut self, name: &str, station: RadioStation) -> Result<(), Error> {
+        self.add_stations(name, [station])
+    }
+
+    /// Appends the stations to the collection, creating the collection if it does not exist.
+    pub fn add_stations(
+        &mut self,
+        name: &str,
+        stations: impl IntoIterator<Item = RadioStation>,
+    ) -> Result<(), Error> {
+        let i = match self.position(name) {
+            Ok(i) => i,
+            Err(_) => {
+                let name = self.new_name(name)?;
+                self.collections.push(Collection {
+                    name,
+                    stations: Vec::new(),
+                });
+                self.collections.len() - 1
+            }
+        };
+        self.collections[i].stations.extend(stations);
+        self.save()
+    }
+
+    fn station_index(&self, collection: usize, index: usize) -> Result<(), Error> {
+        if index < self.collections[collection].stations.len() {
+            Ok(())
+        } else {
+            Err(Error::Collection(format!(
+                "{} has no station {index}",
+                self.collections[collection].name
+            )))
+        }
+    }
+
+    pub fn remove_station(&mut self, name: &str, index: usize) -> Result<RadioStation, Error> {
+        let i = self.position(name)?;
+        self.station_index(i, index)?;
+        let station = self.collections[i].stations.remove(index);
+        self.save()?;
+        Ok(station)
+    }
+
+    /// Removes the stations of the collection `keep` returns `false` for and returns how many
+    /// were removed.
+    pub fn retain_stations(
+        &mut self,
+        name: &str,
+        keep: impl FnMut(&RadioStation) -> bool,
+    ) -> Result<usize, Error> {
+        let i = self.position(name)?;
+        let stations = &mut self.collections[i].stations;
+        let before = stations.len();
+        stations.retain(keep);
+        let removed = before - stations.len();
+        if removed > 0 {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// Calls `update` on every station of the collection `name`, which returns whether it
+    /// changed the station, saves if any changed and returns how many did.
+    pub fn update_collection(
+        &mut self,
+        name: &str,
+        mut update: impl FnMut(&mut RadioStation) -> bool,
+    ) -> Result<usize, Error> {
+        let i = self.position(name)?;
+        let mut changed = 0;
+        for station in &mut self.collections[i].stations {
+            if update(station) {
+                changed += 1;
+            }
+        }
+        if changed > 0 {
+            self.save()?;
+        }
+        Ok(changed)
+    }
+
+    /// Moves the station at `index` `offset` places within its collection and returns its new
+    /// index.
+    pub fn move_station(
+        &mut self,
+        name: &str,
+        index: usize,
+        offset: isize,
+    ) -> Result<usize, Error> {
+        let i = self.position(name)?;
+        self.station_index(i, index)?;
+        let stations = &mut self.collections[i].stations;
+        let to = index.saturating_add_signed(offset).min(stations.len() - 1);
+        if to != index {
+            let station = stations.remove(index);
+            stations.insert(to, station);
+            self.save()?;
+        }
+        Ok(to)
+    }
+
+    /// Moves the station at `index` of collection `from` to the end of collection `to`, which is
+    /// created if it does not exist.
+    pub fn move_station_to(&mut self, from: &str, index: usize, to: &str) -> Result<(), Error> {
+        let i = self.position(from)?;
+        self.station_index(i, index)?;
+        if from == to.trim() {
+            return Ok(());
+        }
+        if self.get(to.trim()).is_none() {
+            self.new_name(to)?;
+        }
+        let station = self.collections[i].stations.remove(index);
+        self.add_station(to.trim(), station)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn station(name: &str) -> RadioStation {
+        RadioStation::custom(format!("http://example.com/{name}"), name.to_string())
+    }
+
+    fn names(collections: &Collections) -> Vec<&str> {
+        collections
+            .collections()
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect()
+    }
+
+    fn station_names<'a>(collections: &'a Collections, name: &str) -> Vec<&'a str> {
+        collections
+            .get(name)
+            .unwrap()
+            .stations
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_create_rename_delete() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(Collections::FILE_NAME);
+        let mut collections = Collections::load(&path)?;
+        collections.create("Jazz")?;
+        collections.create(" Rock ")?;
+        assert!(collections.create("Jazz").is_err());
+        assert!(collections.create("  ").is_err());
+
+        collections.rename("Rock", "Metal")?;
+        assert!(collections.rename("Metal", "Jazz").is_err());
+        assert!(collections.rename("missing", "x").is_err());
+        assert_eq!(names(&Collections::load(&path)?), vec!["Jazz", "Metal"]);
+
+        collections.move_collection("Metal", -1)?;
+        assert_eq!(names(&collections), vec!["Metal", "Jazz"]);
+
+        collections.delete("Metal")?;
+        assert_eq!(names(&Collections::load(&path)?), vec!["Jazz"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stations() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("collections-stations.json");
+        let mut collections = Collections::load(&path)?;
+        collections.add_station("Jazz", station("a"))?;
+        collections.add_station("Jazz", station("b"))?;
+        collections.add_station("Jazz", station("c"))?;
+
+        assert_eq!(collections.move_station("Jazz", 0, 5)?, 2);
+        assert_eq!(station_names(&collections, "Jazz"), vec!["b", "c", "a"]);
+
+        collections.move_station_to("Jazz", 1, "Late")?;
+        assert_eq!(station_names(&collections, "Jazz"), vec!["b", "a"]);
+        assert_eq!(station_names(&collections, "Late"), vec!["c"]);
+        assert!(collections.move_station_to("Jazz", 9, "Late").is_err());
+
+        assert_eq!(collections.remove_station("Jazz", 0)?.name, "b");
+        let reloaded = Collections::load(&path)?;
+        assert_eq!(station_names(&reloaded, "Jazz"), vec!["a"]);
+        assert_eq!(station_names(&reloaded, "Late"), vec!["c"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_collection() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("collections-update.json");
+        let mut collections = Collections::load(&path)?;
+        collections.add_station("Jazz", station("a"))?;
+        collections.add_station("Jazz", station("b"))?;
+        collections.add_station("Late", station("a"))?;
+        let mark = |station: &mut RadioStation| {
+            let changed = station.name == "a" && station.broken.is_none();
+            if changed {
+                station.broken = Some("gone".to_string());
+            }
+            changed
+        };
+
+        assert_eq!(collections.update_collection("Jazz", mark)?, 1);
+        assert_eq!(collections.update_collection("Jazz", mark)?, 0);
+        assert!(collections.update_collection("Missing", mark).is_err());
+        let reloaded = Collections::load(&path)?;
+        let broken = |name: &str| {
+            reloaded.get(name).map(|collection| {
+                collection
+                    .stations
+                    .iter()
+                    .filter(|station| station.broken.is_some())
+                    .count()
+            })
+        };
+        assert_eq!(broken("Jazz"), Some(1));
+        assert_eq!(broken("Late"), Some(0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("collections-version.json");
+        std::fs::write(&path, r#"{"version": 99, "collections": []}"#)?;
+        assert!(matches!(
+            Collections::load(&path),
+            Err(Error::UnsupportedVersion { version: 99, .. })
+        ));
+        Ok(())
+    }
+}
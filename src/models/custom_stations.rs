@@ -0,0 +1,371 @@
+//! Stations defined by the user in `stations.toml`, or `stations.json5`, in the config directory:
+//!
+//! ```toml
+//! [[stations]]
+//! name = "Home stream"
+//! url = "https://radio.example.com/live"
+//! codec = "MP3"
+//! tags = ["private", "jazz"]
+//! headers = { Authorization = "Bearer secret", User-Agent = "voxide" }
+//! ```
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use reqwest::header::{HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+
+use super::{RadioStation, SearchParam};
+use crate::{errors::Error, utils::write_atomic};
+
+/// A station as written in the stations file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CustomStation {
+    name: String,
+    url: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    codec: String,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    bitrate: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    homepage: String,
+    /// Sent with every request for the stream.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    headers: BTreeMap<String, String>,
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StationsFile {
+    #[serde(default)]
+    stations: Vec<CustomStation>,
+}
+
+impl CustomStation {
+    /// The problems with the station, described for the user.
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.name.trim().is_empty() {
+            problems.push("the name is empty".to_string());
+        }
+        match reqwest::Url::parse(&self.url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => (),
+            Ok(url) => problems.push(format!("{} urls are not supported", url.scheme())),
+            Err(e) => problems.push(format!("the url {:?} is invalid: {e}", self.url)),
+        }
+        for (name, value) in &self.headers {
+            if HeaderName::from_bytes(name.as_bytes()).is_err() {
+                problems.push(format!("{name:?} is not a valid header name"));
+            } else if HeaderValue::from_str(value).is_err() {
+                problems.push(format!("the value of header {name} is invalid"));
+            }
+        }
+        problems
+    }
+
+    fn from_station(station: &RadioStation) -> Self {
+        Self {
+            name: station.name.trim().to_string(),
+            url: station.url.clone(),
+            codec: station.codec.clone(),
+            bitrate: station.bitrate,
+            tags: station.tags.clone(),
+            homepage: station.homepage.clone(),
+            headers: station.headers.clone(),
+        }
+    }
+
+    fn into_station(self) -> RadioStation {
+        RadioStation {
+            codec: self.codec,
+            bitrate: self.bitrate,
+            tags: self.tags,
+            homepage: self.homepage,
+            headers: self.headers,
+            ..RadioStation::custom(self.url.trim(), self.name.trim())
+        }
+    }
+}
+
+/// The stations of the stations file, reloaded whenever the file changes.
+#[derive(Debug, Default)]
+pub struct CustomStations {
+    stations: Vec<RadioStation>,
+    dir: PathBuf,
+    /// The file loaded and when it was modified, `None` if there was none.
+    loaded: Option<(PathBuf, SystemTime)>,
+}
+
+impl CustomStations {
+    /// The names of the stations file, the first one found is used.
+    pub const FILE_NAMES: [&'static str; 2] = ["stations.toml", "stations.json5"];
+
+    /// Watches for a stations file in `dir`. Nothing is read until
+    /// [`CustomStations::reload_if_changed`].
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            ..Default::default()
+        }
+    }
+
+    pub fn stations(&self) -> &[RadioStation] {
+        &self.stations
+    }
+
+    pub fn get(&self, stationuuid: &str) -> Option<&RadioStation> {
+        self.stations.iter().find(|s| s.stationuuid == stationuuid)
+    }
+
+    /// Reads the stations file again if it was created, changed or removed since it was last
+    /// read. Returns `true` if the stations changed.
+    ///
+    /// If the file is invalid the stations read before are kept, and the file is only read
+    /// again once it changes.
+    pub fn reload_if_changed(&mut self) -> Result<bool, Error> {
+        let current = Self::FILE_NAMES.iter().find_map(|name| {
+            let path = self.dir.join(name);
+            let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            Some((path, modified))
+        });
+        if current == self.loaded {
+            return Ok(false);
+        }
+        self.loaded = current.clone();
+        let Some((path, _)) = current else {
+            self.stations.clear();
+            return Ok(true);
+        };
+        let contents = std::fs::read_to_string(&path)?;
+        self.stations = Self::parse(&path, &contents)?;
+        tracing::info!(path = %path.display(), count = self.stations.len(), "loaded custom stations");
+        Ok(true)
+    }
+
+    /// Appends the station to `stations.toml`, which is created if needed. The change is picked
+    /// up by the next [`CustomStations::reload_if_changed`].
+    pub fn append(&self, station: &RadioStation) -> Result<PathBuf, Error> {
+        let path = self.dir.join(Self::FILE_NAMES[0]);
+        if !path.exists() && self.dir.join(Self::FILE_NAMES[1]).exists() {
+            return Err(Error::CustomStations(format!(
+                "{} is in use, add the station to it by hand",
+                Self::FILE_NAMES[1]
+            )));
+        }
+        if self.get(&station.stationuuid).is_some() {
+            return Err(Error::CustomStations(format!(
+                "{} is a custom station already",
+                station.url
+            )));
+        }
+        let entry = StationsFile {
+            stations: vec![CustomStation::from_station(station)],
+        };
+        let entry = toml::to_string(&entry).map_err(|e| Error::CustomStations(e.to_string()))?;
+        let mut contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        if !contents.is_empty() {
+            contents.push_str(if contents.ends_with('\n') {
+                "\n"
+            } else {
+                "\n\n"
+            });
+        }
+        contents.push_str(&entry);
+        std::fs::create_dir_all(&self.dir)?;
+        write_atomic(&path, contents.as_bytes())?;
+        Ok(path)
+    }
+
+    /// Parses and validates a stations file, whose format is taken from its extension.
+    pub fn parse(path: &Path, contents: &str) -> Result<Vec<RadioStation>, Error> {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let error = |message: String| Error::CustomStations(format!("{file_name}: {message}"));
+
+        let file: StationsFile = if path.extension().is_some_and(|e| e == "toml") {
+            toml::from_str(contents).map_err(|e| {
+                let line = e
+                    .span()
+                    .map(|span| contents[..span.start].matches('\n').count() + 1);
+                match line {
+                    Some(line) => error(format!("line {line}: {}", e.message())),
+                    None => error(e.message().to_string()),
+                }
+            })?
+        } else {
+            json5::from_str(contents).map_err(|e| match e {
+                json5::Error::Message {
+                    msg,
+                    location: Some(location),
+                } => error(format!("line {}: {msg}", location.line)),
+                json5::Error::Message { msg, .. } => error(msg),
+            })?
+        };
+
+        let mut problems = Vec::new();
+        let mut urls = HashSet::new();
+        for (i, station) in file.stations.iter().enumerate() {
+            let label = format!("station {} ({})", i + 1, station.name.trim());
+            problems.extend(
+                station
+                    .problems()
+                    .into_iter()
+                    .map(|p| format!("{label}: {p}")),
+            );
+            if !urls.insert(station.url.trim()) {
+                problems.push(format!("{label}: the url is listed twice"));
+            }
+        }
+        if !problems.is_empty() {
+            return Err(error(problems.join("; ")));
+        }
+        Ok(file
+            .stations
+            .into_iter()
+            .map(CustomStation::into_station)
+            .collect())
+    }
+
+    /// The stations matching a directory search. Parameters the stations have no data for, like
+    /// the country, match none of them, ordering and limits are ignored.
+    pub fn matching(&self, params: &[SearchParam]) -> Vec<RadioStation> {
+        self.stations
+            .iter()
+            .filter(|station| station.matches(params))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const TOML: &str = r#"
+[[stations]]
+name = "Home stream"
+url = "https://radio.example.com/live"
+codec = "MP3"
+tags = ["private", "jazz"]
+headers = { Authorization = "Bearer secret" }
+
+[[stations]]
+name = "Night"
+url = "http://night.example.com/stream"
+"#;
+
+    #[test]
+    fn test_parse_toml() -> Result<(), Error> {
+        let stations = CustomStations::parse(Path::new("stations.toml"), TOML)?;
+        assert_eq!(stations.len(), 2);
+        assert_eq!(
+            stations[0].stationuuid,
+            "custom:https://radio.example.com/live"
+        );
+        assert_eq!(stations[0].headers["Authorization"], "Bearer secret");
+        assert!(stations[1].is_custom());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_json5() -> Result<(), Error> {
+        let json5 = "{ stations: [{ name: 'A', url: 'http://a.example', bitrate: 128 }] }";
+        let stations = CustomStations::parse(Path::new("stations.json5"), json5)?;
+        assert_eq!(stations[0].bitrate, 128);
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors_name_the_problem() {
+        let error = |contents: &str| {
+            CustomStations::parse(Path::new("stations.toml"), contents)
+                .unwrap_err()
+                .to_string()
+        };
+        let invalid = "[[stations]]\nname = \"A\"\nurl = \"ftp://a\"\n\
+            headers = { \"Bad Header\" = \"x\" }\n\
+            [[stations]]\nname = \"B\"\nurl = \"ftp://a\"\n";
+        assert_eq!(
+            error(invalid),
+            "CustomStationsError: stations.toml: station 1 (A): ftp urls are not supported; \
+             station 1 (A): \"Bad Header\" is not a valid header name; \
+             station 2 (B): ftp urls are not supported; station 2 (B): the url is listed twice"
+        );
+        assert!(error("[[stations]]\nname = \"A\"\n").contains("line 1: missing field `url`"));
+        assert!(
+            error("[[stations]]\nname = \"A\"\nurl = \"http://a\"\ncolor = 1\n")
+                .contains("line 4: unknown field `color`")
+        );
+    }
+
+    #[test]
+    fn test_matching() -> Result<(), Error> {
+        let custom = CustomStations {
+            stations: CustomStations::parse(Path::new("stations.toml"), TOML)?,
+            ..Default::default()
+        };
+        let names = |params: &[SearchParam]| {
+            custom
+                .matching(params)
+                .into_iter()
+                .map(|s| s.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&[]), vec!["Home stream", "Night"]);
+        assert_eq!(
+            names(&[SearchParam::Name("home".into())]),
+            vec!["Home stream"]
+        );
+        assert_eq!(
+            names(&[SearchParam::Tags(vec!["Jazz".into()])]),
+            vec!["Home stream"]
+        );
+        assert!(names(&[SearchParam::Country("Austria".into())]).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reload_if_changed() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let mut custom = CustomStations::new(dir.path());
+        assert!(!custom.reload_if_changed()?);
+
+        let path = dir.path().join(CustomStations::FILE_NAMES[0]);
+        std::fs::write(&path, TOML)?;
+        assert!(custom.reload_if_changed()?);
+        assert_eq!(custom.stations().len(), 2);
+        assert!(!custom.reload_if_changed()?);
+
+        let station = RadioStation {
+            codec: "AAC".to_string(),
+            ..RadioStation::custom("http://new.example/live", "New")
+        };
+        custom.append(&station)?;
+        assert!(custom.append(&custom.stations()[0].clone()).is_err());
+        custom.loaded = None;
+        assert!(custom.reload_if_changed()?);
+        assert_eq!(custom.get(&station.stationuuid), Some(&station));
+
+        std::fs::remove_file(&path)?;
+        assert!(custom.reload_if_changed()?);
+        assert!(custom.stations().is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,218 @@
+//! Tidying up search results: the directory often lists the same station several times, on
+//! other servers or at other bitrates, and lists stations whose streams are down.
+use std::{cmp::Reverse, collections::HashMap};
+
+use ratatui::text::{Line, Span};
+
+use super::{radio_station::label, RadioStation};
+
+/// Words in station names that tell the stream apart rather than the station.
+const STREAM_WORDS: [&str; 12] = [
+    "mp3", "aac", "aacplus", "ogg", "opus", "flac", "hd", "hq", "lq", "low", "high", "kbps",
+];
+
+/// Whether the directory's last check of the stream failed, or a check marked it broken.
+pub fn is_failing(station: &RadioStation) -> bool {
+    (station.is_directory() && !station.lastcheckok) || station.broken.is_some()
+}
+
+/// The name of a station without case, punctuation and words about the stream such as its
+/// codec or bitrate, e.g. `Radio X (128k MP3)` becomes `radio x`.
+pub fn normalized_name(name: &str) -> String {
+    let name = name.to_lowercase();
+    let words: Vec<&str> = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    let is_number = |word: &str| word.chars().all(|c| c.is_ascii_digit());
+    words
+        .iter()
+        .enumerate()
+        .filter(|(i, word)| {
+            let bitrate = word
+                .strip_suffix('k')
+                .is_some_and(|n| !n.is_empty() && is_number(n))
+                || (is_number(word) && matches!(words.get(i + 1), Some(&("k" | "kbps"))))
+                || (**word == "k" && *i > 0 && is_number(words[i - 1]));
+            !bitrate && !STREAM_WORDS.contains(word)
+        })
+        .map(|(_, word)| *word)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The host of a url, lowercase and without `www.`.
+fn host(url: &str) -> Option<String> {
+    let host = reqwest::Url::parse(url.trim())
+        .ok()?
+        .host_str()?
+        .to_lowercase();
+    Some(host.trim_start_matches("www.").to_string())
+}
+
+/// A homepage without its scheme, `www.` and trailing slashes.
+fn normalized_homepage(homepage: &str) -> Option<String> {
+    let homepage = homepage.trim().to_lowercase();
+    let homepage = homepage
+        .split_once("://")
+        .map_or(homepage.as_str(), |(_, rest)| rest)
+        .trim_start_matches("www.")
+        .trim_end_matches('/');
+    (!homepage.is_empty()).then(|| homepage.to_string())
+}
+
+/// A station and the other listings of it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StationGroup {
+    /// The most reliable listing, then the one with the highest bitrate.
+    pub best: RadioStation,
+    pub alternates: Vec<RadioStation>,
+}
+
+/// Groups the listings of the same station: ones with the same normalized name that share
+/// their homepage or the host of their stream. The groups are in the order of their first
+/// listing.
+pub fn group_duplicates(
+    stations: Vec<RadioStation>,
+    is_unreliable: impl Fn(&RadioStation) -> bool,
+) -> Vec<StationGroup> {
+    // Union-find over the stations, joined by the keys they share
+    let mut parent: Vec<usize> = (0..stations.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    let mut first_with_key: HashMap<String, usize> = HashMap::new();
+    for (i, station) in stations.iter().enumerate() {
+        let name = normalized_name(&station.name);
+        if name.is_empty() {
+            continue;
+        }
+        let keys = [
+            normalized_homepage(&station.homepage).map(|homepage| format!("{name}|{homepage}")),
+            host(station.stream_url()).map(|host| format!("{name}|{host}")),
+        ];
+        for key in keys.into_iter().flatten() {
+            match first_with_key.get(&key) {
+                Some(&j) => {
+                    let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                    parent[a.max(b)] = a.min(b);
+                }
+                None => {
+                    first_with_key.insert(key, i);
+                }
+            }
+        }
+    }
+
+    let mut members: Vec<Vec<RadioStation>> = vec![Vec::new(); stations.len()];
+    for (i, station) in stations.into_iter().enumerate() {
+        members[root(&mut parent, i)].push(station);
+    }
+    members
+        .into_iter()
+        .filter(|group| !group.is_empty())
+        .map(|mut group| {
+            group.sort_by_key(|station| {
+                (
+                    is_unreliable(station),
+                    Reverse(station.bitrate),
+                    Reverse(station.votes),
+                )
+            });
+            let best = group.remove(0);
+            StationGroup {
+                best,
+                alternates: group,
+            }
+        })
+        .collect()
+}
+
+/// The other listings of a station, for its details.
+pub fn alternates_details(alternates: &[RadioStation]) -> Vec<Line<'static>> {
+    alternates
+        .iter()
+        .enumerate()
+        .map(|(i, station)| {
+            let title = if i == 0 { "Also at" } else { "" };
+            let mut text = format!("{}k {}", station.bitrate, station.codec);
+            if let Some(host) = host(station.stream_url()) {
+                text.push_str(&format!("  {host}"));
+            }
+            if is_failing(station) {
+                text.push_str("  (failing)");
+            }
+            Line::from(vec![label(title), Span::raw(text)])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn station(uuid: &str, name: &str, url: &str, homepage: &str, bitrate: u32) -> RadioStation {
+        RadioStation {
+            homepage: homepage.to_string(),
+            bitrate,
+            lastcheckok: true,
+            ..RadioStation::new(url.to_string(), uuid.to_string(), name.to_string())
+        }
+    }
+
+    #[test]
+    fn test_normalized_name() {
+        assert_eq!(normalized_name("Radio X (128k MP3)"), "radio x");
+        assert_eq!(normalized_name("RADIO X - HQ 320 kbps"), "radio x");
+        assert_eq!(normalized_name("Radio 1"), "radio 1");
+        assert_eq!(normalized_name("Radio 1 (96 k)"), "radio 1");
+        assert_eq!(normalized_name("K-Love"), "k love");
+    }
+
+    #[test]
+    fn test_group_duplicates() {
+        let stations = vec![
+            station(
+                "a",
+                "Radio X",
+                "http://x1.example/low",
+                "https://radiox.example/",
+                64,
+            ),
+            station("b", "Jazz", "http://jazz.example/", "", 128),
+            station(
+                "c",
+                "Radio X (320k)",
+                "http://x2.example/",
+                "http://www.radiox.example",
+                320,
+            ),
+            // Same name and host as `c`, but no homepage
+            station("d", "radio x", "http://x2.example/aac", "", 128),
+            // Same name only, another station
+            station("e", "Radio X", "http://other.example/", "", 128),
+            RadioStation {
+                lastcheckok: false,
+                ..station("f", "Jazz HD", "http://jazz.example/hd", "", 320)
+            },
+        ];
+        let groups = group_duplicates(stations, |s| !s.lastcheckok);
+        let uuids: Vec<(&str, Vec<&str>)> = groups
+            .iter()
+            .map(|group| {
+                let alternates = group.alternates.iter().map(|s| s.stationuuid.as_str());
+                (group.best.stationuuid.as_str(), alternates.collect())
+            })
+            .collect();
+        assert_eq!(
+            uuids,
+            vec![("c", vec!["d", "a"]), ("b", vec!["f"]), ("e", vec![])]
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::RadioStation;
+use crate::{errors::Error, utils::write_atomic};
+
+/// The user's favorite stations, in the order the user arranged them.
+///
+/// Stations are identified by their `stationuuid`. The full station is stored so favorites can
+/// be listed and played without asking the directory.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Favorites {
+    /// The version of the file format, see [`Favorites::VERSION`].
+    version: u32,
+    stations: Vec<RadioStation>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Default for Favorites {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            stations: Vec::new(),
+            path: PathBuf::new(),
+        }
+    }
+}
+
+impl Favorites {
+    pub const FILE_NAME: &'static str = "favorites.json";
+    /// The newest file format this build reads.
+    pub const VERSION: u32 = 1;
+
+    /// Loads the favorites from `path`, starting with none if the file does not exist yet.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut favorites = match std::fs::read(path) {
+            Ok(data) => serde_json::from_slice::<Self>(&data)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e.into()),
+        };
+        if favorites.version > Self::VERSION {
+            return Err(Error::UnsupportedVersion {
+                file: path.display().to_string(),
+                version: favorites.version,
+                supported: Self::VERSION,
+            });
+        }
+        favorites.version = Self::VERSION;
+        favorites.path = path.to_path_buf();
+        Ok(favorites)
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        write_atomic(&self.path, &serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn stations(&self) -> &[RadioStation] {
+        &self.stations
+    }
+
+    /// Calls `update` on every favorite, which returns whether it changed the station, and saves
+    /// if any changed.
+    pub fn update_stations(
+        &mut self,
+        mut update: impl FnMut(&mut RadioStation) -> bool,
+    ) -> Result<(), Error> {
+        let mut changed = false;
+        for station in &mut self.stations {
+            changed |= update(station);
+        }
+        if changed {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    pub fn contains(&self, stationuuid: &str) -> bool {
+        self.position(stationuuid).is_some()
+    }
+
+    fn position(&self, stationuuid: &str) -> Option<usize> {
+        self.stations
+            .iter()
+            .position(|s| s.stationuuid == stationuuid)
+    }
+
+    /// Adds the station to the end of the favorites, or removes it if it already is one.
+    ///
+    /// Returns `true` if the station is a favorite now.
+    pub fn toggle(&mut self, station: &RadioStation) -> Result<bool, Error> {
+        let added = match self.position(&station.stationuuid) {
+            Some(i) => {
+                self.stations.remove(i);
+                false
+            }
+            None => {
+                self.stations.push(station.clone());
+                true
+            }
+        };
+        self.save()?;
+        Ok(added)
+    }
+
+    /// Adds the stations that are not favorites yet to the end of the favorites.
+    ///
+    /// Returns how many were added.
+    pub fn extend(
+        &mut self,
+        stations: impl IntoIterator<Item = RadioStation>,
+    ) -> Result<usize, Error> {
+        let before = self.stations.len();
+        for station in stations {
+            if !self.contains(&station.stationuuid) {
+                self.stations.push(station);
+            }
+        }
+        self.save()?;
+        Ok(self.stations.len() - before)
+    }
+
+    /// Removes the favorites `keep` returns `false` for and returns how many were removed.
+    pub fn retain(&mut self, keep: impl FnMut(&RadioStation) -> bool) -> Result<usize, Error> {
+        let before = self.stations.len();
+        self.stations.retain(keep);
+        let removed = before - self.stations.len();
+        if removed > 0 {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// Moves the station `offset` places towards the end, negative offsets move it towards the
+    /// start. Returns `false` if the station is not a favorite or already at the edge.
+    pub fn move_by(&mut self, stationuuid: &str, offset: isize) -> Result<bool, Error> {
+        let Some(from) = self.position(stationuuid) else {
+            return Ok(false);
+        };
+        let to = from
+            .saturating_add_signed(offset)
+            .min(self.stations.len() - 1);
+        if to == from {
+            return Ok(false);
+        }
+        let station = self.stations.remove(from);
+        self.stations.insert(to, station);
+        self.save()?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn station(uuid: &str) -> RadioStation {
+        RadioStation::new("http://example.com/stream", uuid, uuid)
+    }
+
+    fn uuids(favorites: &Favorites) -> Vec<&str> {
+        favorites
+            .stations()
+            .iter()
+            .map(|s| s.stationuuid.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_toggle_and_persist() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(Favorites::FILE_NAME);
+        let mut favorites = Favorites::load(&path)?;
+
+        assert!(favorites.toggle(&station("a"))?);
+        assert!(favorites.toggle(&station("b"))?);
+        assert!(favorites.contains("a"));
+
+        let mut reloaded = Favorites::load(&path)?;
+        assert_eq!(uuids(&reloaded), vec!["a", "b"]);
+
+        assert!(!reloaded.toggle(&station("a"))?);
+        assert!(!reloaded.contains("a"));
+        assert_eq!(uuids(&Favorites::load(&path)?), vec!["b"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("favorites-version.json");
+        std::fs::write(&path, r#"{"version": 99, "stations": []}"#)?;
+        assert!(matches!(
+            Favorites::load(&path),
+            Err(Error::UnsupportedVersion { version: 99, .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_by() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("favorites-move.json");
+        let mut favorites = Favorites::load(&path)?;
+        for uuid in ["a", "b", "c"] {
+            favorites.toggle(&station(uuid))?;
+        }
+
+        assert!(favorites.move_by("a", 1)?);
+        assert_eq!(uuids(&favorites), vec!["b", "a", "c"]);
+        assert!(favorites.move_by("c", -5)?);
+        assert_eq!(uuids(&favorites), vec!["c", "b", "a"]);
+        assert!(!favorites.move_by("a", 1)?);
+        assert!(!favorites.move_by("missing", 1)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_extend_skips_favorites() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("favorites-extend.json");
+        let mut favorites = Favorites::load(&path)?;
+        favorites.toggle(&station("a"))?;
+
+        assert_eq!(favorites.extend([station("a"), station("b")])?, 1);
+        assert_eq!(uuids(&Favorites::load(&path)?), vec!["a", "b"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,221 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use strum::Display;
+
+use super::RadioStation;
+use crate::{
+    errors::Error,
+    utils::{unix_now, write_atomic},
+};
+
+/// Why playback of a station ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum EndReason {
+    /// The user stopped playback, or quit.
+    Stopped,
+    /// The stream could not be played.
+    Failed,
+    /// Another station was started.
+    Switched,
+}
+
+/// One listening session of a station.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// The station as it was when it was played, or when the saved stations were last
+    /// refreshed.
+    pub station: RadioStation,
+    /// Seconds since the unix epoch.
+    pub started_at: u64,
+    pub duration_secs: u64,
+    pub end_reason: EndReason,
+}
+
+/// How much history is kept. Zero means unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Retention {
+    pub max_entries: usize,
+    pub max_age_days: u64,
+}
+
+/// The stations played, newest first.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct History {
+    /// The version of the file format, see [`History::VERSION`].
+    version: u32,
+    entries: Vec<HistoryEntry>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            entries: Vec::new(),
+            path: PathBuf::new(),
+        }
+    }
+}
+
+impl History {
+    pub const FILE_NAME: &'static str = "history.json";
+    /// The newest file format this build reads.
+    pub const VERSION: u32 = 1;
+
+    /// Loads the history from `path`, starting an empty one if the file does not exist yet.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut history = match std::fs::read(path) {
+            Ok(data) => serde_json::from_slice::<Self>(&data)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e.into()),
+        };
+        if history.version > Self::VERSION {
+            return Err(Error::UnsupportedVersion {
+                file: path.display().to_string(),
+                version: history.version,
+                supported: Self::VERSION,
+            });
+        }
+        history.version = Self::VERSION;
+        history.path = path.to_path_buf();
+        Ok(history)
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        write_atomic(&self.path, &serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The entries, newest first.
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    /// Calls `update` on every station of the history, which returns whether it changed the
+    /// station, and saves if any changed.
+    pub fn update_stations(
+        &mut self,
+        mut update: impl FnMut(&mut RadioStation) -> bool,
+    ) -> Result<(), Error> {
+        let mut changed = false;
+        for station in self.entries.iter_mut().map(|entry| &mut entry.station) {
+            changed |= update(station);
+        }
+        if changed {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Adds `entry` as the newest entry and drops whatever `retention` no longer allows.
+    pub fn record(&mut self, entry: HistoryEntry, retention: Retention) -> Result<(), Error> {
+        self.entries.insert(0, entry);
+        self.prune(retention, unix_now());
+        self.save()
+    }
+
+    fn prune(&mut self, retention: Retention, now: u64) {
+        if retention.max_age_days > 0 {
+            let max_age = retention.max_age_days * 24 * 60 * 60;
+            self.entries
+                .retain(|entry| now.saturating_sub(entry.started_at) <= max_age);
+        }
+        if retention.max_entries > 0 {
+            self.entries.truncate(retention.max_entries);
+        }
+    }
+
+    pub fn clear(&mut self) -> Result<(), Error> {
+        self.entries.clear();
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn entry(uuid: &str, started_at: u64) -> HistoryEntry {
+        HistoryEntry {
+            station: RadioStation::new("http://example.com/stream", uuid, uuid),
+            started_at,
+            duration_secs: 60,
+            end_reason: EndReason::Stopped,
+        }
+    }
+
+    fn uuids(history: &History) -> Vec<&str> {
+        history
+            .entries()
+            .iter()
+            .map(|e| e.station.stationuuid.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_prune() {
+        let day = 24 * 60 * 60;
+        let mut history = History {
+            entries: vec![entry("c", 10 * day), entry("b", 9 * day), entry("a", day)],
+            ..Default::default()
+        };
+
+        history.prune(
+            Retention {
+                max_entries: 0,
+                max_age_days: 5,
+            },
+            10 * day,
+        );
+        assert_eq!(uuids(&history), vec!["c", "b"]);
+
+        history.prune(
+            Retention {
+                max_entries: 1,
+                max_age_days: 0,
+            },
+            10 * day,
+        );
+        assert_eq!(uuids(&history), vec!["c"]);
+    }
+
+    #[test]
+    fn test_record_and_clear() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(History::FILE_NAME);
+        let retention = Retention {
+            max_entries: 10,
+            max_age_days: 0,
+        };
+        let mut history = History::load(&path)?;
+        history.record(entry("a", 1), retention)?;
+        history.record(entry("b", 2), retention)?;
+
+        let mut reloaded = History::load(&path)?;
+        assert_eq!(uuids(&reloaded), vec!["b", "a"]);
+        assert_eq!(reloaded.entries()[0].end_reason, EndReason::Stopped);
+
+        reloaded.clear()?;
+        assert!(History::load(&path)?.entries().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("history-version.json");
+        std::fs::write(&path, r#"{"version": 99, "entries": []}"#)?;
+        assert!(matches!(
+            History::load(&path),
+            Err(Error::UnsupportedVersion { version: 99, .. })
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+//! Demultiplexing of the ICY (SHOUTcast) metadata interleaved with the audio of many streams.
+//!
+//! When a stream is requested with `Icy-MetaData: 1`, servers that support it answer with an
+//! `icy-metaint` header. After every `icy-metaint` bytes of audio follows one length byte `n`
+//! and `16 * n` bytes of metadata such as `StreamTitle='Artist - Title';`.
+
+/// The request header asking the server to interleave metadata.
+pub const ICY_METADATA_HEADER: &str = "Icy-MetaData";
+/// The response header holding the number of audio bytes between metadata blocks.
+pub const ICY_METAINT_HEADER: &str = "icy-metaint";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum State {
+    /// This many audio bytes remain before the next metadata block.
+    Audio(usize),
+    /// The next byte is the length of a metadata block.
+    Length,
+    /// This many metadata bytes remain.
+    Metadata(usize),
+}
+
+/// Splits a stream into its audio and its metadata blocks.
+#[derive(Debug)]
+pub struct IcyReader {
+    metaint: usize,
+    state: State,
+    metadata: Vec<u8>,
+}
+
+impl IcyReader {
+    pub fn new(metaint: usize) -> Self {
+        Self {
+            metaint,
+            state: State::Audio(metaint),
+            metadata: Vec::new(),
+        }
+    }
+
+    /// Appends the audio of `chunk` to `audio` and returns the stream title of the last
+    /// metadata block completed by this chunk, if any. Chunks may split blocks anywhere.
+    pub fn push(&mut self, mut chunk: &[u8], audio: &mut Vec<u8>) -> Option<String> {
+        let mut title = None;
+        while !chunk.is_empty() {
+            match self.state {
+                State::Audio(remaining) => {
+                    let n = remaining.min(chunk.len());
+                    audio.extend_from_slice(&chunk[..n]);
+                    chunk = &chunk[n..];
+                    self.state = if n == remaining {
+                        State::Length
+                    } else {
+                        State::Audio(remaining - n)
+                    };
+                }
+                State::Length => {
+                    let length = chunk[0] as usize * 16;
+                    chunk = &chunk[1..];
+                    self.state = if length == 0 {
+                        State::Audio(self.metaint)
+                    } else {
+                        State::Metadata(length)
+                    };
+                }
+                State::Metadata(remaining) => {
+                    let n = remaining.min(chunk.len());
+                    self.metadata.extend_from_slice(&chunk[..n]);
+                    chunk = &chunk[n..];
+                    if n == remaining {
+                        let metadata = String::from_utf8_lossy(&self.metadata).into_owned();
+                        title = stream_title(&metadata).or(title);
+                        self.metadata.clear();
+                        self.state = State::Audio(self.metaint);
+                    } else {
+                        self.state = State::Metadata(remaining - n);
+                    }
+                }
+            }
+        }
+        title
+    }
+}
+
+/// Extracts the `StreamTitle` of a metadata block, `None` if it is missing or empty.
+pub fn stream_title(metadata: &str) -> Option<String> {
+    let start = metadata.find("StreamTitle='")? + "StreamTitle='".len();
+    let rest = &metadata[start..];
+    // Titles may contain `'`, the value ends at the `';` before the next field or the padding.
+    let end = rest
+        .find("';")
+        .or_else(|| rest.rfind('\''))
+        .unwrap_or(rest.len());
+    let title = rest[..end].trim_end_matches('\0').trim();
+    (!title.is_empty()).then(|| title.to_string())
+}
+
+/// Splits a stream title into artist and title at the first ` - `.
+pub fn split_title(raw: &str) -> (Option<String>, String) {
+    match raw.split_once(" - ") {
+        Some((artist, title)) if !artist.trim().is_empty() && !title.trim().is_empty() => {
+            (Some(artist.trim().to_string()), title.trim().to_string())
+        }
+        _ => (None, raw.trim().to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    /// Builds a stream with `metaint` bytes of audio, one metadata block, and more audio.
+    fn stream(metaint: usize, metadata: &str) -> Vec<u8> {
+        let mut block = metadata.as_bytes().to_vec();
+        block.resize(block.len().div_ceil(16) * 16, 0);
+        let mut data = vec![1; metaint];
+        data.push((block.len() / 16) as u8);
+        data.extend(block);
+        data.extend(vec![2; metaint]);
+        data.push(0);
+        data.extend(vec![3; 4]);
+        data
+    }
+
+    #[test]
+    fn test_push_whole_stream() {
+        let data = stream(8, "StreamTitle='Artist - Song';StreamUrl='';");
+        let mut reader = IcyReader::new(8);
+        let mut audio = Vec::new();
+        let title = reader.push(&data, &mut audio);
+        assert_eq!(title.as_deref(), Some("Artist - Song"));
+        assert_eq!(audio, [vec![1; 8], vec![2; 8], vec![3; 4]].concat());
+    }
+
+    #[test]
+    fn test_push_byte_by_byte() {
+        let data = stream(5, "StreamTitle='It's a title';");
+        let mut reader = IcyReader::new(5);
+        let mut audio = Vec::new();
+        let titles: Vec<String> = data
+            .iter()
+            .filter_map(|b| reader.push(std::slice::from_ref(b), &mut audio))
+            .collect();
+        assert_eq!(titles, vec!["It's a title"]);
+        assert_eq!(audio.len(), 14);
+    }
+
+    #[test]
+    fn test_stream_title() {
+        assert_eq!(stream_title("StreamTitle='';"), None);
+        assert_eq!(stream_title("StreamUrl='x';"), None);
+        assert_eq!(
+            stream_title("StreamTitle='No terminator\0\0\0").as_deref(),
+            Some("No terminator")
+        );
+    }
+
+    #[test]
+    fn test_split_title() {
+        assert_eq!(
+            split_title("Daft Punk - One More Time"),
+            (Some("Daft Punk".to_string()), "One More Time".to_string())
+        );
+        assert_eq!(
+            split_title("Station jingle"),
+            (None, "Station jingle".to_string())
+        );
+        assert_eq!(split_title(" - Untitled"), (None, "- Untitled".to_string()));
+    }
+}
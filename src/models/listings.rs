@@ -0,0 +1,406 @@
+//! Station listings besides the directory: Icecast `yp.xml` files and Shoutcast style station
+//! lists, fetched now and then and searched locally.
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{stream_probe::codec_name, RadioStation, SearchParam, DEFAULT_SEARCH_LIMIT};
+use crate::{errors::Error, utils::write_atomic};
+
+/// Shoutcast station lists link to playlists relative to this host.
+const SHOUTCAST_TUNEIN_HOST: &str = "http://yp.shoutcast.com";
+
+/// The stations of one listing as last fetched.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Listing {
+    pub url: String,
+    /// Seconds since the unix epoch of the last fetch, successful or not.
+    pub checked_at: u64,
+    pub stations: Vec<RadioStation>,
+}
+
+/// The cached listings, refreshed once they are older than the configured interval.
+///
+/// Persisted as JSON in the data directory.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Listings {
+    /// The version of the file format, see [`Listings::VERSION`].
+    version: u32,
+    listings: Vec<Listing>,
+    /// Whether listings were fetched since the cache was last saved.
+    #[serde(skip)]
+    dirty: bool,
+    /// Whether a save in the background is writing the changes, see [`Listings::begin_save`].
+    #[serde(skip)]
+    saving: bool,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Default for Listings {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            listings: Vec::new(),
+            dirty: false,
+            saving: false,
+            path: PathBuf::new(),
+        }
+    }
+}
+
+impl Drop for Listings {
+    /// Saves the listings no background save wrote yet, e.g. when the program exits.
+    fn drop(&mut self) {
+        if self.dirty {
+            if let Err(e) = self.save() {
+                tracing::warn!(error = ?e, "failed to save the listings");
+            }
+        }
+    }
+}
+
+impl Listings {
+    pub const FILE_NAME: &'static str = "listings.json";
+    /// The newest file format this build reads.
+    pub const VERSION: u32 = 1;
+
+    /// Loads the cache from `path`, starting an empty one if the file does not exist yet.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut listings = match std::fs::read(path) {
+            Ok(data) => serde_json::from_slice::<Self>(&data)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e.into()),
+        };
+        if listings.version > Self::VERSION {
+            return Err(Error::UnsupportedVersion {
+                file: path.display().to_string(),
+                version: listings.version,
+                supported: Self::VERSION,
+            });
+        }
+        listings.version = Self::VERSION;
+        listings.path = path.to_path_buf();
+        Ok(listings)
+    }
+
+    pub fn save(&mut self) -> Result<(), Error> {
+        self.dirty = false;
+        write_atomic(&self.path, &serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+
+    /// Whether the caller should start saving the changes in the background, because there
+    /// are some and no save is writing them yet. It then calls [`Listings::take_changes`] until
+    /// there are none.
+    pub fn begin_save(&mut self) -> bool {
+        if !self.dirty || self.saving {
+            return false;
+        }
+        self.saving = true;
+        true
+    }
+
+    /// The path and contents to write if listings were fetched since the last call, `None`
+    /// once there are no more changes, which ends the save.
+    pub fn take_changes(&mut self) -> Option<Result<(PathBuf, Vec<u8>), Error>> {
+        if !self.dirty {
+            self.saving = false;
+            return None;
+        }
+        self.dirty = false;
+        match serde_json::to_vec(self) {
+            Ok(data) => Some(Ok((self.path.clone(), data))),
+            Err(e) => {
+                self.saving = false;
+                Some(Err(e.into()))
+            }
+        }
+    }
+
+    pub fn get(&self, url: &str) -> Option<&Listing> {
+        self.listings.iter().find(|listing| listing.url == url)
+    }
+
+    /// The urls that were never fetched or not within `max_age` of `now`.
+    pub fn stale<'a>(&self, urls: &'a [String], max_age: Duration, now: u64) -> Vec<&'a str> {
+        urls.iter()
+            .map(String::as_str)
+            .filter(|url| {
+                self.get(url).is_none_or(|listing| {
+                    now.saturating_sub(listing.checked_at) >= max_age.as_secs()
+                })
+            })
+            .collect()
+    }
+
+    /// Replaces the stations of a listing with freshly fetched ones. The cache is saved later,
+    /// see [`Listings::begin_save`].
+    pub fn update(&mut self, url: &str, stations: Vec<RadioStation>, now: u64) {
+        let listing = Listing {
+            url: url.to_string(),
+            checked_at: now,
+            stations,
+        };
+        match self.listings.iter_mut().find(|l| l.url == url) {
+            Some(existing) => *existing = listing,
+            None => self.listings.push(listing),
+        }
+        self.dirty = true;
+    }
+
+    /// Records a failed fetch, keeping the stations fetched before, so that the listing is only
+    /// tried again after the refresh interval.
+    pub fn mark_checked(&mut self, url: &str, now: u64) {
+        match self.listings.iter_mut().find(|l| l.url == url) {
+            Some(existing) => existing.checked_at = now,
+            None => self.listings.push(Listing {
+                url: url.to_string(),
+                checked_at: now,
+                stations: Vec::new(),
+            }),
+        }
+        self.dirty = true;
+    }
+
+    /// The stations of the listings at `urls` matching a directory search, at most as many as
+    /// its limit. Listings no longer configured are not searched.
+    pub fn search(&self, urls: &[String], params: &[SearchParam]) -> Vec<RadioStation> {
+        let limit = params
+            .iter()
+            .find_map(|param| match param {
+                SearchParam::Limit(limit) => Some(*limit),
+                _ => None,
+            })
+            .unwrap_or(DEFAULT_SEARCH_LIMIT);
+        self.listings
+            .iter()
+            .filter(|listing| urls.contains(&listing.url))
+            .flat_map(|listing| &listing.stations)
+            .filter(|station| station.matches(params))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Reads an Icecast `yp.xml` or a Shoutcast station list, told apart by their root element.
+pub fn parse_listing(xml: &str) -> Result<Vec<RadioStation>, Error> {
+    let document = roxmltree::Document::parse(xml.trim_start_matches('\u{feff}'))
+        .map_err(|e| Error::Listing(e.to_string()))?;
+    let root = document.root_element();
+    match root.tag_name().name() {
+        "directory" => Ok(parse_icecast(root)),
+        "stationlist" => Ok(parse_shoutcast(root)),
+        name => Err(Error::Listing(format!("unknown listing format <{name}>"))),
+    }
+}
+
+/// Splits a genre field such as `Classic Rock, Blues` or `jazz/blues` into lowercase tags, like
+/// the directory's tags.
+fn genre_tags(genre: &str) -> Vec<String> {
+    genre
+        .split([',', '/'])
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Icecast servers often list several genres separated by spaces only, e.g. `Jazz Blues`.
+fn icecast_genre_tags(genre: &str) -> Vec<String> {
+    if genre.contains([',', '/']) {
+        genre_tags(genre)
+    } else {
+        genre.split_whitespace().map(str::to_lowercase).collect()
+    }
+}
+
+fn codec(content_type: &str) -> String {
+    let content_type = content_type.trim().to_lowercase();
+    codec_name(&content_type)
+        .map(str::to_string)
+        .unwrap_or_else(|| content_type.to_uppercase())
+}
+
+fn parse_icecast(root: roxmltree::Node) -> Vec<RadioStation> {
+    root.children()
+        .filter(|node| node.has_tag_name("entry"))
+        .filter_map(|entry| {
+            let child = |name: &str| {
+                entry
+                    .children()
+                    .find(|node| node.has_tag_name(name))
+                    .and_then(|node| node.text())
+                    .map(str::trim)
+                    .unwrap_or_default()
+            };
+            let url = child("listen_url");
+            if url.is_empty() {
+                return None;
+            }
+            let name = match child("server_name") {
+                "" => url,
+                name => name,
+            };
+            Some(RadioStation {
+                codec: codec(child("server_type")),
+                bitrate: child("bitrate").parse().unwrap_or_default(),
+                tags: icecast_genre_tags(child("genre")),
+                ..RadioStation::new(
+                    url.to_string(),
+                    format!("{}{url}", RadioStation::ICECAST_PREFIX),
+                    name.to_string(),
+                )
+            })
+        })
+        .collect()
+}
+
+fn parse_shoutcast(root: roxmltree::Node) -> Vec<RadioStation> {
+    let base = root
+        .children()
+        .find(|node| node.has_tag_name("tunein"))
+        .and_then(|tunein| tunein.attribute("base"))
+        .unwrap_or("/sbin/tunein-station.pls");
+    root.children()
+        .filter(|node| node.has_tag_name("station"))
+        .filter_map(|station| {
+            let attribute = |name: &str| station.attribute(name).unwrap_or_default().trim();
+            let id = attribute("id");
+            let name = attribute("name");
+            if id.is_empty() || name.is_empty() {
+                return None;
+            }
+            Some(RadioStation {
+                codec: codec(attribute("mt")),
+                bitrate: attribute("br").parse().unwrap_or_default(),
+                tags: genre_tags(attribute("genre")),
+                clickcount: attribute("lc").parse().unwrap_or_default(),
+                ..RadioStation::new(
+                    format!("{SHOUTCAST_TUNEIN_HOST}{base}?id={id}"),
+                    format!("{}{id}", RadioStation::SHOUTCAST_PREFIX),
+                    name.to_string(),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Downloads and reads the listing at `url`.
+pub async fn fetch_listing(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<Vec<RadioStation>, Error> {
+    let response = client.get(url).send().await?;
+    if !response.status().is_success() {
+        return Err(Error::Http(response.status()));
+    }
+    let body = response.bytes().await?;
+    parse_listing(&String::from_utf8_lossy(&body))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::models::Source;
+
+    const YP_XML: &str = include_str!("../../tests/fixtures/yp.xml");
+    const SHOUTCAST_XML: &str = include_str!("../../tests/fixtures/shoutcast.xml");
+
+    #[test]
+    fn test_parse_icecast() -> Result<(), Error> {
+        let stations = parse_listing(YP_XML)?;
+        assert_eq!(stations.len(), 3);
+        assert_eq!(stations[0].name, "Jazz & Blues");
+        assert_eq!(stations[0].url, "http://jazz.example:8000/live");
+        assert_eq!(stations[0].codec, "MP3");
+        assert_eq!(stations[0].bitrate, 128);
+        assert_eq!(stations[0].tags, vec!["jazz", "blues"]);
+        assert_eq!(stations[0].source(), Source::Icecast);
+        assert_eq!(stations[1].codec, "OGG");
+        assert_eq!(stations[1].bitrate, 0);
+        assert_eq!(stations[2].tags, vec!["classic rock", "blues"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_shoutcast() -> Result<(), Error> {
+        let stations = parse_listing(SHOUTCAST_XML)?;
+        assert_eq!(stations.len(), 3);
+        assert_eq!(
+            stations[0].url,
+            "http://yp.shoutcast.com/sbin/tunein-station.pls?id=1234"
+        );
+        assert_eq!(stations[0].stationuuid, "shoutcast:1234");
+        assert_eq!(stations[0].tags, vec!["classic rock"]);
+        assert_eq!(stations[0].clickcount, 87);
+        assert_eq!(stations[1].codec, "AAC+");
+        assert_eq!(stations[1].tags, vec!["chill", "lounge"]);
+        assert_eq!(stations[1].source(), Source::Shoutcast);
+        assert_eq!(stations[2].tags, vec!["pop", "dance"]);
+        assert!(parse_listing("<html><body/></html>").is_err());
+        assert!(parse_listing("<directory>").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_and_refresh() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(Listings::FILE_NAME);
+        let urls = vec![
+            "http://yp.example".to_string(),
+            "http://sc.example".to_string(),
+        ];
+        let day = Duration::from_secs(24 * 60 * 60);
+
+        let mut listings = Listings::load(&path)?;
+        assert_eq!(
+            listings.stale(&urls, day, 1000),
+            vec!["http://yp.example", "http://sc.example"]
+        );
+        listings.update(&urls[0], parse_listing(YP_XML)?, 1000);
+        listings.mark_checked(&urls[1], 1000);
+        assert!(listings.stale(&urls, day, 2000).is_empty());
+
+        // One save writes the changes until there are none
+        assert!(listings.begin_save());
+        assert!(!listings.begin_save());
+        let (written_to, data) = listings.take_changes().expect("changes")?;
+        assert_eq!(written_to, path);
+        write_atomic(&path, &data)?;
+        assert!(listings.take_changes().is_none());
+        assert!(!listings.begin_save());
+
+        let listings = Listings::load(&path)?;
+        assert_eq!(listings.stale(&urls, day, 1000 + day.as_secs()).len(), 2);
+        let names = |params: &[SearchParam]| -> Vec<String> {
+            listings
+                .search(&urls, params)
+                .into_iter()
+                .map(|s| s.name)
+                .collect()
+        };
+        assert_eq!(
+            names(&[SearchParam::Name("jazz".to_string())]),
+            vec!["Jazz & Blues"]
+        );
+        assert_eq!(
+            names(&[SearchParam::Tags(vec!["talk".to_string()])]),
+            vec!["Night Talk"]
+        );
+        assert_eq!(
+            names(&[SearchParam::Tags(vec!["classic rock".to_string()])]),
+            vec!["Rock Classics"]
+        );
+        assert_eq!(names(&[SearchParam::Limit(1)]).len(), 1);
+        assert!(names(&[SearchParam::Country("Austria".to_string())]).is_empty());
+        assert!(listings.search(&urls[1..], &[]).is_empty());
+        Ok(())
+    }
+}
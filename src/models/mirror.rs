@@ -0,0 +1,643 @@
+//! A local copy of the whole directory, downloaded by `voxide sync`, with an index that answers
+//! searches without the network.
+use std::{
+    cmp::Ordering,
+    collections::{hash_map::RandomState, HashMap},
+    fmt,
+    hash::BuildHasher,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+
+use super::{Order, RadioStation, SearchParam, DEFAULT_SEARCH_LIMIT};
+use crate::{
+    errors::Error,
+    utils::{unix_now, write_atomic},
+};
+
+/// A station as listed by the `stations` and `stations/changed` endpoints, with the fields the
+/// mirror keeps.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct DirectoryStation {
+    changeuuid: String,
+    stationuuid: String,
+    name: String,
+    url: String,
+    url_resolved: String,
+    homepage: String,
+    favicon: String,
+    tags: String,
+    country: String,
+    countrycode: String,
+    state: String,
+    language: String,
+    languagecodes: Option<String>,
+    votes: i32,
+    lastchangetime_iso8601: String,
+    codec: String,
+    bitrate: u32,
+    hls: u8,
+    lastcheckok: u8,
+    clickcount: u32,
+    clicktrend: i32,
+    geo_lat: Option<f64>,
+    geo_long: Option<f64>,
+}
+
+impl From<DirectoryStation> for RadioStation {
+    fn from(value: DirectoryStation) -> Self {
+        Self {
+            name: value.name,
+            stationuuid: value.stationuuid,
+            url: value.url,
+            url_resolved: value.url_resolved,
+            codec: value.codec,
+            bitrate: value.bitrate,
+            homepage: value.homepage,
+            favicon: value.favicon,
+            tags: Self::parse_list(&value.tags),
+            country: value.country,
+            countrycode: value.countrycode,
+            state: value.state,
+            languages: Self::parse_list(&value.language),
+            languagecodes: value.languagecodes,
+            votes: value.votes,
+            clickcount: value.clickcount,
+            clicktrend: value.clicktrend,
+            lastcheckok: value.lastcheckok == 1,
+            hls: value.hls == 1,
+            geo_lat: value.geo_lat,
+            geo_long: value.geo_long,
+            ..Default::default()
+        }
+    }
+}
+
+/// What a sync changed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Whether the whole directory was downloaded rather than the changes since the last sync.
+    pub full: bool,
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    /// The stations in the mirror after the sync.
+    pub total: usize,
+}
+
+impl fmt::Display for SyncReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} stations ({} added, {} updated",
+            self.total, self.added, self.updated
+        )?;
+        if self.full {
+            write!(f, ", {} removed", self.removed)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Positions of the mirror's stations by the values searches filter on.
+#[derive(Debug, Default, Clone)]
+struct Index {
+    /// The lowercase names, by position.
+    names: Vec<String>,
+    tags: HashMap<String, Vec<u32>>,
+    /// By lowercase country name.
+    countries: HashMap<String, Vec<u32>>,
+    /// By uppercase country code.
+    country_codes: HashMap<String, Vec<u32>>,
+    /// By lowercase language.
+    languages: HashMap<String, Vec<u32>>,
+    /// By lowercase codec.
+    codecs: HashMap<String, Vec<u32>>,
+    /// All positions, ordered by bitrate.
+    by_bitrate: Vec<u32>,
+}
+
+impl Index {
+    fn build(stations: &[RadioStation]) -> Self {
+        let mut index = Index::default();
+        for (i, station) in stations.iter().enumerate() {
+            let i = i as u32;
+            index.names.push(station.name.trim().to_lowercase());
+            for tag in &station.tags {
+                push(&mut index.tags, tag.to_lowercase(), i);
+            }
+            if !station.country.is_empty() {
+                push(&mut index.countries, station.country.to_lowercase(), i);
+            }
+            if !station.countrycode.is_empty() {
+                push(
+                    &mut index.country_codes,
+                    station.countrycode.to_uppercase(),
+                    i,
+                );
+            }
+            for language in &station.languages {
+                push(&mut index.languages, language.to_lowercase(), i);
+            }
+            push(&mut index.codecs, station.codec.to_lowercase(), i);
+        }
+        index.by_bitrate = (0..stations.len() as u32).collect();
+        index
+            .by_bitrate
+            .sort_by_key(|i| stations[*i as usize].bitrate);
+        index
+    }
+}
+
+fn push(map: &mut HashMap<String, Vec<u32>>, key: String, position: u32) {
+    let positions = map.entry(key).or_default();
+    // Stations are indexed in order, so a station listing a value twice is the last one
+    if positions.last() != Some(&position) {
+        positions.push(position);
+    }
+}
+
+/// The positions listed under any key containing `query`, as directory searches match countries
+/// and languages partially.
+fn containing(map: &HashMap<String, Vec<u32>>, query: &str) -> Vec<u32> {
+    let query = query.to_lowercase();
+    let mut positions: Vec<u32> = map
+        .iter()
+        .filter(|(key, _)| key.contains(&query))
+        .flat_map(|(_, positions)| positions.iter().copied())
+        .collect();
+    positions.sort_unstable();
+    positions.dedup();
+    positions
+}
+
+/// Keeps the positions of both sorted lists.
+fn intersect(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (mut i, mut j) = (0, 0);
+    let mut both = Vec::new();
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            Ordering::Less => i += 1,
+            Ordering::Greater => j += 1,
+            Ordering::Equal => {
+                both.push(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    both
+}
+
+/// The local copy of the directory.
+///
+/// Persisted as gzip compressed JSON in the data directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mirror {
+    /// The version of the file format, see [`Mirror::VERSION`].
+    version: u32,
+    /// The `changeuuid` of the newest change in the mirror, where the next sync continues.
+    last_change: Option<String>,
+    /// Seconds since the unix epoch of the last sync.
+    synced_at: u64,
+    stations: Vec<RadioStation>,
+    #[serde(skip)]
+    index: Index,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Default for Mirror {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            last_change: None,
+            synced_at: 0,
+            stations: Vec::new(),
+            index: Index::default(),
+            path: PathBuf::new(),
+        }
+    }
+}
+
+impl Mirror {
+    pub const FILE_NAME: &'static str = "directory.json.gz";
+    /// The newest file format this build reads.
+    pub const VERSION: u32 = 1;
+
+    /// Loads the mirror from `path`, starting an empty one if it was never synced.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut mirror = match std::fs::read(path) {
+            Ok(data) => serde_json::from_reader::<_, Self>(GzDecoder::new(data.as_slice()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e.into()),
+        };
+        if mirror.version > Self::VERSION {
+            return Err(Error::UnsupportedVersion {
+                file: path.display().to_string(),
+                version: mirror.version,
+                supported: Self::VERSION,
+            });
+        }
+        mirror.version = Self::VERSION;
+        mirror.index = Index::build(&mirror.stations);
+        mirror.path = path.to_path_buf();
+        Ok(mirror)
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        serde_json::to_writer(&mut encoder, self)?;
+        encoder.flush()?;
+        write_atomic(&self.path, &encoder.finish()?)?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stations.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.stations.len()
+    }
+
+    /// Seconds since the unix epoch of the last sync, 0 if there was none.
+    pub fn synced_at(&self) -> u64 {
+        self.synced_at
+    }
+
+    /// Brings the mirror up to date with the directory at `base_url`. It is not saved, writing
+    /// the large file blocks, so callers in the TUI do that with [`Mirror::save`] on a thread
+    /// where blocking is fine.
+    ///
+    /// The first sync, or any with `full`, downloads every station. Later ones only download
+    /// the changes since the previous sync, which do not tell about deleted stations, so a full
+    /// sync now and then also drops those.
+    pub async fn sync(
+        &mut self,
+        client: &reqwest::Client,
+        base_url: &str,
+        full: bool,
+    ) -> Result<SyncReport, Error> {
+        let base_url = base_url.trim_end_matches('/');
+        let report = match self.last_change.clone().filter(|_| !full) {
+            Some(last_change) => {
+                let url = format!("{base_url}/json/stations/changed");
+                let changes = fetch(client, &url, &[("lastchangeuuid", &last_change)]).await?;
+                self.apply(changes)
+            }
+            None => {
+                let url = format!("{base_url}/json/stations");
+                let stations = fetch(client, &url, &[("hidebroken", "false")]).await?;
+                self.replace(stations)
+            }
+        };
+        self.synced_at = unix_now();
+        Ok(report)
+    }
+
+    /// Replaces the stations with a full download of the directory.
+    fn replace(&mut self, stations: Vec<DirectoryStation>) -> SyncReport {
+        let mut report = SyncReport {
+            full: true,
+            ..Default::default()
+        };
+        self.last_change = newest_change(&stations).or(self.last_change.take());
+        let mut previous: HashMap<String, RadioStation> = self
+            .stations
+            .drain(..)
+            .map(|s| (s.stationuuid.clone(), s))
+            .collect();
+        self.stations = stations.into_iter().map(RadioStation::from).collect();
+        for station in &self.stations {
+            match previous.remove(&station.stationuuid) {
+                None => report.added += 1,
+                Some(old) if old != *station => report.updated += 1,
+                Some(_) => (),
+            }
+        }
+        report.removed = previous.len();
+        report.total = self.stations.len();
+        self.index = Index::build(&self.stations);
+        report
+    }
+
+    /// Applies the changes since the last sync, in the order the directory made them.
+    fn apply(&mut self, changes: Vec<DirectoryStation>) -> SyncReport {
+        let mut report = SyncReport::default();
+        if let Some(change) = newest_change(&changes) {
+            self.last_change = Some(change);
+        }
+        let mut positions: HashMap<String, usize> = self
+            .stations
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (s.stationuuid.clone(), i))
+            .collect();
+        for change in changes {
+            let station = RadioStation::from(change);
+            match positions.get(&station.stationuuid) {
+                Some(i) => {
+                    self.stations[*i] = station;
+                    report.updated += 1;
+                }
+                None => {
+                    positions.insert(station.stationuuid.clone(), self.stations.len());
+                    self.stations.push(station);
+                    report.added += 1;
+                }
+            }
+        }
+        report.total = self.stations.len();
+        self.index = Index::build(&self.stations);
+        report
+    }
+
+    /// Searches the mirror like the directory would: names, countries and languages match
+    /// partially, tags, codecs and country codes exactly, all ignoring case.
+    pub fn search(&self, params: &[SearchParam]) -> Vec<RadioStation> {
+        let index = &self.index;
+        let mut candidates: Option<Vec<u32>> = None;
+        let mut narrow = |positions: Vec<u32>| {
+            candidates = Some(match candidates.take() {
+                Some(current) => intersect(&current, &positions),
+                None => positions,
+            });
+        };
+        let lookup = |map: &HashMap<String, Vec<u32>>, key: String| {
+            map.get(&key).cloned().unwrap_or_default()
+        };
+
+        let (mut min_bitrate, mut max_bitrate) = (0, u32::MAX);
+        for param in params {
+            match param {
+                SearchParam::Tags(tags) => {
+                    for tag in tags {
+                        narrow(lookup(&index.tags, tag.to_lowercase()));
+                    }
+                }
+                SearchParam::Country(country) => narrow(containing(&index.countries, country)),
+                SearchParam::CountryCode(code) => {
+                    narrow(lookup(&index.country_codes, code.to_uppercase()))
+                }
+                SearchParam::Language(language) => narrow(containing(&index.languages, language)),
+                SearchParam::Codec(codec) => narrow(lookup(&index.codecs, codec.to_lowercase())),
+                SearchParam::BitrateMin(min) => min_bitrate = *min,
+                SearchParam::BitrateMax(max) => max_bitrate = *max,
+                _ => (),
+            }
+        }
+        if min_bitrate > 0 || max_bitrate < u32::MAX {
+            let bitrate = |i: &u32| self.stations[*i as usize].bitrate;
+            let start = index
+                .by_bitrate
+                .partition_point(|i| bitrate(i) < min_bitrate);
+            let end = index
+                .by_bitrate
+                .partition_point(|i| bitrate(i) <= max_bitrate);
+            let mut positions = index.by_bitrate[start..end.max(start)].to_vec();
+            positions.sort_unstable();
+            narrow(positions);
+        }
+
+        let exact = params.contains(&SearchParam::NameExact(true));
+        let name = params.iter().find_map(|param| match param {
+            SearchParam::Name(name) => Some(name.trim().to_lowercase()),
+            _ => None,
+        });
+        let mut found: Vec<&RadioStation> = candidates
+            .unwrap_or_else(|| (0..self.stations.len() as u32).collect())
+            .into_iter()
+            .filter(|i| match &name {
+                Some(name) if exact => index.names[*i as usize] == *name,
+                Some(name) => index.names[*i as usize].contains(name.as_str()),
+                None => true,
+            })
+            .map(|i| &self.stations[i as usize])
+            .filter(|station| {
+                params.iter().all(|param| match param {
+                    SearchParam::IsHttps(https) => station.url.starts_with("https:") == *https,
+                    SearchParam::HasGeoInfo(geo) => station.geo_lat.is_some() == *geo,
+                    _ => true,
+                })
+            })
+            .collect();
+
+        let mut order = Order::Votes;
+        let mut reverse = true;
+        let mut limit = DEFAULT_SEARCH_LIMIT;
+        for param in params {
+            match param {
+                SearchParam::Order(o) => order = *o,
+                SearchParam::Reverse(r) => reverse = *r,
+                SearchParam::Limit(l) => limit = *l,
+                _ => (),
+            }
+        }
+        if order == Order::Random {
+            let state = RandomState::new();
+            found.sort_by_cached_key(|station| state.hash_one(&station.stationuuid));
+        } else {
+            found.sort_by(|a, b| {
+                let ordering = compare(order, a, b);
+                if reverse {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            });
+        }
+        found.into_iter().take(limit).cloned().collect()
+    }
+}
+
+/// The `changeuuid` of the most recently changed station.
+fn newest_change(stations: &[DirectoryStation]) -> Option<String> {
+    stations
+        .iter()
+        .filter(|s| !s.changeuuid.is_empty())
+        .max_by(|a, b| a.lastchangetime_iso8601.cmp(&b.lastchangetime_iso8601))
+        .map(|s| s.changeuuid.clone())
+}
+
+/// Compares stations by a directory order. Orders by details the mirror does not keep, like
+/// timestamps, keep the stations as they are.
+fn compare(order: Order, a: &RadioStation, b: &RadioStation) -> Ordering {
+    let text = |f: fn(&RadioStation) -> String| f(a).to_lowercase().cmp(&f(b).to_lowercase());
+    match order {
+        Order::Name => text(|s| s.name.trim().to_string()),
+        Order::Url => text(|s| s.url.clone()),
+        Order::Homepage => text(|s| s.homepage.clone()),
+        Order::Favicon => text(|s| s.favicon.clone()),
+        Order::Tags => text(|s| s.tags.join(",")),
+        Order::Country => text(|s| s.country.clone()),
+        Order::State => text(|s| s.state.clone()),
+        Order::Language => text(|s| s.languages.join(",")),
+        Order::Codec => text(|s| s.codec.clone()),
+        Order::Votes => a.votes.cmp(&b.votes),
+        Order::Bitrate => a.bitrate.cmp(&b.bitrate),
+        Order::Lastcheckok => a.lastcheckok.cmp(&b.lastcheckok),
+        Order::Clicks => a.clickcount.cmp(&b.clickcount),
+        Order::RecentTrend => a.clicktrend.cmp(&b.clicktrend),
+        Order::Lastchecktime | Order::Clicktimestamp | Order::Changetimestamp | Order::Random => {
+            Ordering::Equal
+        }
+    }
+}
+
+async fn fetch(
+    client: &reqwest::Client,
+    url: &str,
+    query: &[(&str, &str)],
+) -> Result<Vec<DirectoryStation>, Error> {
+    tracing::info!(url, ?query, "downloading directory");
+    let response = client.get(url).query(query).send().await?;
+    if !response.status().is_success() {
+        return Err(Error::Http(response.status()));
+    }
+    let body = response.bytes().await?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::models::test_server::{self, Response};
+
+    const DUMP: &str = r#"[
+        {"changeuuid": "c1", "stationuuid": "a", "name": "Jazz Radio", "url": "https://jazz.example",
+         "tags": "jazz,smooth jazz", "country": "Austria", "countrycode": "AT",
+         "language": "german", "codec": "MP3", "bitrate": 128, "votes": 50,
+         "lastchangetime_iso8601": "2024-01-01T00:00:00Z", "lastcheckok": 1},
+        {"changeuuid": "c3", "stationuuid": "b", "name": "Rock FM", "url": "http://rock.example",
+         "tags": "rock", "country": "Germany", "countrycode": "DE", "language": "german,english",
+         "codec": "AAC", "bitrate": 64, "votes": 10,
+         "lastchangetime_iso8601": "2024-03-01T00:00:00Z"},
+        {"changeuuid": "c2", "stationuuid": "c", "name": "Smooth Jazz 24", "url": "http://sj.example",
+         "tags": "jazz", "country": "Austria", "countrycode": "AT", "codec": "MP3",
+         "bitrate": 320, "votes": 99, "lastchangetime_iso8601": "2024-02-01T00:00:00Z"}
+    ]"#;
+
+    const CHANGES: &str = r#"[
+        {"changeuuid": "c4", "stationuuid": "b", "name": "Rock FM", "url": "https://rock.example",
+         "tags": "rock", "countrycode": "DE", "codec": "MP3", "bitrate": 192,
+         "lastchangetime_iso8601": "2024-04-01T00:00:00Z"},
+        {"changeuuid": "c5", "stationuuid": "d", "name": "News", "url": "http://news.example",
+         "codec": "MP3", "bitrate": 96, "lastchangetime_iso8601": "2024-05-01T00:00:00Z"}
+    ]"#;
+
+    /// Serves the dump, and the changes after `c3`, like a radio-browser server.
+    async fn serve() -> String {
+        test_server::serve(|_, path| match path {
+            "/json/stations?hidebroken=false" => Response::ok("application/json", DUMP),
+            "/json/stations/changed?lastchangeuuid=c3" => Response::ok("application/json", CHANGES),
+            _ => Response::not_found(),
+        })
+        .await
+    }
+
+    fn names(mirror: &Mirror, params: &[SearchParam]) -> Vec<String> {
+        mirror.search(params).into_iter().map(|s| s.name).collect()
+    }
+
+    #[tokio::test]
+    async fn test_sync_then_apply_changes() -> Result<(), Error> {
+        let base = serve().await;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(Mirror::FILE_NAME);
+        let client = reqwest::Client::new();
+
+        let mut mirror = Mirror::load(&path)?;
+        assert!(mirror.is_empty());
+        let report = mirror.sync(&client, &base, false).await?;
+        assert_eq!(
+            report,
+            SyncReport {
+                full: true,
+                added: 3,
+                updated: 0,
+                removed: 0,
+                total: 3
+            }
+        );
+        mirror.save()?;
+
+        // The next sync continues after the newest change of the dump
+        let mut mirror = Mirror::load(&path)?;
+        assert_eq!(mirror.len(), 3);
+        let report = mirror.sync(&client, &base, false).await?;
+        assert_eq!((report.full, report.added, report.updated), (false, 1, 1));
+        assert_eq!(report.to_string(), "4 stations (1 added, 1 updated)");
+        mirror.save()?;
+
+        let mirror = Mirror::load(&path)?;
+        assert_eq!(mirror.last_change.as_deref(), Some("c5"));
+        assert_eq!(
+            names(&mirror, &[SearchParam::Codec("mp3".to_string())]),
+            vec!["Smooth Jazz 24", "Jazz Radio", "Rock FM", "News"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_index() {
+        let mut mirror = Mirror::default();
+        mirror.replace(serde_json::from_str(DUMP).unwrap());
+
+        assert_eq!(
+            names(&mirror, &[SearchParam::Name("jazz".to_string())]),
+            vec!["Smooth Jazz 24", "Jazz Radio"]
+        );
+        assert_eq!(
+            names(
+                &mirror,
+                &[
+                    SearchParam::Name("JAZZ RADIO".to_string()),
+                    SearchParam::NameExact(true)
+                ]
+            ),
+            vec!["Jazz Radio"]
+        );
+        assert_eq!(
+            names(
+                &mirror,
+                &[
+                    SearchParam::Tags(vec!["jazz".to_string()]),
+                    SearchParam::BitrateMax(128)
+                ]
+            ),
+            vec!["Jazz Radio"]
+        );
+        assert_eq!(
+            names(&mirror, &[SearchParam::Country("germ".to_string())]),
+            vec!["Rock FM"]
+        );
+        assert_eq!(
+            names(&mirror, &[SearchParam::Language("english".to_string())]),
+            vec!["Rock FM"]
+        );
+        assert_eq!(
+            names(
+                &mirror,
+                &[
+                    SearchParam::CountryCode("at".to_string()),
+                    SearchParam::Order(Order::Name),
+                    SearchParam::Reverse(false),
+                    SearchParam::Limit(1)
+                ]
+            ),
+            vec!["Jazz Radio"]
+        );
+        assert_eq!(
+            names(&mirror, &[SearchParam::IsHttps(true)]),
+            vec!["Jazz Radio"]
+        );
+        assert!(names(&mirror, &[SearchParam::Tags(vec!["pop".to_string()])]).is_empty());
+    }
+}
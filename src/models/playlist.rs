@@ -0,0 +1,344 @@
+//! Reading and writing stations as M3U, PLS, XSPF and JSON playlists.
+use std::{collections::BTreeMap, fmt::Write as _, path::Path};
+
+use strum::{Display, EnumString, VariantNames};
+
+use super::RadioStation;
+use crate::errors::Error;
+
+/// The playlist formats stations can be imported from and exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString, VariantNames)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum PlaylistFormat {
+    /// One url per line.
+    M3u,
+    /// M3U with an `#EXTINF` line naming each station.
+    ExtM3u,
+    Pls,
+    Xspf,
+    /// The stations with all their details, as saved by voxide.
+    Json,
+}
+
+impl PlaylistFormat {
+    /// The format for the extension of `path`. `.m3u` files are written as extended M3U, which
+    /// plain M3U readers accept too.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "m3u" | "m3u8" => Some(PlaylistFormat::ExtM3u),
+            "pls" => Some(PlaylistFormat::Pls),
+            "xspf" => Some(PlaylistFormat::Xspf),
+            "json" => Some(PlaylistFormat::Json),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the contents of a playlist.
+    pub fn detect(contents: &str) -> Option<Self> {
+        let start = contents.trim_start_matches('\u{feff}').trim_start();
+        let first_line = start.lines().next().unwrap_or_default().trim();
+        if first_line.starts_with("#EXTM3U") {
+            Some(PlaylistFormat::ExtM3u)
+        } else if first_line.eq_ignore_ascii_case("[playlist]") {
+            Some(PlaylistFormat::Pls)
+        } else if start.starts_with('<') {
+            Some(PlaylistFormat::Xspf)
+        } else if start.starts_with('[') || start.starts_with('{') {
+            Some(PlaylistFormat::Json)
+        } else if !start.is_empty() {
+            Some(PlaylistFormat::M3u)
+        } else {
+            None
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            PlaylistFormat::M3u | PlaylistFormat::ExtM3u => "m3u",
+            PlaylistFormat::Pls => "pls",
+            PlaylistFormat::Xspf => "xspf",
+            PlaylistFormat::Json => "json",
+        }
+    }
+
+    /// How many of `stations` have request headers, which [`PlaylistFormat::export`] leaves out
+    /// as they may hold credentials.
+    pub fn headers_left_out(stations: &[RadioStation]) -> usize {
+        stations.iter().filter(|s| !s.headers.is_empty()).count()
+    }
+
+    /// Writes `stations` as a playlist in this format, without their request headers.
+    pub fn export(self, stations: &[RadioStation]) -> Result<String, Error> {
+        let mut out = String::new();
+        match self {
+            PlaylistFormat::M3u => {
+                for station in stations {
+                    let _ = writeln!(out, "{}", station.url);
+                }
+            }
+            PlaylistFormat::ExtM3u => {
+                out.push_str("#EXTM3U\n");
+                for station in stations {
+                    let logo = match station.favicon.as_str() {
+                        "" => String::new(),
+                        favicon => format!(" tvg-logo=\"{}\"", favicon.replace('"', "")),
+                    };
+                    let _ = writeln!(out, "#EXTINF:-1{logo},{}", one_line(&station.name));
+                    let _ = writeln!(out, "{}", station.url);
+                }
+            }
+            PlaylistFormat::Pls => {
+                out.push_str("[playlist]\n");
+                for (i, station) in stations.iter().enumerate() {
+                    let n = i + 1;
+                    let _ = writeln!(out, "File{n}={}", station.url);
+                    let _ = writeln!(out, "Title{n}={}", one_line(&station.name));
+                    let _ = writeln!(out, "Length{n}=-1");
+                }
+                let _ = writeln!(out, "NumberOfEntries={}", stations.len());
+                out.push_str("Version=2\n");
+            }
+            PlaylistFormat::Xspf => {
+                out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+                out.push_str("<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n");
+                out.push_str("  <trackList>\n");
+                for station in stations {
+                    out.push_str("    <track>\n");
+                    let _ = writeln!(out, "      <location>{}</location>", escape(&station.url));
+                    let _ = writeln!(out, "      <title>{}</title>", escape(station.name.trim()));
+                    if !station.favicon.is_empty() {
+                        let _ = writeln!(out, "      <image>{}</image>", escape(&station.favicon));
+                    }
+                    if !station.homepage.is_empty() {
+                        let _ = writeln!(out, "      <info>{}</info>", escape(&station.homepage));
+                    }
+                    out.push_str("    </track>\n");
+                }
+                out.push_str("  </trackList>\n</playlist>\n");
+            }
+            PlaylistFormat::Json => {
+                let stations: Vec<RadioStation> = stations
+                    .iter()
+                    .map(|station| RadioStation {
+                        headers: BTreeMap::new(),
+                        ..station.clone()
+                    })
+                    .collect();
+                out = serde_json::to_string_pretty(&stations)?;
+            }
+        }
+        Ok(out)
+    }
+
+    /// Reads the stations of a playlist, guessing its format if `format` is `None`.
+    ///
+    /// Stations of JSON playlists keep their details, the others become custom stations named by
+    /// their title, or by their url if they have none.
+    pub fn import(contents: &str, format: Option<Self>) -> Result<Vec<RadioStation>, Error> {
+        let contents = contents.trim_start_matches('\u{feff}');
+        let format = format
+            .or_else(|| Self::detect(contents))
+            .ok_or_else(|| Error::Playlist("the playlist is empty".to_string()))?;
+        let stations = match format {
+            PlaylistFormat::M3u | PlaylistFormat::ExtM3u => import_m3u(contents),
+            PlaylistFormat::Pls => import_pls(contents),
+            PlaylistFormat::Xspf => import_xspf(contents)?,
+            PlaylistFormat::Json => serde_json::from_str(contents)?,
+        };
+        Ok(stations)
+    }
+}
+
+fn import_m3u(contents: &str) -> Vec<RadioStation> {
+    let mut stations = Vec::new();
+    let mut title = None;
+    for line in contents.lines().map(str::trim) {
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            title = extinf_title(info);
+        } else if !line.is_empty() && !line.starts_with('#') {
+            stations.push(custom_station(line, title.take()));
+        }
+    }
+    stations
+}
+
+/// The title of an `#EXTINF:<duration> [attributes],<title>` line: everything after the first
+/// comma outside quoted attribute values.
+fn extinf_title(info: &str) -> Option<String> {
+    let mut quoted = false;
+    for (i, c) in info.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                let title = info[i + 1..].trim();
+                return (!title.is_empty()).then(|| title.to_string());
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+fn import_pls(contents: &str) -> Vec<RadioStation> {
+    // Entries are numbered and their keys may come in any order
+    let mut entries: BTreeMap<u32, (Option<String>, Option<String>)> = BTreeMap::new();
+    for line in contents.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim().to_string();
+        if let Some(n) = key.strip_prefix("file").and_then(|n| n.parse().ok()) {
+            entries.entry(n).or_default().0 = Some(value);
+        } else if let Some(n) = key.strip_prefix("title").and_then(|n| n.parse().ok()) {
+            entries.entry(n).or_default().1 = Some(value).filter(|v| !v.is_empty());
+        }
+    }
+    entries
+        .into_values()
+        .filter_map(|(url, title)| Some(custom_station(&url?, title)))
+        .collect()
+}
+
+fn import_xspf(contents: &str) -> Result<Vec<RadioStation>, Error> {
+    let document =
+        roxmltree::Document::parse(contents).map_err(|e| Error::Playlist(e.to_string()))?;
+    let stations = document
+        .descendants()
+        .filter(|node| node.has_tag_name("track"))
+        .filter_map(|track| {
+            let child = |name: &str| {
+                track
+                    .children()
+                    .find(|node| node.has_tag_name(name))
+                    .and_then(|node| node.text())
+                    .map(|text| text.trim().to_string())
+                    .filter(|text| !text.is_empty())
+            };
+            let mut station = custom_station(&child("location")?, child("title"));
+            station.favicon = child("image").unwrap_or_default();
+            station.homepage = child("info").unwrap_or_default();
+            Some(station)
+        })
+        .collect();
+    Ok(stations)
+}
+
+fn custom_station(url: &str, title: Option<String>) -> RadioStation {
+    let name = title.unwrap_or_else(|| url.to_string());
+    RadioStation::custom(url, &name)
+}
+
+/// Keeps a name on one line, as the line based formats require.
+fn one_line(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn stations() -> Vec<RadioStation> {
+        let mut jazz = RadioStation::new("http://jazz.example/stream", "uuid-1", "Jazz & Blues");
+        jazz.favicon = "http://jazz.example/logo.png".to_string();
+        jazz.homepage = "http://jazz.example".to_string();
+        vec![
+            jazz,
+            RadioStation::custom("http://news.example/live.mp3", "News, 24/7"),
+        ]
+    }
+
+    #[test]
+    fn test_round_trip_keeps_urls_and_names() {
+        for format in [
+            PlaylistFormat::ExtM3u,
+            PlaylistFormat::Pls,
+            PlaylistFormat::Xspf,
+            PlaylistFormat::Json,
+        ] {
+            let exported = format.export(&stations()).unwrap();
+            assert_eq!(PlaylistFormat::detect(&exported), Some(format), "{format}");
+            let imported = PlaylistFormat::import(&exported, None).unwrap();
+            let summary: Vec<_> = imported.iter().map(|s| (&*s.url, &*s.name)).collect();
+            assert_eq!(
+                summary,
+                vec![
+                    ("http://jazz.example/stream", "Jazz & Blues"),
+                    ("http://news.example/live.mp3", "News, 24/7")
+                ],
+                "{format}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_json_leaves_out_headers() {
+        let mut stations = stations();
+        stations[1]
+            .headers
+            .insert("Authorization".to_string(), "Basic c2VjcmV0".to_string());
+        assert_eq!(PlaylistFormat::headers_left_out(&stations), 1);
+        let exported = PlaylistFormat::Json.export(&stations).unwrap();
+        assert!(!exported.contains("Authorization"));
+        let imported = PlaylistFormat::import(&exported, None).unwrap();
+        assert!(imported[1].headers.is_empty());
+    }
+
+    #[test]
+    fn test_plain_m3u_is_named_by_url() {
+        let exported = PlaylistFormat::M3u.export(&stations()).unwrap();
+        let imported = PlaylistFormat::import(&exported, None).unwrap();
+        assert_eq!(imported[0].name, "http://jazz.example/stream");
+        assert!(imported[0].is_custom());
+    }
+
+    #[test]
+    fn test_import_extm3u_with_attributes() {
+        let playlist = "#EXTM3U\n\
+            #EXTINF:-1 tvg-logo=\"http://a.example/x,y.png\" group-title=\"Jazz\",Smooth Jazz\n\
+            http://a.example/stream\n\
+            # a comment\n\
+            http://b.example/stream\n";
+        let imported = PlaylistFormat::import(playlist, None).unwrap();
+        assert_eq!(imported.len(), 2);
+        assert_eq!(imported[0].name, "Smooth Jazz");
+        assert_eq!(imported[1].name, "http://b.example/stream");
+    }
+
+    #[test]
+    fn test_import_pls_out_of_order() {
+        let playlist = "[playlist]\r\nTitle2=Second\r\nFile2=http://two.example\r\n\
+            File1=http://one.example\r\nNumberOfEntries=2\r\n";
+        let imported = PlaylistFormat::import(playlist, None).unwrap();
+        let urls: Vec<_> = imported.iter().map(|s| &*s.url).collect();
+        assert_eq!(urls, vec!["http://one.example", "http://two.example"]);
+        assert_eq!(imported[1].name, "Second");
+    }
+
+    #[test]
+    fn test_import_xspf_reads_details() {
+        let exported = PlaylistFormat::Xspf.export(&stations()).unwrap();
+        let imported = PlaylistFormat::import(&exported, Some(PlaylistFormat::Xspf)).unwrap();
+        assert_eq!(imported[0].favicon, "http://jazz.example/logo.png");
+        assert_eq!(imported[0].homepage, "http://jazz.example");
+        assert!(PlaylistFormat::import("<playlist><trackList>", None).is_err());
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        let format = |path: &str| PlaylistFormat::from_path(Path::new(path));
+        assert_eq!(format("a/b.M3U8"), Some(PlaylistFormat::ExtM3u));
+        assert_eq!(format("b.xspf"), Some(PlaylistFormat::Xspf));
+        assert_eq!(format("b.txt"), None);
+    }
+}
@@ -1,57 +1,299 @@
-use radiobrowser::{RadioBrowserAPI, StationOrder};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
 use serde::{Deserialize, Serialize};
-use strum::EnumString;
+use strum::{EnumString, VariantNames};
+
+use super::{
+    check_history::ApiCheck, response_cache::Lookup, DirectoryCheck, Mirror, RadioStation,
+    ResponseCache, SyncReport,
+};
+use crate::{
+    errors::Error,
+    utils::{unix_now, write_atomic},
+};
 
-use crate::errors::Error;
+/// How many stations are looked up per request by [`RadioApi::stations_by_uuid`], keeping the
+/// url short enough for servers.
+const UUID_BATCH_SIZE: usize = 50;
+
+/// The round robin address of all radio-browser servers.
+pub const DEFAULT_API_URL: &str = "https://all.api.radio-browser.info";
 
 #[derive(Debug)]
 pub struct RadioApi {
-    api: RadioBrowserAPI,
+    client: reqwest::Client,
+    /// Base url of the radio-browser server every request goes to, without a trailing slash.
+    base_url: String,
+    /// Responses kept on disk, see [`RadioApi::with_cache`].
+    cache: Option<Arc<Mutex<ResponseCache>>>,
+}
+
+/// The response of the `vote` and `url` (click) endpoints.
+#[derive(Debug, Clone, Deserialize)]
+struct ActionResponse {
+    ok: bool,
+    #[serde(default)]
+    message: String,
+}
+
+/// Writes the changes of the cache on a thread where blocking is fine, outside its lock.
+fn save_cache(cache: Arc<Mutex<ResponseCache>>) {
+    tokio::task::spawn_blocking(move || loop {
+        let Some(changes) = cache.lock().ok().and_then(|mut cache| cache.take_changes()) else {
+            return;
+        };
+        let result = changes.and_then(|(path, data)| Ok(write_atomic(&path, &data)?));
+        if let Err(e) = result {
+            tracing::warn!(error = ?e, "failed to save the response cache");
+        }
+    });
 }
 
 impl RadioApi {
-    pub async fn new() -> Result<Self, Error> {
+    pub fn new(base_url: &str) -> Result<Self, Error> {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!(
+                env!("CARGO_PKG_NAME"),
+                "/",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .build()?;
         Ok(Self {
-            api: RadioBrowserAPI::new().await?,
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            cache: None,
         })
     }
 
-    pub async fn get_stations(
-        &self,
-        params: Vec<SearchParam>,
-    ) -> Result<Vec<super::RadioStation>, Error> {
-        let mut builder = self
-            .api
-            .get_stations()
-            .reverse(true)
-            .limit(30.to_string())
-            .order(Order::Votes.into());
+    /// Answers searches and station lookups from `cache` when it can. Stale responses are
+    /// answered with as well, while they are fetched again in the background.
+    pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(Arc::new(Mutex::new(cache)));
+        self
+    }
 
-        for param in params.into_iter() {
-            tracing::info!(?param, "building search");
-            match param {
-                SearchParam::Name(name) => builder = builder.name(name),
-                SearchParam::Language(language) => builder = builder.language(language),
-                SearchParam::Country(country) => builder = builder.country(country),
-                SearchParam::Tags(tags) => {
-                    builder = builder.tag_list(tags.iter().map(|x| x.as_str()).collect())
+    /// Answers the request `key` from the cache, or with `fetch` if it is not cached or
+    /// `bypass` is set.
+    async fn cached<F, Fut>(
+        self: &Arc<Self>,
+        key: String,
+        bypass: bool,
+        fetch: F,
+    ) -> Result<Vec<RadioStation>, Error>
+    where
+        F: FnOnce(Arc<Self>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Vec<RadioStation>, Error>> + Send + 'static,
+    {
+        let Some(cache) = self.cache.clone() else {
+            return fetch(self.clone()).await;
+        };
+        if !bypass {
+            let lookup = cache
+                .lock()
+                .map_err(|e| Error::Lock(e.to_string()))?
+                .get(&key, unix_now());
+            match lookup {
+                Lookup::Fresh(stations) => return Ok(stations),
+                Lookup::Stale { stations, refresh } => {
+                    if refresh {
+                        let api = self.clone();
+                        tokio::spawn(async move {
+                            tracing::debug!(key, "refreshing stale response");
+                            let result = fetch(api.clone()).await;
+                            api.store(&key, &result);
+                        });
+                    }
+                    return Ok(stations);
                 }
-                SearchParam::Limit(limit) => builder = builder.limit(limit.to_string()),
-                SearchParam::Reverse(reverse) => builder = builder.reverse(reverse),
-                SearchParam::Order(order) => builder = builder.order(order.into()),
+                Lookup::Missing => (),
             }
         }
+        let result = fetch(self.clone()).await;
+        self.store(&key, &result);
+        result
+    }
 
-        Ok(builder
-            .send()
-            .await?
+    fn store(&self, key: &str, result: &Result<Vec<RadioStation>, Error>) {
+        let Some(shared) = self.cache.as_ref() else {
+            return;
+        };
+        let Ok(mut cache) = shared.lock() else {
+            return;
+        };
+        match result {
+            Ok(stations) => {
+                if let Err(e) = cache.insert(key, stations.clone(), unix_now()) {
+                    tracing::warn!(key, error = ?e, "failed to cache response");
+                }
+            }
+            Err(_) => cache.refresh_failed(key),
+        }
+        if cache.begin_save() {
+            save_cache(shared.clone());
+        }
+    }
+
+    /// Sends a GET request to `endpoint` of the radio-browser server and decodes the JSON body.
+    async fn get_json<T: serde::de::DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T, Error> {
+        let url = format!("{}/json/{}", self.base_url, endpoint);
+        tracing::debug!(url, ?query, "radio-browser request");
+        let response = self.client.get(&url).query(query).send().await?;
+        if !response.status().is_success() {
+            return Err(Error::Http(response.status()));
+        }
+        let body = response.bytes().await?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    /// Tells the directory that the station is being listened to, which feeds its click ranking.
+    pub async fn click(&self, stationuuid: &str) -> Result<(), Error> {
+        let response: ActionResponse = self.get_json(&format!("url/{stationuuid}"), &[]).await?;
+        if !response.ok {
+            return Err(Error::Api(response.message));
+        }
+        Ok(())
+    }
+
+    /// Upvotes the station. The directory only accepts one vote per station every 10 minutes.
+    pub async fn vote(&self, stationuuid: &str) -> Result<(), Error> {
+        let response: ActionResponse = self.get_json(&format!("vote/{stationuuid}"), &[]).await?;
+        if !response.ok {
+            return Err(Error::Api(response.message));
+        }
+        Ok(())
+    }
+
+    /// The directory station streaming from `url`, if there is one.
+    pub async fn station_by_url(
+        self: &Arc<Self>,
+        url: &str,
+    ) -> Result<Option<RadioStation>, Error> {
+        let url = url.to_string();
+        let key = format!("stations/byurl?url={url}");
+        let stations = self
+            .cached(key, false, |api| async move {
+                let stations: Vec<radiobrowser::ApiStation> =
+                    api.get_json("stations/byurl", &[("url", &url)]).await?;
+                Ok(stations.into_iter().map(RadioStation::from).collect())
+            })
+            .await?;
+        Ok(stations.into_iter().next())
+    }
+
+    /// The directory's current version of the stations, looked up by `stationuuid` in batches.
+    /// Stations the directory no longer lists are missing from the result. The cache is not
+    /// used, the point is to see the latest.
+    pub async fn stations_by_uuid(&self, uuids: &[String]) -> Result<Vec<RadioStation>, Error> {
+        let mut stations = Vec::new();
+        for batch in uuids.chunks(UUID_BATCH_SIZE) {
+            let found: Vec<radiobrowser::ApiStation> = self
+                .get_json("stations/byuuid", &[("uuids", &batch.join(","))])
+                .await?;
+            stations.extend(found.into_iter().map(RadioStation::from));
+        }
+        Ok(stations)
+    }
+
+    /// The directory's checks of the station's stream in the last `seconds`.
+    pub async fn station_checks(
+        &self,
+        stationuuid: &str,
+        seconds: u64,
+    ) -> Result<Vec<DirectoryCheck>, Error> {
+        let checks: Vec<ApiCheck> = self
+            .get_json(
+                &format!("checks/{stationuuid}"),
+                &[("seconds", &seconds.to_string())],
+            )
+            .await?;
+        Ok(checks
             .into_iter()
-            .map(super::RadioStation::from)
+            .filter_map(ApiCheck::into_check)
             .collect())
     }
+
+    /// Brings the local copy of the directory up to date, see [`Mirror::sync`].
+    pub async fn sync_mirror(&self, mirror: &mut Mirror, full: bool) -> Result<SyncReport, Error> {
+        mirror.sync(&self.client, &self.base_url, full).await
+    }
+
+    /// Replaces the custom stations whose url is in the directory with the directory's station.
+    ///
+    /// Lookups that fail leave the station as it is. Returns how many stations were matched.
+    pub async fn match_stations(self: &Arc<Self>, stations: &mut [RadioStation]) -> usize {
+        let mut matched = 0;
+        for station in stations.iter_mut().filter(|s| s.is_custom()) {
+            match self.station_by_url(&station.url).await {
+                Ok(Some(found)) => {
+                    *station = found;
+                    matched += 1;
+                }
+                Ok(None) => (),
+                Err(e) => {
+                    tracing::warn!(url = station.url, error = ?e, "failed to look up station")
+                }
+            }
+        }
+        matched
+    }
+
+    /// Searches the directory, or the cache unless `params` ask for a
+    /// [`SearchParam::BypassCache`].
+    pub async fn get_stations(
+        self: &Arc<Self>,
+        params: Vec<SearchParam>,
+    ) -> Result<Vec<RadioStation>, Error> {
+        let key = ResponseCache::key("stations", &params);
+        let bypass = params.contains(&SearchParam::BypassCache(true));
+        self.cached(key, bypass, |api| async move { api.search(params).await })
+            .await
+    }
+
+    async fn search(&self, params: Vec<SearchParam>) -> Result<Vec<RadioStation>, Error> {
+        let mut query: HashMap<&str, String> = HashMap::from([
+            ("reverse", true.to_string()),
+            ("limit", DEFAULT_SEARCH_LIMIT.to_string()),
+            ("order", Order::Votes.as_param().to_string()),
+        ]);
+
+        for param in params.into_iter() {
+            tracing::info!(?param, "building search");
+            let (key, value) = match param {
+                SearchParam::Name(name) => ("name", name),
+                SearchParam::Language(language) => ("language", language),
+                SearchParam::Country(country) => ("country", country),
+                SearchParam::Tags(tags) => ("tagList", tags.join(",")),
+                SearchParam::Limit(limit) => ("limit", limit.to_string()),
+                SearchParam::Reverse(reverse) => ("reverse", reverse.to_string()),
+                SearchParam::Order(order) => ("order", order.as_param().to_string()),
+                SearchParam::Codec(codec) => ("codec", codec),
+                SearchParam::BitrateMin(bitrate) => ("bitrateMin", bitrate.to_string()),
+                SearchParam::BitrateMax(bitrate) => ("bitrateMax", bitrate.to_string()),
+                SearchParam::NameExact(exact) => ("nameExact", exact.to_string()),
+                SearchParam::CountryCode(code) => ("countrycode", code),
+                SearchParam::IsHttps(https) => ("is_https", https.to_string()),
+                SearchParam::HasGeoInfo(geo) => ("has_geo_info", geo.to_string()),
+                SearchParam::BypassCache(_) => continue,
+            };
+            query.insert(key, value);
+        }
+
+        let query: Vec<(&str, &str)> = query.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        let stations: Vec<radiobrowser::ApiStation> =
+            self.get_json("stations/search", &query).await?;
+        Ok(stations.into_iter().map(RadioStation::from).collect())
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, EnumString)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, EnumString, VariantNames)]
 pub enum Order {
     Name,
     Url,
@@ -73,31 +315,35 @@ pub enum Order {
     Random,
 }
 
-impl From<Order> for StationOrder {
-    fn from(value: Order) -> Self {
-        match value {
-            Order::Name => StationOrder::Name,
-            Order::Url => StationOrder::Url,
-            Order::Homepage => StationOrder::Homepage,
-            Order::Favicon => StationOrder::Favicon,
-            Order::Tags => StationOrder::Tags,
-            Order::Country => StationOrder::Country,
-            Order::State => StationOrder::State,
-            Order::Language => StationOrder::Language,
-            Order::Votes => StationOrder::Votes,
-            Order::Codec => StationOrder::Codec,
-            Order::Bitrate => StationOrder::Bitrate,
-            Order::Lastcheckok => StationOrder::Lastcheckok,
-            Order::Lastchecktime => StationOrder::Lastchecktime,
-            Order::Clicktimestamp => StationOrder::Clicktimestamp,
-            Order::Clicks => StationOrder::Clickcount,
-            Order::RecentTrend => StationOrder::Clicktrend,
-            Order::Changetimestamp => StationOrder::Changetimestamp,
-            Order::Random => StationOrder::Random,
+impl Order {
+    /// The value of the directory's `order` search parameter.
+    pub fn as_param(self) -> &'static str {
+        match self {
+            Order::Name => "name",
+            Order::Url => "url",
+            Order::Homepage => "homepage",
+            Order::Favicon => "favicon",
+            Order::Tags => "tags",
+            Order::Country => "country",
+            Order::State => "state",
+            Order::Language => "language",
+            Order::Votes => "votes",
+            Order::Codec => "codec",
+            Order::Bitrate => "bitrate",
+            Order::Lastcheckok => "lastcheckok",
+            Order::Lastchecktime => "lastchecktime",
+            Order::Clicktimestamp => "clicktimestamp",
+            Order::Clicks => "clickcount",
+            Order::RecentTrend => "clicktrend",
+            Order::Changetimestamp => "changetimestamp",
+            Order::Random => "random",
         }
     }
 }
 
+/// How many stations a search returns without a [`SearchParam::Limit`].
+pub const DEFAULT_SEARCH_LIMIT: usize = 30;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum SearchParam {
     Name(String),
@@ -107,4 +353,17 @@ pub enum SearchParam {
     Limit(usize),
     Reverse(bool),
     Order(Order),
+    Codec(String),
+    /// Minimum bitrate in kbps.
+    BitrateMin(u32),
+    /// Maximum bitrate in kbps.
+    BitrateMax(u32),
+    /// Only match stations whose name is exactly [`SearchParam::Name`].
+    NameExact(bool),
+    /// Two letter ISO 3166-1 country code, e.g. `DE`.
+    CountryCode(String),
+    IsHttps(bool),
+    HasGeoInfo(bool),
+    /// Ask the directory even if the response is cached.
+    BypassCache(bool),
 }
@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     io::Write,
     sync::{mpsc::RecvError, Arc},
     thread,
@@ -16,6 +17,7 @@ use ratatui::{
 use reqwest::header;
 use rodio::{Decoder, OutputStream, Sink};
 use serde::{Deserialize, Serialize};
+use strum::Display;
 use tokio::sync::{broadcast, mpsc, Mutex};
 
 use crate::{
@@ -23,6 +25,12 @@ use crate::{
     errors::Error,
 };
 
+use super::{
+    countries::country_name,
+    icy::{IcyReader, ICY_METADATA_HEADER, ICY_METAINT_HEADER},
+    stream_probe, SearchParam,
+};
+
 use super::audio_stream::AudioStream;
 
 const TODO_HEADER_BG: Color = tailwind::BLUE.c950;
@@ -31,24 +39,67 @@ const ALT_ROW_COLOR: Color = tailwind::SLATE.c900;
 const SELECTED_STYLE_FG: Color = tailwind::BLUE.c300;
 const TEXT_COLOR: Color = tailwind::SLATE.c200;
 const COMPLETED_TEXT_COLOR: Color = tailwind::GREEN.c500;
+const LABEL_COLOR: Color = tailwind::SLATE.c400;
+const CHIP_BG: Color = tailwind::INDIGO.c800;
+const BADGE_BG: Color = tailwind::TEAL.c800;
 
 pub struct State {
     output_guard: Arc<Mutex<OutputStream>>,
     sink: Arc<Mutex<Sink>>,
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// Where a station was found, told by the prefix of its `stationuuid`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum Source {
+    #[strum(serialize = "radio-browser")]
+    RadioBrowser,
+    /// An Icecast `yp.xml` listing.
+    #[strum(serialize = "icecast")]
+    Icecast,
+    /// A Shoutcast style station list.
+    #[strum(serialize = "shoutcast")]
+    Shoutcast,
+    /// Added by the user.
+    #[strum(serialize = "custom")]
+    Custom,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RadioStation {
     pub name: String,
     pub stationuuid: String,
     pub url: String,
+    /// The stream url after following playlists and redirects, empty if unknown.
+    pub url_resolved: String,
     pub codec: String,
     pub bitrate: u32,
     pub homepage: String,
-    pub tags: String,
+    pub favicon: String,
+    pub tags: Vec<String>,
+    pub country: String,
     pub countrycode: String,
+    pub state: String,
+    pub languages: Vec<String>,
     pub languagecodes: Option<String>,
     pub votes: i32,
+    pub clickcount: u32,
+    pub clicktrend: i32,
+    /// Whether the most recent directory check of the stream succeeded.
+    pub lastcheckok: bool,
+    /// Whether the stream is HTTP Live Streaming.
+    pub hls: bool,
+    pub geo_lat: Option<f64>,
+    pub geo_long: Option<f64>,
+    /// Extra request headers for the stream, e.g. authorization, set for custom stations.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// Whether a refresh of the saved stations found that the directory no longer lists it.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub deleted: bool,
+    /// Why the stream did not play when it was last checked, if it was marked broken.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub broken: Option<String>,
 }
 
 impl RadioStation {
@@ -60,6 +111,89 @@ impl RadioStation {
             ..Default::default()
         }
     }
+
+    /// Prefix of the `stationuuid` of stations that are not in the directory.
+    pub const CUSTOM_PREFIX: &'static str = "custom:";
+
+    /// A station that is not in the directory, identified by its url.
+    pub fn custom<T: ToString>(url: T, name: T) -> Self {
+        let url = url.to_string();
+        Self {
+            name: name.to_string(),
+            stationuuid: format!("{}{url}", Self::CUSTOM_PREFIX),
+            url,
+            ..Default::default()
+        }
+    }
+
+    /// Whether the station was added by the user rather than found in the directory.
+    pub fn is_custom(&self) -> bool {
+        self.stationuuid.is_empty() || self.stationuuid.starts_with(Self::CUSTOM_PREFIX)
+    }
+
+    /// Prefix of the `stationuuid` of stations from Icecast listings, followed by their url.
+    pub const ICECAST_PREFIX: &'static str = "icecast:";
+    /// Prefix of the `stationuuid` of stations from Shoutcast listings, followed by their id.
+    pub const SHOUTCAST_PREFIX: &'static str = "shoutcast:";
+
+    pub fn source(&self) -> Source {
+        if self.is_custom() {
+            Source::Custom
+        } else if self.stationuuid.starts_with(Self::ICECAST_PREFIX) {
+            Source::Icecast
+        } else if self.stationuuid.starts_with(Self::SHOUTCAST_PREFIX) {
+            Source::Shoutcast
+        } else {
+            Source::RadioBrowser
+        }
+    }
+
+    /// Whether the directory knows the station, so that clicks and votes can be sent for it.
+    pub fn is_directory(&self) -> bool {
+        self.source() == Source::RadioBrowser
+    }
+
+    /// Whether the station matches search parameters, for stations searched locally rather than
+    /// by the directory. Parameters about details only the directory knows match nothing.
+    pub fn matches(&self, params: &[SearchParam]) -> bool {
+        let exact = params.contains(&SearchParam::NameExact(true));
+        params.iter().all(|param| match param {
+            SearchParam::Name(name) if exact => self.name.eq_ignore_ascii_case(name),
+            SearchParam::Name(name) => self.name.to_lowercase().contains(&name.to_lowercase()),
+            SearchParam::Tags(tags) => tags
+                .iter()
+                .all(|tag| self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))),
+            SearchParam::Codec(codec) => self.codec.eq_ignore_ascii_case(codec),
+            SearchParam::BitrateMin(min) => self.bitrate >= *min,
+            SearchParam::BitrateMax(max) => self.bitrate <= *max,
+            SearchParam::IsHttps(https) => self.url.starts_with("https:") == *https,
+            SearchParam::Country(_)
+            | SearchParam::CountryCode(_)
+            | SearchParam::Language(_)
+            | SearchParam::HasGeoInfo(true) => false,
+            _ => true,
+        })
+    }
+
+    /// Splits a comma separated directory field such as `tags` into its trimmed, non empty parts.
+    pub fn parse_list(value: &str) -> Vec<String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// The url to stream from, preferring the resolved url over the one submitted to the directory.
+    pub fn stream_url(&self) -> &str {
+        if self.url_resolved.is_empty() {
+            &self.url
+        } else {
+            &self.url_resolved
+        }
+    }
+
     pub async fn play(
         &mut self,
         mut download_shutdown_rx: broadcast::Receiver<()>,
@@ -67,14 +201,25 @@ impl RadioStation {
         initial_volume: f32,
         mut volume_rx: broadcast::Receiver<f32>,
         mut volume_shutdown_rx: broadcast::Receiver<()>,
+        title_tx: mpsc::UnboundedSender<String>,
     ) -> Result<(), Error> {
-        tracing::info!(station = ?self, "playing");
+        // The headers may hold credentials, keep them out of the log
+        tracing::info!(name = self.name, url = self.stream_url(), "playing");
         let client = reqwest::Client::new();
-        let mut response = client
-            .get(&self.url)
+        // Listings such as Shoutcast's link to playlists rather than to the stream
+        if self.url_resolved.is_empty() && stream_probe::is_playlist_url(&self.url) {
+            self.url_resolved = stream_probe::resolve_url(&client, &self.url)
+                .await?
+                .url_resolved;
+        }
+        let mut request = client
+            .get(self.stream_url())
             .header(header::CONNECTION, "keep-alive")
-            .send()
-            .await?;
+            .header(ICY_METADATA_HEADER, "1");
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        let mut response = request.send().await?;
 
         tracing::debug!(?response, "got response");
 
@@ -87,6 +232,16 @@ impl RadioStation {
 
         let buf = audio_stream.get_buf();
 
+        // Strip the track titles from the audio if the server interleaves them
+        let mut icy = response
+            .headers()
+            .get(ICY_METAINT_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<usize>().ok())
+            .filter(|metaint| *metaint > 0)
+            .map(IcyReader::new);
+        let mut last_title = None;
+
         tracing::info!("spawning chunker");
         let handle = tokio::spawn(async move {
             tracing::info!("getting chunks...");
@@ -98,8 +253,21 @@ impl RadioStation {
                             Ok(chunk) => {
                                 if let Some(chunk) = chunk {
                                     tracing::trace!("got chunk: {}", chunk.len());
+                                    let mut audio = Vec::with_capacity(chunk.len());
+                                    let audio = match icy.as_mut() {
+                                        Some(icy) => {
+                                            let title = icy.push(&chunk, &mut audio);
+                                            if let Some(title) = title.filter(|t| last_title.as_ref() != Some(t)) {
+                                                tracing::info!(title, "track changed");
+                                                let _ = title_tx.send(title.clone());
+                                                last_title = Some(title);
+                                            }
+                                            audio.as_slice()
+                                        }
+                                        None => chunk.as_ref(),
+                                    };
                                     let mut guard = buf.lock().expect("failed to lock buffer");
-                                    let result = guard.write(chunk.as_ref());
+                                    let result = guard.write(audio);
                                     match result {
                                         Ok(n) => tracing::trace!(bytes=?n, "pushed chunk"),
                                         Err(e) => tracing::error!(error=?e, "failed to get chunk"),
@@ -130,15 +298,37 @@ impl RadioStation {
 
         // Spin off the stream handling to a task that allows blocking. This will then not use the
         // Tokio thread pool, but instead use a CPU managed thread.
+        let (started_tx, started_rx) = tokio::sync::oneshot::channel::<Result<(), Error>>();
         tokio::task::spawn_blocking(move || {
             // This is running on a thread where blocking is fine.
             tracing::info!("streaming task spawned");
 
-            let (stream, stream_handle) = OutputStream::try_default().unwrap();
-            let sink = Sink::try_new(&stream_handle).unwrap();
-
             tracing::debug!("setting up decoder");
-            let decoder = Decoder::new_mp3(audio_stream).unwrap();
+            // The same decoder as the stream check, which tells the format from the audio
+            let decoder = match Decoder::new(audio_stream) {
+                Ok(decoder) => decoder,
+                Err(e) => {
+                    let _ = started_tx.send(Err(Error::Stream(format!(
+                        "the audio cannot be decoded: {e}"
+                    ))));
+                    return;
+                }
+            };
+            let (stream, stream_handle) = match OutputStream::try_default() {
+                Ok(output) => output,
+                Err(e) => {
+                    let _ = started_tx.send(Err(Error::Stream(e.to_string())));
+                    return;
+                }
+            };
+            let sink = match Sink::try_new(&stream_handle) {
+                Ok(sink) => sink,
+                Err(e) => {
+                    let _ = started_tx.send(Err(Error::Stream(e.to_string())));
+                    return;
+                }
+            };
+            let _ = started_tx.send(Ok(()));
             sink.append(decoder);
             sink.set_volume(initial_volume);
 
@@ -170,18 +360,115 @@ impl RadioStation {
             tracing::info!("done playing....");
         });
 
-        Ok(())
+        started_rx
+            .await
+            .map_err(|_| Error::Stream("the player stopped before playing".to_string()))?
     }
+}
+
+pub(super) fn label(text: &str) -> Span<'static> {
+    Span::styled(format!("{text:<10}"), Style::default().fg(LABEL_COLOR))
+}
 
-    pub fn to_list_item(&self, index: usize) -> ListItem {
-        let bg_color = match index % 2 {
-            0 => NORMAL_ROW_COLOR,
-            _ => ALT_ROW_COLOR,
+fn badge(text: String, bg: Color) -> Span<'static> {
+    Span::styled(format!(" {text} "), Style::default().fg(TEXT_COLOR).bg(bg))
+}
+
+impl RadioStation {
+    /// The country name resolved from `countrycode`, falling back to the directory's name.
+    pub fn country_name(&self) -> String {
+        country_name(&self.countrycode)
+            .map(str::to_string)
+            .unwrap_or_else(|| self.country.clone())
+    }
+
+    /// Renders all known metadata of the station for the details pane.
+    pub fn to_details(&self) -> Vec<Line<'static>> {
+        let mut lines = vec![
+            Line::styled(
+                self.name.clone(),
+                Style::default()
+                    .fg(SELECTED_STYLE_FG)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Line::default(),
+        ];
+
+        let mut badges = vec![];
+        if !self.codec.is_empty() {
+            badges.push(badge(self.codec.to_uppercase(), BADGE_BG));
+            badges.push(Span::raw(" "));
+        }
+        if self.bitrate > 0 {
+            badges.push(badge(format!("{} kbps", self.bitrate), BADGE_BG));
+            badges.push(Span::raw(" "));
+        }
+        if self.hls {
+            badges.push(badge("HLS".to_string(), BADGE_BG));
+            badges.push(Span::raw(" "));
+        }
+        let (status, status_color) = if self.deleted {
+            (
+                "● deleted from the directory".to_string(),
+                tailwind::RED.c500,
+            )
+        } else if let Some(reason) = &self.broken {
+            (format!("● broken: {reason}"), tailwind::RED.c500)
+        } else if !self.is_directory() {
+            // Only the directory checks streams, other stations never pass its check
+            ("● not checked".to_string(), LABEL_COLOR)
+        } else if self.lastcheckok {
+            ("● online".to_string(), COMPLETED_TEXT_COLOR)
+        } else {
+            ("● failing".to_string(), tailwind::RED.c500)
         };
-        let line = Line::styled(format!(" * {} - {}", self.name, self.url), TEXT_COLOR);
+        badges.push(Span::styled(status, Style::default().fg(status_color)));
+        lines.push(Line::from(badges));
+        lines.push(Line::default());
+
+        let country = self.country_name();
+        if !country.is_empty() {
+            let mut place = format!("{country} ({})", self.countrycode);
+            if !self.state.is_empty() {
+                place = format!("{}, {place}", self.state);
+            }
+            lines.push(Line::from(vec![label("Country"), Span::raw(place)]));
+        }
+        if !self.languages.is_empty() {
+            lines.push(Line::from(vec![
+                label("Language"),
+                Span::raw(self.languages.join(", ")),
+            ]));
+        }
+        lines.push(Line::from(vec![
+            label("Votes"),
+            Span::raw(format!(
+                "{}  clicks {} ({:+})",
+                self.votes, self.clickcount, self.clicktrend
+            )),
+        ]));
+        if !self.homepage.is_empty() {
+            lines.push(Line::from(vec![
+                label("Homepage"),
+                Span::raw(self.homepage.clone()),
+            ]));
+        }
+        lines.push(Line::from(vec![
+            label("Stream"),
+            Span::raw(self.stream_url().to_string()),
+        ]));
+
+        if !self.tags.is_empty() {
+            lines.push(Line::default());
+            let mut chips = vec![];
+            for tag in self.tags.iter() {
+                chips.push(badge(tag.clone(), CHIP_BG));
+                chips.push(Span::raw(" "));
+            }
+            lines.push(Line::from(chips));
+        }
 
-        let list_item = ListItem::new(line);
-        list_item.bg(bg_color)
+        lines
     }
 }
 
@@ -191,13 +478,27 @@ impl From<ApiStation> for RadioStation {
             name: value.name,
             stationuuid: value.stationuuid,
             url: value.url,
+            url_resolved: value.url_resolved,
             codec: value.codec,
             bitrate: value.bitrate,
             homepage: value.homepage,
-            tags: value.tags,
+            favicon: value.favicon,
+            tags: Self::parse_list(&value.tags),
+            country: value.country,
             countrycode: value.countrycode,
+            state: value.state,
+            languages: Self::parse_list(&value.language),
             languagecodes: value.languagecodes,
             votes: value.votes,
+            clickcount: value.clickcount,
+            clicktrend: value.clicktrend,
+            lastcheckok: value.lastcheckok == 1,
+            hls: value.hls == 1,
+            geo_lat: value.geo_lat,
+            geo_long: value.geo_long,
+            headers: BTreeMap::new(),
+            deleted: false,
+            broken: None,
         }
     }
 }
@@ -0,0 +1,399 @@
+//! Directory responses kept on disk, so that repeated searches and lookups do not wait for the
+//! network.
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Order, RadioStation, SearchParam, DEFAULT_SEARCH_LIMIT};
+use crate::{errors::Error, utils::write_atomic};
+
+/// How long responses stay fresh and how much the cache holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheLimits {
+    /// Responses older than this are stale: still used, but refreshed in the background.
+    pub ttl: Duration,
+    /// The most responses kept, `0` keeps none.
+    pub max_entries: usize,
+    /// The most bytes of responses kept, `0` keeps none.
+    pub max_bytes: usize,
+}
+
+impl Default for CacheLimits {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(60 * 60),
+            max_entries: 500,
+            max_bytes: 20 * 1024 * 1024,
+        }
+    }
+}
+
+/// The answer of [`ResponseCache::get`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lookup {
+    Fresh(Vec<RadioStation>),
+    /// The response is older than the TTL. `refresh` is `true` for the first caller, who should
+    /// fetch it again, see [`ResponseCache::insert`] and [`ResponseCache::refresh_failed`].
+    Stale {
+        stations: Vec<RadioStation>,
+        refresh: bool,
+    },
+    Missing,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    /// Seconds since the unix epoch when the response arrived.
+    stored_at: u64,
+    /// Seconds since the unix epoch when the response was last used, the least recently used
+    /// responses are dropped first.
+    used_at: u64,
+    /// The size of the response as JSON, counted against [`CacheLimits::max_bytes`].
+    size: usize,
+    stations: Vec<RadioStation>,
+}
+
+/// Directory responses by a normalized key of their request, see [`ResponseCache::key`].
+///
+/// Persisted as JSON in the data directory.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResponseCache {
+    /// The version of the file format, see [`ResponseCache::VERSION`].
+    version: u32,
+    entries: HashMap<String, Entry>,
+    #[serde(skip)]
+    limits: CacheLimits,
+    /// The keys of the stale responses being refreshed.
+    #[serde(skip)]
+    refreshing: HashSet<String>,
+    /// Whether responses were stored since the cache was last saved.
+    #[serde(skip)]
+    dirty: bool,
+    /// Whether a save in the background is writing the changes, see
+    /// [`ResponseCache::begin_save`].
+    #[serde(skip)]
+    saving: bool,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            entries: HashMap::new(),
+            limits: CacheLimits::default(),
+            refreshing: HashSet::new(),
+            dirty: false,
+            saving: false,
+            path: PathBuf::new(),
+        }
+    }
+}
+
+impl Drop for ResponseCache {
+    /// Saves the responses no background save wrote yet, e.g. when the program exits.
+    fn drop(&mut self) {
+        if self.dirty {
+            if let Err(e) = self.save() {
+                tracing::warn!(error = ?e, "failed to save the response cache");
+            }
+        }
+    }
+}
+
+impl ResponseCache {
+    pub const FILE_NAME: &'static str = "responses.json";
+    /// The newest file format this build reads.
+    pub const VERSION: u32 = 1;
+
+    /// Loads the cache from `path`, starting an empty one if the file does not exist yet.
+    pub fn load(path: &Path, limits: CacheLimits) -> Result<Self, Error> {
+        let mut cache = match std::fs::read(path) {
+            Ok(data) => serde_json::from_slice::<Self>(&data)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e.into()),
+        };
+        if cache.version > Self::VERSION {
+            return Err(Error::UnsupportedVersion {
+                file: path.display().to_string(),
+                version: cache.version,
+                supported: Self::VERSION,
+            });
+        }
+        cache.version = Self::VERSION;
+        cache.limits = limits;
+        cache.path = path.to_path_buf();
+        Ok(cache)
+    }
+
+    pub fn save(&mut self) -> Result<(), Error> {
+        self.dirty = false;
+        write_atomic(&self.path, &serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+
+    /// Whether the caller should start saving the changes in the background, because there
+    /// are some and no save is writing them yet. It then calls
+    /// [`ResponseCache::take_changes`] until there are none.
+    pub fn begin_save(&mut self) -> bool {
+        if !self.dirty || self.saving {
+            return false;
+        }
+        self.saving = true;
+        true
+    }
+
+    /// The path and contents to write if responses were stored since the last call, `None`
+    /// once there are no more changes, which ends the save.
+    pub fn take_changes(&mut self) -> Option<Result<(PathBuf, Vec<u8>), Error>> {
+        if !self.dirty {
+            self.saving = false;
+            return None;
+        }
+        self.dirty = false;
+        match serde_json::to_vec(self) {
+            Ok(data) => Some(Ok((self.path.clone(), data))),
+            Err(e) => {
+                self.saving = false;
+                Some(Err(e.into()))
+            }
+        }
+    }
+
+    /// The key of a request to `endpoint`. Requests that ask the same, like ones listing the
+    /// same parameters in another order, with other case or spelling out the defaults, share a
+    /// key.
+    pub fn key(endpoint: &str, params: &[SearchParam]) -> String {
+        let mut parts: Vec<String> = params
+            .iter()
+            .filter_map(|param| {
+                let part = match param {
+                    SearchParam::Name(name) => format!("name={}", name.trim().to_lowercase()),
+                    SearchParam::Country(country) => {
+                        format!("country={}", country.trim().to_lowercase())
+                    }
+                    SearchParam::Language(language) => {
+                        format!("language={}", language.trim().to_lowercase())
+                    }
+                    SearchParam::Tags(tags) => {
+                        let mut tags: Vec<String> =
+                            tags.iter().map(|t| t.trim().to_lowercase()).collect();
+                        tags.sort();
+                        tags.dedup();
+                        format!("tags={}", tags.join(","))
+                    }
+                    SearchParam::Codec(codec) => format!("codec={}", codec.trim().to_lowercase()),
+                    SearchParam::CountryCode(code) => {
+                        format!("countrycode={}", code.trim().to_lowercase())
+                    }
+                    SearchParam::BitrateMin(min) => format!("bitratemin={min}"),
+                    SearchParam::BitrateMax(max) => format!("bitratemax={max}"),
+                    SearchParam::NameExact(false) | SearchParam::BypassCache(_) => return None,
+                    SearchParam::NameExact(true) => "exact".to_string(),
+                    SearchParam::IsHttps(https) => format!("https={https}"),
+                    SearchParam::HasGeoInfo(geo) => format!("geo={geo}"),
+                    // Filled in below, the last one given counts
+                    SearchParam::Limit(_) | SearchParam::Order(_) | SearchParam::Reverse(_) => {
+                        return None
+                    }
+                };
+                Some(part)
+            })
+            .collect();
+        let (mut limit, mut order, mut reverse) = (DEFAULT_SEARCH_LIMIT, Order::Votes, true);
+        for param in params {
+            match param {
+                SearchParam::Limit(l) => limit = *l,
+                SearchParam::Order(o) => order = *o,
+                SearchParam::Reverse(r) => reverse = *r,
+                _ => (),
+            }
+        }
+        parts.push(format!("limit={limit}"));
+        parts.push(format!("order={order:?}").to_lowercase());
+        parts.push(format!("reverse={reverse}"));
+        parts.sort();
+        parts.dedup();
+        format!("{endpoint}?{}", parts.join("&"))
+    }
+
+    /// The response for `key`, if there is one.
+    pub fn get(&mut self, key: &str, now: u64) -> Lookup {
+        let Some(entry) = self.entries.get_mut(key) else {
+            return Lookup::Missing;
+        };
+        entry.used_at = now;
+        let stations = entry.stations.clone();
+        if now.saturating_sub(entry.stored_at) < self.limits.ttl.as_secs() {
+            return Lookup::Fresh(stations);
+        }
+        Lookup::Stale {
+            stations,
+            refresh: self.refreshing.insert(key.to_string()),
+        }
+    }
+
+    /// Stores a response, dropping the least recently used ones beyond the limits. The cache is
+    /// saved later, see [`ResponseCache::begin_save`].
+    pub fn insert(
+        &mut self,
+        key: &str,
+        stations: Vec<RadioStation>,
+        now: u64,
+    ) -> Result<(), Error> {
+        self.refreshing.remove(key);
+        let size = serde_json::to_vec(&stations)?.len();
+        self.entries.insert(
+            key.to_string(),
+            Entry {
+                stored_at: now,
+                used_at: now,
+                size,
+                stations,
+            },
+        );
+        self.evict();
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// Lets the next caller of [`ResponseCache::get`] try to refresh the stale response again.
+    pub fn refresh_failed(&mut self, key: &str) {
+        self.refreshing.remove(key);
+    }
+
+    fn evict(&mut self) {
+        let mut total: usize = self.entries.values().map(|e| e.size).sum();
+        let mut by_use: Vec<(u64, String)> = self
+            .entries
+            .iter()
+            .map(|(key, entry)| (entry.used_at, key.clone()))
+            .collect();
+        by_use.sort();
+        for (_, key) in by_use {
+            if self.entries.len() <= self.limits.max_entries && total <= self.limits.max_bytes {
+                break;
+            }
+            if let Some(entry) = self.entries.remove(&key) {
+                total -= entry.size;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn stations(name: &str) -> Vec<RadioStation> {
+        vec![RadioStation::new("http://a.example", "uuid", name)]
+    }
+
+    #[test]
+    fn test_key_is_normalized() {
+        let key = ResponseCache::key(
+            "stations",
+            &[
+                SearchParam::Tags(vec!["Rock".to_string(), "jazz".to_string()]),
+                SearchParam::Name(" Radio ".to_string()),
+                SearchParam::BypassCache(true),
+            ],
+        );
+        assert_eq!(
+            key,
+            ResponseCache::key(
+                "stations",
+                &[
+                    SearchParam::Name("radio".to_string()),
+                    SearchParam::Tags(vec!["jazz".to_string(), "rock".to_string()]),
+                    SearchParam::Limit(DEFAULT_SEARCH_LIMIT),
+                    SearchParam::Order(Order::Votes),
+                    SearchParam::NameExact(false),
+                ]
+            )
+        );
+        assert_eq!(
+            key,
+            "stations?limit=30&name=radio&order=votes&reverse=true&tags=jazz,rock"
+        );
+        assert_ne!(
+            key,
+            ResponseCache::key("stations", &[SearchParam::Name("radio".to_string())])
+        );
+    }
+
+    #[test]
+    fn test_fresh_then_stale_while_refreshing() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(ResponseCache::FILE_NAME);
+        let limits = CacheLimits {
+            ttl: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let mut cache = ResponseCache::load(&path, limits)?;
+        assert_eq!(cache.get("k", 1000), Lookup::Missing);
+        cache.insert("k", stations("old"), 1000)?;
+        assert_eq!(cache.get("k", 1059), Lookup::Fresh(stations("old")));
+
+        // One save writes the changes until there are none
+        assert!(cache.begin_save());
+        assert!(!cache.begin_save());
+        let (written_to, data) = cache.take_changes().expect("changes")?;
+        assert_eq!(written_to, path);
+        write_atomic(&path, &data)?;
+        assert!(cache.take_changes().is_none());
+        assert!(!cache.begin_save());
+
+        let mut cache = ResponseCache::load(&path, limits)?;
+        assert_eq!(
+            cache.get("k", 1060),
+            Lookup::Stale {
+                stations: stations("old"),
+                refresh: true
+            }
+        );
+        // Only the first caller refreshes, until the refresh is done or failed
+        assert!(matches!(
+            cache.get("k", 1061),
+            Lookup::Stale { refresh: false, .. }
+        ));
+        cache.refresh_failed("k");
+        assert!(matches!(
+            cache.get("k", 1062),
+            Lookup::Stale { refresh: true, .. }
+        ));
+        cache.insert("k", stations("new"), 1063)?;
+        assert_eq!(cache.get("k", 1064), Lookup::Fresh(stations("new")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("evict-responses.json");
+        let limits = CacheLimits {
+            max_entries: 2,
+            ..Default::default()
+        };
+        let mut cache = ResponseCache::load(&path, limits)?;
+        cache.insert("a", stations("a"), 1)?;
+        cache.insert("b", stations("b"), 2)?;
+        cache.get("a", 3);
+        cache.insert("c", stations("c"), 4)?;
+        assert_eq!(cache.get("b", 5), Lookup::Missing);
+        assert!(matches!(cache.get("a", 5), Lookup::Fresh(_)));
+
+        let size = cache.entries["c"].size;
+        cache.limits.max_bytes = size;
+        cache.insert("d", stations("d"), 6)?;
+        assert_eq!(cache.entries.keys().collect::<Vec<_>>(), vec!["d"]);
+        Ok(())
+    }
+}
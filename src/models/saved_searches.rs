@@ -0,0 +1,170 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::SearchParam;
+use crate::{errors::Error, utils::write_atomic};
+
+/// A search the user saved under a name to run it again later.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub name: String,
+    pub params: Vec<SearchParam>,
+}
+
+/// The recent searches and the saved searches.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedSearches {
+    /// The version of the file format, see [`SavedSearches::VERSION`].
+    version: u32,
+    /// The last searches run, newest first.
+    recent: Vec<Vec<SearchParam>>,
+    saved: Vec<SavedSearch>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Default for SavedSearches {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            recent: Vec::new(),
+            saved: Vec::new(),
+            path: PathBuf::new(),
+        }
+    }
+}
+
+impl SavedSearches {
+    pub const FILE_NAME: &'static str = "searches.json";
+    /// The newest file format this build reads.
+    pub const VERSION: u32 = 1;
+
+    /// Loads the searches from `path`, starting with none if the file does not exist yet.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut searches = match std::fs::read(path) {
+            Ok(data) => serde_json::from_slice::<Self>(&data)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e.into()),
+        };
+        if searches.version > Self::VERSION {
+            return Err(Error::UnsupportedVersion {
+                file: path.display().to_string(),
+                version: searches.version,
+                supported: Self::VERSION,
+            });
+        }
+        searches.version = Self::VERSION;
+        searches.path = path.to_path_buf();
+        Ok(searches)
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        write_atomic(&self.path, &serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The last searches run, newest first.
+    pub fn recent(&self) -> &[Vec<SearchParam>] {
+        &self.recent
+    }
+
+    /// Remembers `params` as the newest search, keeping at most `max` searches.
+    ///
+    /// Running the same search again moves it to the front instead of adding it twice.
+    pub fn push_recent(&mut self, params: Vec<SearchParam>, max: usize) -> Result<(), Error> {
+        if params.is_empty() || max == 0 {
+            return Ok(());
+        }
+        self.recent.retain(|recent| *recent != params);
+        self.recent.insert(0, params);
+        self.recent.truncate(max);
+        self.save()
+    }
+
+    pub fn saved(&self) -> &[SavedSearch] {
+        &self.saved
+    }
+
+    /// Saves `params` under `name`, replacing a saved search of the same name.
+    pub fn save_search(&mut self, name: &str, params: Vec<SearchParam>) -> Result<(), Error> {
+        let search = SavedSearch {
+            name: name.trim().to_string(),
+            params,
+        };
+        match self.saved.iter_mut().find(|s| s.name == search.name) {
+            Some(existing) => *existing = search,
+            None => self.saved.push(search),
+        }
+        self.save()
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<(), Error> {
+        self.saved.retain(|s| s.name != name);
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn name(name: &str) -> Vec<SearchParam> {
+        vec![SearchParam::Name(name.to_string())]
+    }
+
+    #[test]
+    fn test_recent_dedupes_and_truncates() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(SavedSearches::FILE_NAME);
+        let mut searches = SavedSearches::load(&path)?;
+        searches.push_recent(name("a"), 2)?;
+        searches.push_recent(name("b"), 2)?;
+        searches.push_recent(name("a"), 2)?;
+        searches.push_recent(vec![], 2)?;
+        assert_eq!(searches.recent(), &[name("a"), name("b")]);
+        searches.push_recent(name("c"), 2)?;
+        assert_eq!(
+            SavedSearches::load(&path)?.recent(),
+            &[name("c"), name("a")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_search_replaces_by_name() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(SavedSearches::FILE_NAME);
+        let mut searches = SavedSearches::load(&path)?;
+        searches.save_search("jazz", name("jazz"))?;
+        searches.save_search(" jazz ", name("smooth jazz"))?;
+        searches.save_search("rock", name("rock"))?;
+        assert_eq!(searches.saved().len(), 2);
+        assert_eq!(searches.saved()[0].params, name("smooth jazz"));
+
+        searches.remove("jazz")?;
+        let names: Vec<String> = SavedSearches::load(&path)?
+            .saved()
+            .iter()
+            .map(|s| s.name.clone())
+            .collect();
+        assert_eq!(names, vec!["rock"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("searches-version.json");
+        std::fs::write(&path, r#"{"version": 99, "recent": [], "saved": []}"#)?;
+        assert!(matches!(
+            SavedSearches::load(&path),
+            Err(Error::UnsupportedVersion { version: 99, .. })
+        ));
+        Ok(())
+    }
+}
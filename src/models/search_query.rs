@@ -0,0 +1,676 @@
+//! A single-line query language for station searches.
+//!
+//! Free words are joined into a name search, everything else is a `field:value` pair:
+//!
+//! ```text
+//! jazz tag:smooth country:DE codec:aac bitrate>=128 order:votes limit:100
+//! ```
+//!
+//! Values containing spaces can be quoted, e.g. `tag:"smooth jazz"`. The `bitrate` field also
+//! accepts the comparison operators `>=`, `>`, `<=` and `<`. `fresh:true` asks the directory
+//! even if the response is cached.
+use std::fmt;
+
+use strum::VariantNames;
+
+use super::{Order, SearchParam};
+
+/// The fields understood by [`parse_query`], including their aliases.
+const FIELDS: [(&str, Field); 18] = [
+    ("name", Field::Name),
+    ("tag", Field::Tags),
+    ("tags", Field::Tags),
+    ("country", Field::Country),
+    ("countrycode", Field::CountryCode),
+    ("cc", Field::CountryCode),
+    ("language", Field::Language),
+    ("lang", Field::Language),
+    ("codec", Field::Codec),
+    ("bitrate", Field::Bitrate),
+    ("limit", Field::Limit),
+    ("order", Field::Order),
+    ("reverse", Field::Reverse),
+    ("exact", Field::NameExact),
+    ("https", Field::IsHttps),
+    ("geo", Field::HasGeoInfo),
+    ("hasgeo", Field::HasGeoInfo),
+    ("fresh", Field::BypassCache),
+];
+
+/// The maximum number of hints returned by [`query_hints`].
+const MAX_HINTS: usize = 8;
+
+/// Errors produced while parsing a search query.
+///
+/// All columns are 1-based character offsets into the query.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum QueryError {
+    #[error("unterminated quote starting at column {column}")]
+    UnterminatedQuote { column: usize },
+    #[error("unknown field `{field}` at column {column}")]
+    UnknownField { field: String, column: usize },
+    #[error("missing value for `{field}` at column {column}")]
+    MissingValue { field: String, column: usize },
+    #[error("invalid value `{value}` for `{field}` at column {column}, expected {expected}")]
+    InvalidValue {
+        field: String,
+        value: String,
+        column: usize,
+        expected: &'static str,
+    },
+    #[error("`{field}` does not support `{operator}` at column {column}")]
+    UnsupportedOperator {
+        field: String,
+        operator: Operator,
+        column: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Name,
+    Tags,
+    Country,
+    CountryCode,
+    Language,
+    Codec,
+    Bitrate,
+    Limit,
+    Order,
+    Reverse,
+    NameExact,
+    IsHttps,
+    HasGeoInfo,
+    BypassCache,
+}
+
+impl Field {
+    fn lookup(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        FIELDS
+            .iter()
+            .find(|(alias, _)| *alias == name)
+            .map(|(_, field)| *field)
+    }
+
+    fn is_bool(&self) -> bool {
+        matches!(
+            self,
+            Field::Reverse
+                | Field::NameExact
+                | Field::IsHttps
+                | Field::HasGeoInfo
+                | Field::BypassCache
+        )
+    }
+}
+
+/// The operator separating a field from its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            Operator::Eq => ":",
+            Operator::Gt => ">",
+            Operator::Ge => ">=",
+            Operator::Lt => "<",
+            Operator::Le => "<=",
+        };
+        f.write_str(op)
+    }
+}
+
+/// A whitespace separated piece of the query with quotes removed.
+#[derive(Debug, PartialEq, Eq)]
+struct Token {
+    text: String,
+    /// 1-based column of the first character of the token.
+    column: usize,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut current: Option<Token> = None;
+    let mut quote_start: Option<usize> = None;
+
+    for (i, c) in input.chars().enumerate() {
+        let column = i + 1;
+        match c {
+            '"' => {
+                quote_start = match quote_start {
+                    Some(_) => None,
+                    None => Some(column),
+                };
+                current.get_or_insert(Token {
+                    text: String::new(),
+                    column,
+                });
+            }
+            c if c.is_whitespace() && quote_start.is_none() => {
+                if let Some(token) = current.take() {
+                    tokens.push(token);
+                }
+            }
+            c => current
+                .get_or_insert(Token {
+                    text: String::new(),
+                    column,
+                })
+                .text
+                .push(c),
+        }
+    }
+
+    if let Some(column) = quote_start {
+        return Err(QueryError::UnterminatedQuote { column });
+    }
+    if let Some(token) = current.take() {
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// Splits `field:value` style tokens into their parts. Returns `None` for free words.
+fn split_field(text: &str) -> Option<(&str, Operator, &str)> {
+    let idx = text.find([':', '=', '<', '>'])?;
+    let (field, rest) = text.split_at(idx);
+    if field.is_empty() || !field.chars().all(|c| c.is_ascii_alphabetic() || c == '_') {
+        return None;
+    }
+
+    let (operator, len) = if rest.starts_with(">=") {
+        (Operator::Ge, 2)
+    } else if rest.starts_with("<=") {
+        (Operator::Le, 2)
+    } else if rest.starts_with('>') {
+        (Operator::Gt, 1)
+    } else if rest.starts_with('<') {
+        (Operator::Lt, 1)
+    } else {
+        (Operator::Eq, 1)
+    };
+
+    Some((field, operator, &rest[len..]))
+}
+
+fn parse_bool(field: &str, value: &str, column: usize) -> Result<bool, QueryError> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => Err(QueryError::InvalidValue {
+            field: field.to_string(),
+            value: value.to_string(),
+            column,
+            expected: "true or false",
+        }),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(
+    field: &str,
+    value: &str,
+    column: usize,
+) -> Result<T, QueryError> {
+    value.parse::<T>().map_err(|_| QueryError::InvalidValue {
+        field: field.to_string(),
+        value: value.to_string(),
+        column,
+        expected: "a positive number",
+    })
+}
+
+fn parse_order(field: &str, value: &str, column: usize) -> Result<Order, QueryError> {
+    Order::VARIANTS
+        .iter()
+        .find(|variant| variant.eq_ignore_ascii_case(value))
+        .and_then(|variant| variant.parse::<Order>().ok())
+        .ok_or_else(|| QueryError::InvalidValue {
+            field: field.to_string(),
+            value: value.to_string(),
+            column,
+            expected: "a sort order such as votes, name or bitrate",
+        })
+}
+
+/// Parses a single-line search query into the equivalent [`SearchParam`]s.
+///
+/// # Errors
+///
+/// Returns a [`QueryError`] describing the first token that could not be parsed.
+pub fn parse_query(input: &str) -> Result<Vec<SearchParam>, QueryError> {
+    let mut params = Vec::new();
+    let mut words = Vec::new();
+    let mut tags = Vec::new();
+
+    for token in tokenize(input)? {
+        let Some((name, operator, value)) = split_field(&token.text) else {
+            words.push(token.text);
+            continue;
+        };
+
+        let field = Field::lookup(name).ok_or_else(|| QueryError::UnknownField {
+            field: name.to_string(),
+            column: token.column,
+        })?;
+        let value_column = token.column + name.chars().count() + operator.to_string().len();
+
+        if value.is_empty() {
+            return Err(QueryError::MissingValue {
+                field: name.to_string(),
+                column: value_column,
+            });
+        }
+        if operator != Operator::Eq && field != Field::Bitrate {
+            return Err(QueryError::UnsupportedOperator {
+                field: name.to_string(),
+                operator,
+                column: token.column + name.chars().count(),
+            });
+        }
+
+        let param = match field {
+            Field::Name => SearchParam::Name(value.to_string()),
+            Field::Tags => {
+                tags.extend(
+                    value
+                        .split(',')
+                        .map(|tag| tag.trim().to_string())
+                        .filter(|tag| !tag.is_empty()),
+                );
+                continue;
+            }
+            Field::Country => SearchParam::Country(value.to_string()),
+            Field::CountryCode => SearchParam::CountryCode(value.to_uppercase()),
+            Field::Language => SearchParam::Language(value.to_string()),
+            Field::Codec => SearchParam::Codec(value.to_string()),
+            Field::Limit => SearchParam::Limit(parse_number(name, value, value_column)?),
+            Field::Order => SearchParam::Order(parse_order(name, value, value_column)?),
+            Field::Reverse => SearchParam::Reverse(parse_bool(name, value, value_column)?),
+            Field::NameExact => SearchParam::NameExact(parse_bool(name, value, value_column)?),
+            Field::IsHttps => SearchParam::IsHttps(parse_bool(name, value, value_column)?),
+            Field::HasGeoInfo => SearchParam::HasGeoInfo(parse_bool(name, value, value_column)?),
+            Field::BypassCache => SearchParam::BypassCache(parse_bool(name, value, value_column)?),
+            Field::Bitrate => {
+                let bitrate: u32 = parse_number(name, value, value_column)?;
+                match operator {
+                    Operator::Eq => {
+                        params.push(SearchParam::BitrateMin(bitrate));
+                        SearchParam::BitrateMax(bitrate)
+                    }
+                    Operator::Ge => SearchParam::BitrateMin(bitrate),
+                    Operator::Gt => SearchParam::BitrateMin(bitrate.saturating_add(1)),
+                    Operator::Le => SearchParam::BitrateMax(bitrate),
+                    Operator::Lt => SearchParam::BitrateMax(bitrate.checked_sub(1).ok_or(
+                        QueryError::InvalidValue {
+                            field: name.to_string(),
+                            value: value.to_string(),
+                            column: value_column,
+                            expected: "a bitrate above 0",
+                        },
+                    )?),
+                }
+            }
+        };
+        params.push(param);
+    }
+
+    if !tags.is_empty() {
+        params.insert(0, SearchParam::Tags(tags));
+    }
+    if !words.is_empty() {
+        params.insert(0, SearchParam::Name(words.join(" ")));
+    }
+
+    Ok(params)
+}
+
+/// Quotes `value` if it contains whitespace. Quotes inside values cannot be expressed and are
+/// dropped.
+fn quote(value: &str) -> String {
+    let value = value.replace('"', "");
+    if value.contains(char::is_whitespace) {
+        format!("\"{value}\"")
+    } else {
+        value
+    }
+}
+
+/// Formats `params` as a query that [`parse_query`] turns back into the same parameters.
+pub fn to_query(params: &[SearchParam]) -> String {
+    params
+        .iter()
+        .map(|param| match param {
+            // Plain words are shorter, unless they would be mistaken for fields
+            SearchParam::Name(name)
+                if name.split_whitespace().all(|w| split_field(w).is_none()) =>
+            {
+                quote(name)
+            }
+            SearchParam::Name(name) => format!("name:{}", quote(name)),
+            SearchParam::Tags(tags) => format!("tag:{}", quote(&tags.join(","))),
+            SearchParam::Country(country) => format!("country:{}", quote(country)),
+            SearchParam::CountryCode(code) => format!("countrycode:{}", quote(code)),
+            SearchParam::Language(language) => format!("language:{}", quote(language)),
+            SearchParam::Codec(codec) => format!("codec:{}", quote(codec)),
+            SearchParam::BitrateMin(bitrate) => format!("bitrate>={bitrate}"),
+            SearchParam::BitrateMax(bitrate) => format!("bitrate<={bitrate}"),
+            SearchParam::Limit(limit) => format!("limit:{limit}"),
+            SearchParam::Order(order) => format!("order:{}", format!("{order:?}").to_lowercase()),
+            SearchParam::Reverse(reverse) => format!("reverse:{reverse}"),
+            SearchParam::NameExact(exact) => format!("exact:{exact}"),
+            SearchParam::IsHttps(https) => format!("https:{https}"),
+            SearchParam::HasGeoInfo(geo) => format!("geo:{geo}"),
+            SearchParam::BypassCache(bypass) => format!("fresh:{bypass}"),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Returns completions for the last, partially typed token of `input`.
+///
+/// Each hint is the full replacement for that token, e.g. `order:votes` for `order:vo`.
+pub fn query_hints(input: &str) -> Vec<String> {
+    if input.is_empty() || input.ends_with(char::is_whitespace) {
+        return Vec::new();
+    }
+    let last = input.split_whitespace().last().unwrap_or_default();
+
+    let hints: Vec<String> = match split_field(last) {
+        None => {
+            let prefix = last.to_ascii_lowercase();
+            FIELDS
+                .iter()
+                .filter(|(alias, _)| alias.starts_with(&prefix))
+                .map(|(alias, _)| format!("{alias}:"))
+                .collect()
+        }
+        Some((name, operator, value)) => {
+            let Some(field) = Field::lookup(name) else {
+                return Vec::new();
+            };
+            let value = value.to_ascii_lowercase();
+            let candidates: Vec<String> = if field == Field::Order {
+                Order::VARIANTS
+                    .iter()
+                    .map(|variant| variant.to_ascii_lowercase())
+                    .collect()
+            } else if field.is_bool() {
+                vec!["true".to_string(), "false".to_string()]
+            } else {
+                Vec::new()
+            };
+            candidates
+                .into_iter()
+                .filter(|candidate| candidate.starts_with(&value) && *candidate != value)
+                .map(|candidate| format!("{name}{operator}{candidate}"))
+                .collect()
+        }
+    };
+
+    hints.into_iter().take(MAX_HINTS).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_empty_query() {
+        assert_eq!(parse_query("").unwrap(), vec![]);
+        assert_eq!(parse_query("   ").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_free_words_become_name() {
+        assert_eq!(
+            parse_query("smooth  jazz").unwrap(),
+            vec![SearchParam::Name("smooth jazz".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_full_example() {
+        assert_eq!(
+            parse_query("jazz tag:smooth country:DE codec:aac bitrate>=128 order:votes limit:100")
+                .unwrap(),
+            vec![
+                SearchParam::Name("jazz".to_string()),
+                SearchParam::Tags(vec!["smooth".to_string()]),
+                SearchParam::Country("DE".to_string()),
+                SearchParam::Codec("aac".to_string()),
+                SearchParam::BitrateMin(128),
+                SearchParam::Order(Order::Votes),
+                SearchParam::Limit(100),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_quoted_values() {
+        assert_eq!(
+            parse_query(r#"tag:"smooth jazz" "radio paradise""#).unwrap(),
+            vec![
+                SearchParam::Name("radio paradise".to_string()),
+                SearchParam::Tags(vec!["smooth jazz".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tags_accumulate() {
+        assert_eq!(
+            parse_query("tag:jazz,blues tags:soul").unwrap(),
+            vec![SearchParam::Tags(vec![
+                "jazz".to_string(),
+                "blues".to_string(),
+                "soul".to_string()
+            ])]
+        );
+    }
+
+    #[test]
+    fn test_field_aliases_and_case() {
+        assert_eq!(
+            parse_query("CC:de Lang:german").unwrap(),
+            vec![
+                SearchParam::CountryCode("DE".to_string()),
+                SearchParam::Language("german".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_equals_is_same_as_colon() {
+        assert_eq!(
+            parse_query("limit=5").unwrap(),
+            parse_query("limit:5").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_bitrate_operators() {
+        assert_eq!(
+            parse_query("bitrate:128").unwrap(),
+            vec![SearchParam::BitrateMin(128), SearchParam::BitrateMax(128)]
+        );
+        assert_eq!(
+            parse_query("bitrate>128").unwrap(),
+            vec![SearchParam::BitrateMin(129)]
+        );
+        assert_eq!(
+            parse_query("bitrate<=320").unwrap(),
+            vec![SearchParam::BitrateMax(320)]
+        );
+        assert_eq!(
+            parse_query("bitrate<320").unwrap(),
+            vec![SearchParam::BitrateMax(319)]
+        );
+    }
+
+    #[test]
+    fn test_bitrate_below_zero() {
+        assert_eq!(
+            parse_query("bitrate<0").unwrap_err(),
+            QueryError::InvalidValue {
+                field: "bitrate".to_string(),
+                value: "0".to_string(),
+                column: 9,
+                expected: "a bitrate above 0",
+            }
+        );
+    }
+
+    #[test]
+    fn test_booleans() {
+        assert_eq!(
+            parse_query("https:yes geo:false exact:1 reverse:off fresh:true").unwrap(),
+            vec![
+                SearchParam::IsHttps(true),
+                SearchParam::HasGeoInfo(false),
+                SearchParam::NameExact(true),
+                SearchParam::Reverse(false),
+                SearchParam::BypassCache(true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_order_is_case_insensitive() {
+        assert_eq!(
+            parse_query("order:RecentTrend").unwrap(),
+            parse_query("order:recenttrend").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        let err = parse_query("jazz foo:bar").unwrap_err();
+        assert_eq!(
+            err,
+            QueryError::UnknownField {
+                field: "foo".to_string(),
+                column: 6,
+            }
+        );
+        assert_eq!(err.to_string(), "unknown field `foo` at column 6");
+    }
+
+    #[test]
+    fn test_missing_value() {
+        assert_eq!(
+            parse_query("codec:").unwrap_err(),
+            QueryError::MissingValue {
+                field: "codec".to_string(),
+                column: 7,
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        assert!(matches!(
+            parse_query("limit:lots").unwrap_err(),
+            QueryError::InvalidValue { column: 7, .. }
+        ));
+        assert!(matches!(
+            parse_query("https:maybe").unwrap_err(),
+            QueryError::InvalidValue { column: 7, .. }
+        ));
+        assert_eq!(
+            parse_query("order:loudest").unwrap_err().to_string(),
+            "invalid value `loudest` for `order` at column 7, expected a sort order such as votes, name or bitrate"
+        );
+    }
+
+    #[test]
+    fn test_unsupported_operator() {
+        assert_eq!(
+            parse_query("limit>=5").unwrap_err(),
+            QueryError::UnsupportedOperator {
+                field: "limit".to_string(),
+                operator: Operator::Ge,
+                column: 6,
+            }
+        );
+    }
+
+    #[test]
+    fn test_unterminated_quote() {
+        assert_eq!(
+            parse_query(r#"jazz tag:"smooth"#).unwrap_err(),
+            QueryError::UnterminatedQuote { column: 10 }
+        );
+    }
+
+    #[test]
+    fn test_words_with_symbols_are_names() {
+        assert_eq!(
+            parse_query("80s:hits").unwrap(),
+            vec![SearchParam::Name("80s:hits".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_to_query_round_trips() {
+        let params = vec![
+            SearchParam::Name("smooth jazz".to_string()),
+            SearchParam::Tags(vec!["late night".to_string(), "blues".to_string()]),
+            SearchParam::CountryCode("DE".to_string()),
+            SearchParam::Codec("aac".to_string()),
+            SearchParam::BitrateMin(128),
+            SearchParam::BitrateMax(320),
+            SearchParam::Order(Order::RecentTrend),
+            SearchParam::Reverse(true),
+            SearchParam::Limit(50),
+            SearchParam::IsHttps(false),
+        ];
+        let query = to_query(&params);
+        assert_eq!(
+            query,
+            r#""smooth jazz" tag:"late night,blues" countrycode:DE codec:aac bitrate>=128 bitrate<=320 order:recenttrend reverse:true limit:50 https:false"#
+        );
+        assert_eq!(parse_query(&query).unwrap(), params);
+    }
+
+    #[test]
+    fn test_to_query_names_that_look_like_fields() {
+        let params = vec![SearchParam::Name("radio x:y".to_string())];
+        assert_eq!(to_query(&params), r#"name:"radio x:y""#);
+        assert_eq!(parse_query(&to_query(&params)).unwrap(), params);
+    }
+
+    #[test]
+    fn test_hints_for_fields() {
+        assert_eq!(
+            query_hints("jazz co"),
+            vec!["country:", "countrycode:", "codec:"]
+        );
+        assert_eq!(query_hints("jazz "), Vec::<String>::new());
+        assert_eq!(query_hints(""), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_hints_for_values() {
+        assert_eq!(query_hints("order:vo"), vec!["order:votes"]);
+        assert_eq!(query_hints("https:"), vec!["https:true", "https:false"]);
+        assert_eq!(query_hints("https:true"), Vec::<String>::new());
+        assert_eq!(query_hints("codec:mp"), Vec::<String>::new());
+        assert_eq!(query_hints("nope:x"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_hints_are_limited() {
+        assert!(query_hints("order:").len() <= MAX_HINTS);
+    }
+}
@@ -0,0 +1,107 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::{RadioStation, SearchParam};
+use crate::{components::home::HomeView, errors::Error, utils::write_atomic};
+
+/// What was on screen and playing when voxide last ran, to pick up where the user left off.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    /// The version of the file format, see [`Session::VERSION`].
+    pub version: u32,
+    /// The station playing, if any.
+    pub station: Option<RadioStation>,
+    pub volume: f32,
+    /// The parameters of the last search.
+    pub search: Vec<SearchParam>,
+    /// The results of the last search.
+    pub results: Vec<RadioStation>,
+    /// The index of the selected row in the active view.
+    pub selected: Option<usize>,
+    pub view: HomeView,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            station: None,
+            volume: 1.0,
+            search: Vec::new(),
+            results: Vec::new(),
+            selected: None,
+            view: HomeView::default(),
+        }
+    }
+}
+
+impl Session {
+    pub const FILE_NAME: &'static str = "session.json";
+    /// The newest file format this build reads.
+    pub const VERSION: u32 = 1;
+
+    /// Loads the session saved at `path`, `None` if there is none yet.
+    pub fn load(path: &Path) -> Result<Option<Self>, Error> {
+        let mut session = match std::fs::read(path) {
+            Ok(data) => serde_json::from_slice::<Self>(&data)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if session.version > Self::VERSION {
+            return Err(Error::UnsupportedVersion {
+                file: path.display().to_string(),
+                version: session.version,
+                supported: Self::VERSION,
+            });
+        }
+        session.version = Self::VERSION;
+        Ok(Some(session))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        write_atomic(path, &serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_round_trip() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(Session::FILE_NAME);
+        assert_eq!(Session::load(&path)?, None);
+
+        let station = RadioStation::new("http://example.com/stream", "a", "Jazz FM");
+        let session = Session {
+            station: Some(station.clone()),
+            volume: 0.5,
+            search: vec![SearchParam::Name("jazz".to_string())],
+            results: vec![station],
+            selected: Some(0),
+            view: HomeView::Favorites,
+            ..Default::default()
+        };
+        session.save(&path)?;
+        assert_eq!(Session::load(&path)?, Some(session));
+        Ok(())
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("session-version.json");
+        std::fs::write(&path, r#"{"version": 99}"#)?;
+        assert!(matches!(
+            Session::load(&path),
+            Err(Error::UnsupportedVersion { version: 99, .. })
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,308 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+
+use super::{icy::split_title, RadioStation};
+use crate::{
+    errors::Error,
+    utils::{unix_now, write_atomic},
+};
+
+/// A track announced by a station through its stream title.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SongEntry {
+    /// Seconds since the unix epoch.
+    pub timestamp: u64,
+    pub station: String,
+    pub stationuuid: String,
+    /// The stream title as sent by the station.
+    pub raw: String,
+    pub artist: Option<String>,
+    pub title: String,
+}
+
+impl SongEntry {
+    pub fn new(station: &RadioStation, raw: &str) -> Self {
+        let (artist, title) = split_title(raw);
+        Self {
+            timestamp: unix_now(),
+            station: station.name.trim().to_string(),
+            stationuuid: station.stationuuid.clone(),
+            raw: raw.to_string(),
+            artist,
+            title,
+        }
+    }
+
+    /// Whether every word of `query` occurs in the title, the artist or the station name,
+    /// ignoring case.
+    pub fn matches(&self, query: &str) -> bool {
+        let haystack = format!("{} {}", self.raw, self.station).to_lowercase();
+        query
+            .to_lowercase()
+            .split_whitespace()
+            .all(|word| haystack.contains(word))
+    }
+}
+
+/// The formats the song log can be exported as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// Every track heard, newest first.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SongLog {
+    /// The version of the file format, see [`SongLog::VERSION`].
+    version: u32,
+    entries: Vec<SongEntry>,
+    /// Whether entries were recorded since the log was last saved.
+    #[serde(skip)]
+    dirty: bool,
+    /// Whether a save in the background is writing the changes, see [`SongLog::begin_save`].
+    #[serde(skip)]
+    saving: bool,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Default for SongLog {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            entries: Vec::new(),
+            dirty: false,
+            saving: false,
+            path: PathBuf::new(),
+        }
+    }
+}
+
+impl Drop for SongLog {
+    /// Saves the entries no background save wrote yet, e.g. when the program exits.
+    fn drop(&mut self) {
+        if self.dirty {
+            if let Err(e) = self.save() {
+                tracing::warn!(error = ?e, "failed to save the song log");
+            }
+        }
+    }
+}
+
+impl SongLog {
+    pub const FILE_NAME: &'static str = "songs.json";
+    /// The newest file format this build reads.
+    pub const VERSION: u32 = 1;
+
+    /// Loads the log from `path`, starting an empty one if the file does not exist yet.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut log = match std::fs::read(path) {
+            Ok(data) => serde_json::from_slice::<Self>(&data)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e.into()),
+        };
+        if log.version > Self::VERSION {
+            return Err(Error::UnsupportedVersion {
+                file: path.display().to_string(),
+                version: log.version,
+                supported: Self::VERSION,
+            });
+        }
+        log.version = Self::VERSION;
+        log.path = path.to_path_buf();
+        Ok(log)
+    }
+
+    pub fn save(&mut self) -> Result<(), Error> {
+        self.dirty = false;
+        write_atomic(&self.path, &serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+
+    /// Whether the caller should start saving the changes in the background, because there
+    /// are some and no save is writing them yet. It then calls [`SongLog::take_changes`] until
+    /// there are none.
+    pub fn begin_save(&mut self) -> bool {
+        if !self.dirty || self.saving {
+            return false;
+        }
+        self.saving = true;
+        true
+    }
+
+    /// The path and contents to write if entries were recorded since the last call, `None`
+    /// once there are no more changes, which ends the save.
+    pub fn take_changes(&mut self) -> Option<Result<(PathBuf, Vec<u8>), Error>> {
+        if !self.dirty {
+            self.saving = false;
+            return None;
+        }
+        self.dirty = false;
+        match serde_json::to_vec(self) {
+            Ok(data) => Some(Ok((self.path.clone(), data))),
+            Err(e) => {
+                self.saving = false;
+                Some(Err(e.into()))
+            }
+        }
+    }
+
+    /// The entries, newest first.
+    pub fn entries(&self) -> &[SongEntry] {
+        &self.entries
+    }
+
+    /// Adds `entry` as the newest entry, keeping at most `max_entries` (`0` keeps all). The log
+    /// is saved later, see [`SongLog::begin_save`].
+    ///
+    /// Returns `false` if it repeats the newest entry of the same station.
+    pub fn record(&mut self, entry: SongEntry, max_entries: usize) -> bool {
+        let repeated = self
+            .entries
+            .iter()
+            .find(|e| e.stationuuid == entry.stationuuid)
+            .is_some_and(|e| e.raw == entry.raw);
+        if repeated {
+            return false;
+        }
+        self.entries.insert(0, entry);
+        if max_entries > 0 {
+            self.entries.truncate(max_entries);
+        }
+        self.dirty = true;
+        true
+    }
+
+    /// The entries matching `query`, newest first. An empty query matches all.
+    pub fn search<'a>(&'a self, query: &'a str) -> impl Iterator<Item = &'a SongEntry> + 'a {
+        self.entries.iter().filter(move |e| e.matches(query))
+    }
+
+    /// Serializes `entries` in the given format.
+    pub fn export<'a>(
+        entries: impl IntoIterator<Item = &'a SongEntry>,
+        format: ExportFormat,
+    ) -> Result<String, Error> {
+        let entries = entries.into_iter();
+        match format {
+            ExportFormat::Json => Ok(serde_json::to_string_pretty(&entries.collect::<Vec<_>>())?),
+            ExportFormat::Csv => {
+                let mut csv = String::from("time,station,stationuuid,artist,title,raw\n");
+                for e in entries {
+                    let fields = [
+                        DateTime::from_timestamp(e.timestamp as i64, 0)
+                            .unwrap_or_default()
+                            .to_rfc3339_opts(SecondsFormat::Secs, true),
+                        e.station.clone(),
+                        e.stationuuid.clone(),
+                        e.artist.clone().unwrap_or_default(),
+                        e.title.clone(),
+                        e.raw.clone(),
+                    ];
+                    let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+                    csv.push_str(&row.join(","));
+                    csv.push('\n');
+                }
+                Ok(csv)
+            }
+        }
+    }
+}
+
+/// Quotes a CSV field if it contains a separator, a quote or a line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn entry(uuid: &str, raw: &str) -> SongEntry {
+        SongEntry {
+            timestamp: 0,
+            ..SongEntry::new(
+                &RadioStation::new("http://example.com", uuid, "Jazz FM"),
+                raw,
+            )
+        }
+    }
+
+    #[test]
+    fn test_record_skips_repeats() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(SongLog::FILE_NAME);
+        let mut log = SongLog::load(&path)?;
+        assert!(log.record(entry("a", "One - Song"), 0));
+        assert!(!log.record(entry("a", "One - Song"), 0));
+        assert!(log.record(entry("b", "One - Song"), 0));
+        assert!(log.record(entry("a", "Two - Song"), 2));
+
+        // One save writes the changes until there are none
+        assert!(log.begin_save());
+        assert!(!log.begin_save());
+        let (written_to, data) = log.take_changes().expect("changes")?;
+        assert_eq!(written_to, path);
+        write_atomic(&path, &data)?;
+        assert!(log.take_changes().is_none());
+        assert!(!log.begin_save());
+        assert_eq!(SongLog::load(&path)?.entries().len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_search() {
+        let mut log = SongLog::default();
+        log.entries = vec![entry("a", "Miles Davis - So What"), entry("a", "Other")];
+        let found: Vec<&str> = log.search("so DAVIS").map(|e| e.raw.as_str()).collect();
+        assert_eq!(found, vec!["Miles Davis - So What"]);
+        assert_eq!(log.search("jazz fm").count(), 2);
+        assert_eq!(log.search("").count(), 2);
+    }
+
+    #[test]
+    fn test_export_csv() -> Result<(), Error> {
+        let entries = [entry("a", "Earth, Wind & Fire - \"September\"")];
+        let csv = SongLog::export(&entries, ExportFormat::Csv)?;
+        assert_eq!(
+            csv,
+            "time,station,stationuuid,artist,title,raw\n\
+             1970-01-01T00:00:00Z,Jazz FM,a,\"Earth, Wind & Fire\",\"\"\"September\"\"\",\
+             \"Earth, Wind & Fire - \"\"September\"\"\"\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_json() -> Result<(), Error> {
+        let entries = [entry("a", "Artist - Title")];
+        let json = SongLog::export(&entries, ExportFormat::Json)?;
+        let parsed: Vec<SongEntry> = serde_json::from_str(&json)?;
+        assert_eq!(parsed, entries);
+        Ok(())
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("songs-version.json");
+        std::fs::write(&path, r#"{"version": 99, "entries": []}"#)?;
+        assert!(matches!(
+            SongLog::load(&path),
+            Err(Error::UnsupportedVersion { version: 99, .. })
+        ));
+        Ok(())
+    }
+}
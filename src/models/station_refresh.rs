@@ -0,0 +1,227 @@
+//! Bringing saved stations up to date with the directory, which knows when broadcasters move
+//! their streams or stop broadcasting.
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+};
+
+use super::RadioStation;
+
+/// Something that changed about a saved station.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StationChange {
+    /// The stream moved to another url.
+    Url {
+        from: String,
+        to: String,
+    },
+    Codec {
+        from: String,
+        to: String,
+    },
+    Bitrate {
+        from: u32,
+        to: u32,
+    },
+    /// The directory no longer lists the station.
+    Deleted,
+    /// The directory's last check of the stream failed.
+    Failing,
+    /// The directory's last check of the stream succeeded again.
+    Recovered,
+}
+
+impl fmt::Display for StationChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StationChange::Url { from, to } => write!(f, "moved from {from} to {to}"),
+            StationChange::Codec { from, to } => write!(f, "codec {from} → {to}"),
+            StationChange::Bitrate { from, to } => write!(f, "bitrate {from}k → {to}k"),
+            StationChange::Deleted => write!(f, "deleted from the directory"),
+            StationChange::Failing => write!(f, "failing the directory's checks"),
+            StationChange::Recovered => write!(f, "passing the directory's checks again"),
+        }
+    }
+}
+
+/// The directory's current version of saved stations.
+#[derive(Debug, Default, Clone)]
+pub struct StationRefresh {
+    /// The `stationuuid`s that were looked up.
+    requested: HashSet<String>,
+    /// The stations the directory still lists, by `stationuuid`.
+    found: HashMap<String, RadioStation>,
+}
+
+impl StationRefresh {
+    /// `found` are the directory's stations for the `requested` uuids, the ones missing from it
+    /// were deleted.
+    pub fn new(requested: impl IntoIterator<Item = String>, found: Vec<RadioStation>) -> Self {
+        Self {
+            requested: requested.into_iter().collect(),
+            found: found
+                .into_iter()
+                .map(|station| (station.stationuuid.clone(), station))
+                .collect(),
+        }
+    }
+
+    /// How many stations were looked up.
+    pub fn checked(&self) -> usize {
+        self.requested.len()
+    }
+
+    /// Replaces a saved station with the directory's version, or flags it as deleted, and tells
+    /// what changed. Stations that were not looked up stay as they are.
+    pub fn apply(&self, station: &mut RadioStation) -> Vec<StationChange> {
+        if !self.requested.contains(&station.stationuuid) {
+            return Vec::new();
+        }
+        let Some(current) = self.found.get(&station.stationuuid) else {
+            if station.deleted {
+                return Vec::new();
+            }
+            station.deleted = true;
+            return vec![StationChange::Deleted];
+        };
+
+        let mut changes = Vec::new();
+        if current.stream_url() != station.stream_url() {
+            changes.push(StationChange::Url {
+                from: station.stream_url().to_string(),
+                to: current.stream_url().to_string(),
+            });
+        }
+        if !current.codec.eq_ignore_ascii_case(&station.codec) {
+            changes.push(StationChange::Codec {
+                from: station.codec.clone(),
+                to: current.codec.clone(),
+            });
+        }
+        if current.bitrate != station.bitrate {
+            changes.push(StationChange::Bitrate {
+                from: station.bitrate,
+                to: current.bitrate,
+            });
+        }
+        match (station.lastcheckok, current.lastcheckok) {
+            (true, false) => changes.push(StationChange::Failing),
+            (false, true) => changes.push(StationChange::Recovered),
+            _ => (),
+        }
+        // Whether the stream played when checked is not the directory's to tell
+        *station = RadioStation {
+            broken: station.broken.take(),
+            ..current.clone()
+        };
+        changes
+    }
+}
+
+/// What a refresh of the saved stations changed.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RefreshReport {
+    /// How many stations were looked up.
+    pub checked: usize,
+    /// The names of the stations that changed and what changed, by `stationuuid`.
+    pub changes: BTreeMap<String, (String, Vec<StationChange>)>,
+}
+
+impl RefreshReport {
+    /// Records the changes of a station. The same station saved in several places is only
+    /// reported once.
+    pub fn record(&mut self, station: &RadioStation, changes: Vec<StationChange>) {
+        if !changes.is_empty() {
+            self.changes
+                .entry(station.stationuuid.clone())
+                .or_insert_with(|| (station.name.trim().to_string(), changes));
+        }
+    }
+
+    /// One line per change, ordered by station name.
+    pub fn lines(&self) -> Vec<String> {
+        let mut stations: Vec<_> = self.changes.values().collect();
+        stations.sort_by_key(|(name, _)| name.to_lowercase());
+        stations
+            .into_iter()
+            .flat_map(|(name, changes)| changes.iter().map(move |c| format!("{name}: {c}")))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn station(uuid: &str, url: &str, bitrate: u32, lastcheckok: bool) -> RadioStation {
+        RadioStation {
+            codec: "MP3".to_string(),
+            bitrate,
+            lastcheckok,
+            ..RadioStation::new(url.to_string(), uuid.to_string(), format!("Station {uuid}"))
+        }
+    }
+
+    #[test]
+    fn test_apply_updates_and_flags() {
+        let refresh = StationRefresh::new(
+            ["a", "b", "c"].map(str::to_string),
+            vec![
+                station("a", "http://new.example", 192, true),
+                station("b", "http://b.example", 128, false),
+            ],
+        );
+
+        let mut moved = station("a", "http://old.example", 128, true);
+        assert_eq!(
+            refresh.apply(&mut moved),
+            vec![
+                StationChange::Url {
+                    from: "http://old.example".to_string(),
+                    to: "http://new.example".to_string()
+                },
+                StationChange::Bitrate { from: 128, to: 192 }
+            ]
+        );
+        assert_eq!(moved.url, "http://new.example");
+
+        let mut failing = station("b", "http://b.example", 128, true);
+        assert_eq!(refresh.apply(&mut failing), vec![StationChange::Failing]);
+        assert_eq!(refresh.apply(&mut failing), vec![]);
+
+        let mut deleted = station("c", "http://c.example", 128, true);
+        assert_eq!(refresh.apply(&mut deleted), vec![StationChange::Deleted]);
+        assert!(deleted.deleted);
+        assert_eq!(refresh.apply(&mut deleted), vec![]);
+
+        let mut custom = RadioStation::custom("http://d.example", "Custom");
+        assert_eq!(refresh.apply(&mut custom), vec![]);
+    }
+
+    #[test]
+    fn test_report_lists_each_station_once() {
+        let mut report = RefreshReport::default();
+        let zulu = station("z", "http://z.example", 0, true);
+        let alpha = RadioStation::new("http://a.example", "a", "alpha");
+        report.record(&zulu, vec![StationChange::Deleted]);
+        report.record(&zulu, vec![StationChange::Deleted]);
+        report.record(&alpha, vec![]);
+        report.record(
+            &alpha,
+            vec![
+                StationChange::Recovered,
+                StationChange::Bitrate { from: 64, to: 128 },
+            ],
+        );
+        assert_eq!(
+            report.lines(),
+            vec![
+                "alpha: passing the directory's checks again",
+                "alpha: bitrate 64k → 128k",
+                "Station z: deleted from the directory",
+            ]
+        );
+    }
+}
@@ -0,0 +1,307 @@
+//! Checking that the streams of a list of stations play: each is connected to, its first bytes
+//! are read and decoded, and how fast and at what bitrate it answered is measured.
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use futures::StreamExt;
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    stream_probe::{codec_name, content_type, is_playlist_url, playlist_format, resolve_url},
+    RadioStation,
+};
+
+/// How many bytes of each stream are read, a second of audio at 128 kbps.
+pub const CHECK_BYTES: usize = 16 * 1024;
+
+/// What a working stream sent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamStats {
+    /// The content type of the response, without parameters.
+    pub content_type: String,
+    /// The time from sending the request to the first byte of audio.
+    pub ttfb: Duration,
+    /// The bitrate in kbps of the audio read, from how long it plays.
+    pub bitrate: u32,
+}
+
+/// The outcome of checking the stream of a station.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamCheck {
+    pub station: RadioStation,
+    /// What the stream sent, or why it does not play.
+    pub result: Result<StreamStats, String>,
+}
+
+impl StreamCheck {
+    pub fn is_ok(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+impl fmt::Display for StreamCheck {
+    /// A row of [`report_table`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.station.name.trim();
+        match &self.result {
+            Ok(stats) => write!(
+                f,
+                "{:<6} {:>7} {:>9} {:<12} {name}",
+                "ok",
+                format!("{}ms", stats.ttfb.as_millis()),
+                match self.station.bitrate {
+                    0 => format!("{}k", stats.bitrate),
+                    claimed => format!("{}/{claimed}k", stats.bitrate),
+                },
+                stats.content_type,
+            ),
+            Err(reason) => write!(
+                f,
+                "{:<6} {:>7} {:>9} {:<12} {name}: {reason}",
+                "broken", "-", "-", "-"
+            ),
+        }
+    }
+}
+
+/// The checks as a table with a header, the bitrate column showing the measured bitrate and
+/// the one the station claims.
+pub fn report_table(checks: &[StreamCheck]) -> Vec<String> {
+    let header = format!(
+        "{:<6} {:>7} {:>9} {:<12} STATION",
+        "STATUS", "TTFB", "BITRATE", "TYPE"
+    );
+    std::iter::once(header)
+        .chain(checks.iter().map(StreamCheck::to_string))
+        .collect()
+}
+
+/// The outcome of the checks by `stationuuid`, to mark the saved stations with.
+#[derive(Debug, Default, Clone)]
+pub struct BrokenMarks(HashMap<String, Option<String>>);
+
+impl BrokenMarks {
+    pub fn new(checks: &[StreamCheck]) -> Self {
+        Self(
+            checks
+                .iter()
+                .map(|check| {
+                    let broken = check.result.as_ref().err().cloned();
+                    (check.station.stationuuid.clone(), broken)
+                })
+                .collect(),
+        )
+    }
+
+    /// Marks a checked station as broken with the reason, or clears the mark if it played, and
+    /// tells whether that changed it.
+    pub fn apply(&self, station: &mut RadioStation) -> bool {
+        match self.0.get(&station.stationuuid) {
+            Some(broken) if station.broken != *broken => {
+                station.broken = broken.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Checks the streams of `stations`, at most `concurrency` at a time, giving each `timeout` to
+/// send its first [`CHECK_BYTES`]. The checks are in the order of the stations.
+pub async fn check_streams(
+    client: &reqwest::Client,
+    stations: Vec<RadioStation>,
+    concurrency: usize,
+    timeout: Duration,
+) -> Vec<StreamCheck> {
+    futures::stream::iter(stations)
+        .map(|station| check_stream(client, station, timeout))
+        .buffered(concurrency.max(1))
+        .collect()
+        .await
+}
+
+pub async fn check_stream(
+    client: &reqwest::Client,
+    station: RadioStation,
+    timeout: Duration,
+) -> StreamCheck {
+    let result = match tokio::time::timeout(timeout, probe(client, &station)).await {
+        Ok(result) => result,
+        Err(_) => Err(format!("no audio within {}s", timeout.as_secs())),
+    };
+    StreamCheck { station, result }
+}
+
+async fn probe(client: &reqwest::Client, station: &RadioStation) -> Result<StreamStats, String> {
+    let mut url = station.stream_url().trim().to_string();
+    if url.is_empty() {
+        return Err("no stream url".to_string());
+    }
+    if is_playlist_url(&url) {
+        url = resolve_url(client, &url)
+            .await
+            .map_err(|e| e.to_string())?
+            .url_resolved;
+    }
+
+    let started = Instant::now();
+    let mut request = client.get(&url);
+    for (name, value) in &station.headers {
+        request = request.header(name, value);
+    }
+    let mut response = request.send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    let content_type = content_type(response.headers());
+    if playlist_format(&content_type, response.url()).is_some() {
+        return Err(format!("{content_type} is a playlist, not a stream"));
+    }
+    if !is_audio(&content_type) {
+        return Err(format!("{content_type} is not audio"));
+    }
+
+    let mut audio = Vec::with_capacity(CHECK_BYTES);
+    let mut ttfb = None;
+    while audio.len() < CHECK_BYTES {
+        match response.chunk().await.map_err(|e| e.to_string())? {
+            Some(chunk) => {
+                ttfb.get_or_insert_with(|| started.elapsed());
+                audio.extend_from_slice(&chunk);
+            }
+            None => break,
+        }
+    }
+    let Some(ttfb) = ttfb else {
+        return Err("no audio data".to_string());
+    };
+
+    let codec = codec_name(&content_type)
+        .unwrap_or(&content_type)
+        .to_string();
+    let bitrate = tokio::task::spawn_blocking(move || decoded_bitrate(audio))
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("the {codec} audio cannot be decoded"))?;
+    Ok(StreamStats {
+        content_type,
+        ttfb,
+        bitrate,
+    })
+}
+
+/// Whether a content type can be audio. Some servers do not tell and send octets.
+fn is_audio(content_type: &str) -> bool {
+    content_type.is_empty()
+        || content_type.starts_with("audio/")
+        || matches!(content_type, "application/ogg" | "application/octet-stream")
+}
+
+/// Decodes the start of a stream with the decoder the player uses and returns its bitrate in
+/// kbps from how long it plays, or `None` if it does not decode to any audio.
+fn decoded_bitrate(audio: Vec<u8>) -> Option<u32> {
+    let bytes = audio.len();
+    let decoder = rodio::Decoder::new(std::io::Cursor::new(audio)).ok()?;
+    let samples_per_second = decoder.sample_rate() as f64 * decoder.channels() as f64;
+    let samples = decoder.count();
+    if samples == 0 || samples_per_second == 0.0 {
+        return None;
+    }
+    let seconds = samples as f64 / samples_per_second;
+    Some((bytes as f64 * 8.0 / seconds / 1000.0).round() as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::models::test_server::{self, Response};
+
+    /// Silent MPEG-1 Layer III frames at 128 kbps and 44.1 kHz.
+    fn mp3_frames(count: usize) -> Vec<u8> {
+        let mut frame = vec![0; 417];
+        frame[..4].copy_from_slice(&[0xff, 0xfb, 0x90, 0x64]);
+        frame.repeat(count)
+    }
+
+    /// Serves a good stream at `/good`, a playlist at `/list.pls`, noise at `/noise`, a web
+    /// page at `/page`, a stream that never sends audio at `/stall` and 404 elsewhere.
+    async fn serve() -> String {
+        test_server::serve(|base, path| match path {
+            "/good" => Response::ok("audio/mpeg", mp3_frames(60)),
+            "/list.pls" => {
+                Response::ok("audio/x-scpls", format!("[playlist]\nFile1={base}/good\n"))
+            }
+            "/noise" => Response::ok("audio/mpeg", vec![0x55; 8192]),
+            "/page" => Response::ok("text/html", "<html></html>"),
+            "/stall" => Response::stall("audio/mpeg"),
+            _ => Response::not_found(),
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_check_streams() {
+        let base = serve().await;
+        let station = |path: &str| RadioStation {
+            bitrate: 128,
+            ..RadioStation::custom(format!("{base}{path}"), path.to_string())
+        };
+        let checks = check_streams(
+            &reqwest::Client::new(),
+            ["/good", "/list.pls", "/noise", "/page", "/stall", "/gone"]
+                .map(station)
+                .to_vec(),
+            3,
+            Duration::from_secs(1),
+        )
+        .await;
+
+        let results: Vec<_> = checks
+            .iter()
+            .map(|check| match &check.result {
+                // The last frame read is cut short, which skews the bitrate a little
+                Ok(stats) => Ok((
+                    stats.content_type.as_str(),
+                    stats.bitrate.abs_diff(128) <= 4,
+                )),
+                Err(reason) => Err(reason.as_str()),
+            })
+            .collect();
+        assert_eq!(
+            results,
+            vec![
+                Ok(("audio/mpeg", true)),
+                Ok(("audio/mpeg", true)),
+                Err("the MP3 audio cannot be decoded"),
+                Err("text/html is not audio"),
+                Err("no audio within 1s"),
+                Err("HTTP 404 Not Found"),
+            ]
+        );
+
+        let table = report_table(&checks);
+        assert_eq!(table.len(), 7);
+        assert!(table[1].starts_with("ok"));
+        assert!(table[1].ends_with("/128k audio/mpeg   /good"));
+        assert!(table[6].ends_with("/gone: HTTP 404 Not Found"));
+
+        let marks = BrokenMarks::new(&checks);
+        let mut gone = station("/gone");
+        assert!(marks.apply(&mut gone));
+        assert_eq!(gone.broken.as_deref(), Some("HTTP 404 Not Found"));
+        assert!(!marks.apply(&mut gone));
+        let mut good = RadioStation {
+            broken: Some("HTTP 500".to_string()),
+            ..station("/good")
+        };
+        assert!(marks.apply(&mut good));
+        assert_eq!(good.broken, None);
+    }
+}
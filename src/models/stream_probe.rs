@@ -0,0 +1,181 @@
+//! Turns a url typed by the user into a playable station: playlists are followed to the stream
+//! they list, and the stream's headers tell its codec and name.
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+
+use super::{icy::ICY_METADATA_HEADER, PlaylistFormat, RadioStation};
+use crate::errors::Error;
+
+/// How many playlists pointing at playlists are followed.
+const MAX_PLAYLIST_DEPTH: usize = 3;
+/// Playlists larger than this are not streams' playlists.
+const MAX_PLAYLIST_SIZE: usize = 256 * 1024;
+
+/// The playlist format of a response, from its content type or else the extension of its url.
+pub(super) fn playlist_format(content_type: &str, url: &reqwest::Url) -> Option<PlaylistFormat> {
+    match content_type {
+        "audio/x-mpegurl"
+        | "audio/mpegurl"
+        | "application/x-mpegurl"
+        | "application/vnd.apple.mpegurl" => Some(PlaylistFormat::ExtM3u),
+        "audio/x-scpls" | "audio/scpls" => Some(PlaylistFormat::Pls),
+        "application/xspf+xml" => Some(PlaylistFormat::Xspf),
+        _ if content_type.starts_with("audio/") => None,
+        _ => PlaylistFormat::from_path(std::path::Path::new(url.path()))
+            .filter(|format| *format != PlaylistFormat::Json),
+    }
+}
+
+/// Whether a url names a playlist rather than a stream by its extension.
+pub fn is_playlist_url(url: &str) -> bool {
+    reqwest::Url::parse(url.trim()).is_ok_and(|url| playlist_format("", &url).is_some())
+}
+
+/// The codec name used by the directory for an audio content type.
+pub fn codec_name(content_type: &str) -> Option<&'static str> {
+    match content_type {
+        "audio/mpeg" | "audio/mp3" | "audio/mpeg3" => Some("MP3"),
+        "audio/aac" | "audio/aacp" | "audio/x-aac" => Some("AAC+"),
+        "audio/ogg" | "application/ogg" | "audio/vorbis" => Some("OGG"),
+        "audio/opus" => Some("OPUS"),
+        "audio/flac" | "audio/x-flac" => Some("FLAC"),
+        "audio/wav" | "audio/x-wav" => Some("WAV"),
+        _ => None,
+    }
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+/// The content type of a response, lowercase and without parameters.
+pub(super) fn content_type(headers: &HeaderMap) -> String {
+    header(headers, CONTENT_TYPE.as_str())
+        .and_then(|value| value.split(';').next())
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+}
+
+/// Follows `url` through redirects and playlists to a stream and describes it as a custom
+/// station named by the stream, or by the url if it does not tell its name.
+pub async fn resolve_url(client: &reqwest::Client, url: &str) -> Result<RadioStation, Error> {
+    let mut current = reqwest::Url::parse(url.trim())
+        .map_err(|e| Error::Stream(format!("{url} is not a valid url: {e}")))?;
+    for _ in 0..=MAX_PLAYLIST_DEPTH {
+        let response = client
+            .get(current.clone())
+            .header(ICY_METADATA_HEADER, "1")
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(Error::Http(response.status()));
+        }
+        let headers = response.headers().clone();
+        let content_type = content_type(&headers);
+        let final_url = response.url().clone();
+
+        if let Some(format) = playlist_format(&content_type, &final_url) {
+            let body = response.bytes().await?;
+            if body.len() > MAX_PLAYLIST_SIZE {
+                return Err(Error::Stream(format!(
+                    "{final_url} is too large for a playlist"
+                )));
+            }
+            let contents = String::from_utf8_lossy(&body);
+            if contents.contains("#EXT-X-") {
+                return Err(Error::Stream(
+                    "HLS streams are not supported yet".to_string(),
+                ));
+            }
+            let entry = PlaylistFormat::import(&contents, Some(format))?
+                .into_iter()
+                .next()
+                .ok_or_else(|| Error::Stream(format!("the playlist {final_url} is empty")))?;
+            current = final_url
+                .join(&entry.url)
+                .map_err(|e| Error::Stream(format!("the playlist lists an invalid url: {e}")))?;
+            continue;
+        }
+
+        let name = header(&headers, "icy-name").unwrap_or(url.trim());
+        return Ok(RadioStation {
+            url_resolved: final_url.to_string(),
+            codec: codec_name(&content_type)
+                .map(str::to_string)
+                .unwrap_or_else(|| content_type.to_uppercase()),
+            bitrate: header(&headers, "icy-br")
+                .and_then(|br| br.split(',').next()?.trim().parse().ok())
+                .unwrap_or_default(),
+            tags: header(&headers, "icy-genre")
+                .map(|genre| {
+                    genre
+                        .split([',', ' '])
+                        .filter(|tag| !tag.is_empty())
+                        .map(str::to_lowercase)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            homepage: header(&headers, "icy-url").unwrap_or_default().to_string(),
+            ..RadioStation::custom(url.trim(), name)
+        });
+    }
+    Err(Error::Stream(format!(
+        "gave up after following {MAX_PLAYLIST_DEPTH} playlists"
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::models::test_server::{self, Response};
+
+    /// Serves one canned response per path, the body of `/playlist.pls` pointing at `/stream`.
+    async fn serve() -> String {
+        test_server::serve(|base, path| match path {
+            "/redirect" => Response::new("302 Found", "").header("Location", "/playlist.pls"),
+            "/playlist.pls" => Response::ok(
+                "audio/x-scpls",
+                format!("[playlist]\nFile1={base}/stream\nNumberOfEntries=1\n"),
+            ),
+            _ => Response::ok("audio/mpeg", "ID3\0")
+                .header("icy-name", "Test FM")
+                .header("icy-br", "128")
+                .header("icy-genre", "Jazz Blues"),
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_resolve_through_redirect_and_playlist() -> Result<(), Error> {
+        let base = serve().await;
+        let station = resolve_url(&reqwest::Client::new(), &format!("{base}/redirect")).await?;
+        assert_eq!(station.name, "Test FM");
+        assert_eq!(station.url, format!("{base}/redirect"));
+        assert_eq!(station.url_resolved, format!("{base}/stream"));
+        assert_eq!(station.codec, "MP3");
+        assert_eq!(station.bitrate, 128);
+        assert_eq!(station.tags, vec!["jazz", "blues"]);
+        assert!(station.is_custom());
+        Ok(())
+    }
+
+    #[test]
+    fn test_playlist_format() {
+        let url = |u: &str| reqwest::Url::parse(u).unwrap();
+        assert_eq!(
+            playlist_format("audio/x-mpegurl", &url("http://a/listen")),
+            Some(PlaylistFormat::ExtM3u)
+        );
+        assert_eq!(
+            playlist_format("text/plain", &url("http://a/listen.pls")),
+            Some(PlaylistFormat::Pls)
+        );
+        assert_eq!(playlist_format("audio/mpeg", &url("http://a/x.m3u")), None);
+    }
+}
@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+/// A canned HTTP response sent by [`serve`].
+pub struct Response {
+    status: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+    /// Whether only the head is sent, holding the connection open without a body.
+    stall: bool,
+}
+
+impl Response {
+    pub fn new(status: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+            stall: false,
+        }
+    }
+
+    pub fn ok(content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+        Self::new("200 OK", body).header("Content-Type", content_type)
+    }
+
+    pub fn not_found() -> Self {
+        Self::new("404 Not Found", "")
+    }
+
+    /// A response that starts like a stream of `content_type` but never sends any of it.
+    pub fn stall(content_type: &str) -> Self {
+        Self {
+            stall: true,
+            ..Self::ok(content_type, "")
+        }
+    }
+
+    pub fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+}
+
+/// Serves `respond(base, path)` for every request on a local port and returns the base url.
+pub async fn serve(respond: impl Fn(&str, &str) -> Response + Send + Sync + 'static) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let respond = std::sync::Arc::new(respond);
+    let server_base = base.clone();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let respond = respond.clone();
+            let base = server_base.clone();
+            tokio::spawn(async move {
+                let mut request = vec![0; 4096];
+                let n = socket.read(&mut request).await.unwrap();
+                let request = String::from_utf8_lossy(&request[..n]);
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                let response = respond(&base, path);
+                let mut head = format!("HTTP/1.1 {}\r\n", response.status);
+                for (name, value) in &response.headers {
+                    head.push_str(&format!("{name}: {value}\r\n"));
+                }
+                if !response.stall {
+                    head.push_str(&format!("Content-Length: {}\r\n", response.body.len()));
+                }
+                head.push_str("\r\n");
+                let _ = socket.write_all(head.as_bytes()).await;
+                if response.stall {
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                    return;
+                }
+                let _ = socket.write_all(&response.body).await;
+            });
+        }
+    });
+    base
+}
@@ -0,0 +1,89 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::Error,
+    utils::{unix_now, write_atomic},
+};
+
+/// Remembers when each station was last voted for, so votes can be rate limited per station.
+///
+/// Persisted as JSON in the data directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct VoteLog {
+    /// Seconds since the unix epoch of the last vote, by `stationuuid`.
+    votes: HashMap<String, u64>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl VoteLog {
+    pub const FILE_NAME: &'static str = "votes.json";
+
+    /// Loads the log from `path`, starting an empty one if the file does not exist yet.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut log = match std::fs::read(path) {
+            Ok(data) => serde_json::from_slice::<Self>(&data)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e.into()),
+        };
+        log.path = path.to_path_buf();
+        Ok(log)
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        write_atomic(&self.path, &serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Returns how long to wait before the station can be voted for again, `None` if it can be
+    /// voted for now.
+    pub fn cooldown_remaining(&self, stationuuid: &str, cooldown: Duration) -> Option<Duration> {
+        let voted = *self.votes.get(stationuuid)?;
+        let elapsed = unix_now().saturating_sub(voted);
+        cooldown
+            .as_secs()
+            .checked_sub(elapsed)
+            .filter(|remaining| *remaining > 0)
+            .map(Duration::from_secs)
+    }
+
+    /// Records a vote for the station and forgets votes older than `cooldown`.
+    pub fn record(&mut self, stationuuid: &str, cooldown: Duration) -> Result<(), Error> {
+        let now = unix_now();
+        self.votes
+            .retain(|_, voted| now.saturating_sub(*voted) < cooldown.as_secs());
+        self.votes.insert(stationuuid.to_string(), now);
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_cooldown() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(VoteLog::FILE_NAME);
+        let cooldown = Duration::from_secs(600);
+
+        let mut log = VoteLog::load(&path)?;
+        assert_eq!(log.cooldown_remaining("a", cooldown), None);
+        log.record("a", cooldown)?;
+        assert!(log.cooldown_remaining("a", cooldown).is_some());
+        assert_eq!(log.cooldown_remaining("b", cooldown), None);
+        assert_eq!(log.cooldown_remaining("a", Duration::ZERO), None);
+
+        let reloaded = VoteLog::load(&path)?;
+        assert!(reloaded.cooldown_remaining("a", cooldown).is_some());
+        Ok(())
+    }
+}
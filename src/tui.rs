@@ -57,7 +57,10 @@ use ratatui::{
     backend::CrosstermBackend,
     crossterm::{
         cursor,
-        event::{Event as CrosstermEvent, KeyEvent, KeyEventKind, MouseEvent},
+        event::{
+            DisableBracketedPaste, EnableBracketedPaste, Event as CrosstermEvent, KeyEvent,
+            KeyEventKind, MouseEvent,
+        },
         terminal::{EnterAlternateScreen, LeaveAlternateScreen},
     },
 };
@@ -286,7 +289,13 @@ impl Tui {
 
     pub fn enter(&mut self) -> Result<()> {
         crossterm::terminal::enable_raw_mode()?;
-        crossterm::execute!(std::io::stderr(), EnterAlternateScreen, cursor::Hide)?;
+        // Bracketed paste delivers pasted text as one `Event::Paste` instead of key presses
+        crossterm::execute!(
+            std::io::stderr(),
+            EnterAlternateScreen,
+            EnableBracketedPaste,
+            cursor::Hide
+        )?;
         self.start();
         Ok(())
     }
@@ -295,7 +304,12 @@ impl Tui {
         self.stop()?;
         if crossterm::terminal::is_raw_mode_enabled()? {
             self.flush()?;
-            crossterm::execute!(std::io::stderr(), LeaveAlternateScreen, cursor::Show)?;
+            crossterm::execute!(
+                std::io::stderr(),
+                DisableBracketedPaste,
+                LeaveAlternateScreen,
+                cursor::Show
+            )?;
             crossterm::terminal::disable_raw_mode()?;
         }
         Ok(())
@@ -1,4 +1,7 @@
-use std::path::PathBuf;
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
 
 use color_eyre::eyre::Result;
 use directories::ProjectDirs;
@@ -9,6 +12,8 @@ use tracing_subscriber::{
     self, prelude::__tracing_subscriber_SubscriberExt, util::SubscriberInitExt, Layer,
 };
 
+use crate::errors::Error;
+
 const VERSION_MESSAGE: &str = concat!(
     env!("CARGO_PKG_VERSION"),
     "-",
@@ -109,6 +114,89 @@ pub fn get_config_dir() -> PathBuf {
     directory
 }
 
+/// Writes `contents` to `path` so that readers see either the old or the new file, never a
+/// partially written one.
+///
+/// The data is written to a temporary file next to `path`, synced to disk and then renamed over
+/// `path`. Missing parent directories are created.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(format!(".{}.tmp", std::process::id()));
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let result = (|| {
+        let mut file = std::fs::File::create(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, path)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// Loads a data file with `load`. If it cannot be parsed it is renamed to
+/// `<name>.<unix time>.broken`, so it is kept rather than overwritten by the next save, and `load`
+/// starts over without it. Files set aside earlier are never replaced. Returns the parse error
+/// that set the file aside.
+///
+/// Other errors, like a file written by a newer version or one that cannot be read at all, are
+/// returned as they are and leave the file in place.
+pub fn load_or_set_aside<T: Default>(
+    path: &Path,
+    load: impl Fn(&Path) -> Result<T, Error>,
+) -> Result<(T, Option<Error>), Error> {
+    match load(path) {
+        Ok(value) => Ok((value, None)),
+        Err(e @ Error::Json(_)) => {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            let stamp = unix_now();
+            let mut broken = path.with_file_name(format!("{file_name}.{stamp}.broken"));
+            for n in 1.. {
+                if !broken.exists() {
+                    break;
+                }
+                broken = path.with_file_name(format!("{file_name}.{stamp}-{n}.broken"));
+            }
+            std::fs::rename(path, broken)?;
+            Ok((load(path).unwrap_or_default(), Some(e)))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Seconds since the unix epoch.
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Formats a duration in seconds compactly, e.g. `42s`, `5m 3s` or `2h 10m`.
+pub fn format_duration(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{secs}s"),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// Formats how long ago the unix timestamp `then` was, relative to `now`, e.g. `3h ago`.
+pub fn format_age(then: u64, now: u64) -> String {
+    let secs = now.saturating_sub(then);
+    match secs {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
 pub fn initialize_logging() -> Result<()> {
     let directory = get_data_dir();
     std::fs::create_dir_all(directory.clone())?;
@@ -177,3 +265,77 @@ Config directory: {config_dir_path}
 Data directory: {data_dir_path}"
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(42), "42s");
+        assert_eq!(format_duration(303), "5m 3s");
+        assert_eq!(format_duration(7800), "2h 10m");
+    }
+
+    #[test]
+    fn test_load_or_set_aside() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("aside.json");
+        let load = |path: &Path| match std::fs::read(path) {
+            Ok(data) if data.starts_with(b"[99") => Err(Error::UnsupportedVersion {
+                file: path.display().to_string(),
+                version: 99,
+                supported: 1,
+            }),
+            Ok(data) => Ok(serde_json::from_slice::<Vec<u32>>(&data)?),
+            Err(_) => Ok(Vec::new()),
+        };
+        let broken = || -> std::io::Result<Vec<String>> {
+            let mut names = Vec::new();
+            for entry in std::fs::read_dir(dir.path())? {
+                let name = entry?.file_name().to_string_lossy().to_string();
+                if name.ends_with(".broken") {
+                    names.push(std::fs::read_to_string(dir.path().join(name))?);
+                }
+            }
+            names.sort();
+            Ok(names)
+        };
+
+        std::fs::write(&path, "[1, 2")?;
+        let (numbers, error) = load_or_set_aside(&path, load)?;
+        assert_eq!(numbers, Vec::<u32>::new());
+        assert!(matches!(error, Some(Error::Json(_))));
+        assert_eq!(broken()?, vec!["[1, 2"]);
+        assert!(!path.exists());
+
+        std::fs::write(&path, "[3")?;
+        assert!(load_or_set_aside(&path, load)?.1.is_some());
+        assert_eq!(broken()?, vec!["[1, 2", "[3"]);
+
+        std::fs::write(&path, "[1, 2]")?;
+        let (numbers, error) = load_or_set_aside(&path, load)?;
+        assert_eq!(numbers, vec![1, 2]);
+        assert!(error.is_none());
+
+        std::fs::write(&path, "[99]")?;
+        assert!(matches!(
+            load_or_set_aside(&path, load),
+            Err(Error::UnsupportedVersion { version: 99, .. })
+        ));
+        assert!(path.exists());
+        assert_eq!(broken()?, vec!["[1, 2", "[3"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(100, 130), "just now");
+        assert_eq!(format_age(0, 600), "10m ago");
+        assert_eq!(format_age(0, 3 * 3600), "3h ago");
+        assert_eq!(format_age(0, 2 * 86400 + 5), "2d ago");
+        assert_eq!(format_age(200, 100), "just now");
+    }
+}